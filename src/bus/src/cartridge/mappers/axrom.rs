@@ -0,0 +1,81 @@
+use crate::cartridge::Mirroring;
+use crate::cartridge::mappers::{Mapped, Mapper};
+use crate::inesformat::format::PRG_ROM_SIZE_FACTOR;
+use crate::inesformat::header::Header;
+
+/// Mapper 7 (AxROM)
+///
+/// Writes to $8000-$FFFF select a 32KB PRG-ROM bank with bits 0-2 and which nametable is used
+/// for every screen with bit 4.
+pub struct Axrom {
+    // in 32KB units
+    prg_rom_banks: usize,
+    register: u8,
+}
+
+impl Axrom {
+    pub fn new(header: &Header) -> Self {
+        Axrom {
+            prg_rom_banks: (header.prg_rom_banks() / 2).max(1),
+            register: 0,
+        }
+    }
+}
+
+impl Mapper for Axrom {
+    fn cpu_peek(&self, addr: u16) -> Mapped {
+        match addr {
+            0x8000..=0xFFFF => {
+                let bank = (self.register & 0x07) as usize % self.prg_rom_banks;
+                Mapped::PrgRom(bank * 2 * PRG_ROM_SIZE_FACTOR + (addr & 0x7FFF) as usize)
+            }
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) -> Mapped {
+        if addr >= 0x8000 {
+            self.register = value;
+        }
+        Mapped::Unmapped
+    }
+
+    fn ppu_peek(&self, addr: u16) -> Mapped {
+        Mapped::Chr((addr & 0x1FFF) as usize)
+    }
+
+    fn ppu_write(&mut self, addr: u16, _value: u8) -> Mapped {
+        Mapped::Chr((addr & 0x1FFF) as usize)
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        if self.register & 0x10 == 0x10 {
+            Some(Mirroring::SingleScreenUpper)
+        } else {
+            Some(Mirroring::SingleScreenLower)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{banked_rom_image, bus_with_rom};
+
+    #[test]
+    fn test_prg_banking_and_mirroring() {
+        let mut bus = bus_with_rom(&banked_rom_image(7, 8, 1));
+        assert_eq!(bus.cpu_peek_u8(0x8000), 0);
+        assert_eq!(bus.cpu_peek_u8(0xE000), 3);
+        assert_eq!(bus.cartridge().mirroring(), Mirroring::SingleScreenLower);
+
+        bus.cpu_write_u8(0x8000, 0x12);
+        assert_eq!(bus.cpu_peek_u8(0x8000), 8);
+        assert_eq!(bus.cpu_peek_u8(0xE000), 11);
+        assert_eq!(bus.cartridge().mirroring(), Mirroring::SingleScreenUpper);
+
+        // banks wrap around
+        bus.cpu_write_u8(0x8000, 0x07);
+        assert_eq!(bus.cpu_peek_u8(0x8000), 12);
+    }
+}
@@ -0,0 +1,86 @@
+use crate::cartridge::mappers::{Mapped, Mapper};
+use crate::inesformat::format::CHR_ROM_SIZE_FACTOR;
+use crate::inesformat::header::Header;
+
+/// Mapper 3 (CNROM)
+///
+/// PRG-ROM is laid out the same way as NROM and writes to $8000-$FFFF select the 8KB CHR bank.
+pub struct Cnrom {
+    prg_rom_mask: u16,
+    // in 8KB units
+    chr_banks: usize,
+    chr_bank: u8,
+}
+
+impl Cnrom {
+    pub fn new(header: &Header) -> Self {
+        Cnrom {
+            prg_rom_mask: if header.prg_rom_banks() > 1 { 0x7FFF } else { 0x3FFF },
+            chr_banks: header.chr_banks(),
+            chr_bank: 0,
+        }
+    }
+
+    fn chr_offset(&self, addr: u16) -> usize {
+        (self.chr_bank as usize % self.chr_banks) * CHR_ROM_SIZE_FACTOR + (addr & 0x1FFF) as usize
+    }
+}
+
+impl Mapper for Cnrom {
+    fn cpu_peek(&self, addr: u16) -> Mapped {
+        match addr {
+            0x8000..=0xFFFF => Mapped::PrgRom((addr & self.prg_rom_mask) as usize),
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) -> Mapped {
+        if addr >= 0x8000 {
+            self.chr_bank = value;
+        }
+        Mapped::Unmapped
+    }
+
+    fn ppu_peek(&self, addr: u16) -> Mapped {
+        Mapped::Chr(self.chr_offset(addr))
+    }
+
+    fn ppu_write(&mut self, addr: u16, _value: u8) -> Mapped {
+        Mapped::Chr(self.chr_offset(addr))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test::{banked_rom_image, bus_with_rom};
+
+    #[test]
+    fn test_chr_banking() {
+        let mut bus = bus_with_rom(&banked_rom_image(3, 2, 4));
+        assert_eq!(bus.cpu_peek_u8(0x8000), 0);
+        assert_eq!(bus.cpu_peek_u8(0xC000), 2);
+        assert_eq!(bus.cartridge().ppu_peek(0x0000), 0);
+
+        bus.cpu_write_u8(0x8000, 0x02);
+        assert_eq!(bus.cartridge().ppu_peek(0x0000), 16);
+        assert_eq!(bus.cartridge().ppu_peek(0x1FFF), 23);
+
+        // banks wrap around
+        bus.cpu_write_u8(0x8000, 0x07);
+        assert_eq!(bus.cartridge().ppu_peek(0x0000), 24);
+    }
+
+    #[test]
+    fn test_bus_conflicts() {
+        let mut rom = banked_rom_image(3, 2, 4);
+        rom[10] |= 0x20;
+        let mut bus = bus_with_rom(&rom);
+
+        // $8000 holds 0x00 (bank index) which swallows every bit written to it
+        bus.cpu_write_u8(0x8000, 0x03);
+        assert_eq!(bus.cartridge().ppu_peek(0x0000), 0);
+        // 0xEA & 0x03
+        bus.cpu_write_u8(0x8001, 0x03);
+        assert_eq!(bus.cartridge().ppu_peek(0x0000), 16);
+    }
+}
@@ -0,0 +1,228 @@
+use crate::cartridge::Mirroring;
+use crate::cartridge::mappers::{Mapped, Mapper};
+use crate::inesformat::format::{CHR_ROM_SIZE_FACTOR, PRG_ROM_SIZE_FACTOR};
+use crate::inesformat::header::Header;
+
+/// Mapper 69 (Sunsoft FME-7 and 5A/5B)
+///
+/// Everything goes through a command register at $8000 and a parameter register at $A000:
+/// eight 1KB CHR banks, four 8KB PRG banks (the one at $6000 can be either ROM or RAM, the last
+/// bank is fixed), mirroring and a 16-bit IRQ counter decremented every CPU cycle. The 5B's
+/// sound chip at $C000/$E000 isn't emulated, see Mapper::audio_output.
+pub struct Fme7 {
+    // in 8KB units
+    prg_rom_banks: usize,
+    prg_ram_size: usize,
+    // in 1KB units
+    chr_banks: usize,
+    command: u8,
+    chr_registers: [u8; 8],
+    // $6000, $8000, $A000 and $C000
+    prg_registers: [u8; 4],
+    mirroring: Mirroring,
+    irq_enabled: bool,
+    irq_counter_enabled: bool,
+    irq_counter: u16,
+    irq_pending: bool,
+}
+
+impl Fme7 {
+    pub fn new(header: &Header) -> Self {
+        Fme7 {
+            prg_rom_banks: header.prg_rom_banks() * 2,
+            prg_ram_size: header.prg_ram_size(),
+            chr_banks: header.chr_banks() * CHR_ROM_SIZE_FACTOR / 0x400,
+            command: 0,
+            chr_registers: [0; 8],
+            prg_registers: [0; 4],
+            mirroring: Mirroring::Vertical,
+            irq_enabled: false,
+            irq_counter_enabled: false,
+            irq_counter: 0,
+            irq_pending: false,
+        }
+    }
+
+    fn prg_rom_offset(&self, bank: usize, addr: u16) -> usize {
+        (bank % self.prg_rom_banks) * (PRG_ROM_SIZE_FACTOR / 2) + (addr & 0x1FFF) as usize
+    }
+
+    // $6000-$7FFF: bit 6 selects RAM and bit 7 enables it
+    fn low_bank(&self, addr: u16) -> Mapped {
+        let register = self.prg_registers[0];
+        let bank = (register & 0x3F) as usize;
+        match register & 0xC0 {
+            0xC0 if self.prg_ram_size > 0 => {
+                Mapped::PrgRam((bank * 0x2000 + (addr & 0x1FFF) as usize) % self.prg_ram_size)
+            }
+            0x00 | 0x80 => Mapped::PrgRom(self.prg_rom_offset(bank, addr)),
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn write_parameter(&mut self, value: u8) {
+        match self.command {
+            0x0..=0x7 => self.chr_registers[self.command as usize] = value,
+            0x8 => self.prg_registers[0] = value,
+            0x9..=0xB => self.prg_registers[(self.command - 0x8) as usize] = value & 0x3F,
+            0xC => {
+                self.mirroring = match value & 0x3 {
+                    0 => Mirroring::Vertical,
+                    1 => Mirroring::Horizontal,
+                    2 => Mirroring::SingleScreenLower,
+                    _ => Mirroring::SingleScreenUpper,
+                };
+            }
+            0xD => {
+                self.irq_enabled = value & 0x1 == 0x1;
+                self.irq_counter_enabled = value & 0x80 == 0x80;
+                self.irq_pending = false;
+            }
+            0xE => self.irq_counter = (self.irq_counter & 0xFF00) | value as u16,
+            _ => self.irq_counter = (self.irq_counter & 0x00FF) | (value as u16) << 8,
+        }
+    }
+
+    fn chr_offset(&self, addr: u16) -> usize {
+        let bank = self.chr_registers[(addr >> 10) as usize & 0x7] as usize;
+        (bank % self.chr_banks) * 0x400 + (addr & 0x3FF) as usize
+    }
+}
+
+impl Mapper for Fme7 {
+    fn cpu_peek(&self, addr: u16) -> Mapped {
+        match addr {
+            0x6000..=0x7FFF => self.low_bank(addr),
+            0x8000..=0xDFFF => {
+                let register = self.prg_registers[((addr - 0x6000) >> 13) as usize];
+                Mapped::PrgRom(self.prg_rom_offset(register as usize, addr))
+            }
+            0xE000..=0xFFFF => Mapped::PrgRom(self.prg_rom_offset(self.prg_rom_banks - 1, addr)),
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) -> Mapped {
+        match addr {
+            0x6000..=0x7FFF => {
+                if let mapped @ Mapped::PrgRam(_) = self.low_bank(addr) {
+                    return mapped;
+                }
+            }
+            0x8000..=0x9FFF => self.command = value & 0x0F,
+            0xA000..=0xBFFF => self.write_parameter(value),
+            // 5B audio isn't emulated, its registers are ignored for now
+            _ => {}
+        }
+        Mapped::Unmapped
+    }
+
+    fn ppu_peek(&self, addr: u16) -> Mapped {
+        Mapped::Chr(self.chr_offset(addr))
+    }
+
+    fn ppu_write(&mut self, addr: u16, _value: u8) -> Mapped {
+        Mapped::Chr(self.chr_offset(addr))
+    }
+
+    fn cpu_clock(&mut self) {
+        if !self.irq_counter_enabled {
+            return;
+        }
+        self.irq_counter = self.irq_counter.wrapping_sub(1);
+        if self.irq_counter == 0xFFFF && self.irq_enabled {
+            self.irq_pending = true;
+        }
+    }
+
+    fn irq_asserted(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(self.mirroring)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Bus;
+    use crate::test::{banked_rom_image, bus_with_rom};
+
+    fn init() -> Bus {
+        bus_with_rom(&banked_rom_image(69, 8, 8))
+    }
+
+    fn command(bus: &mut Bus, command: u8, value: u8) {
+        bus.cpu_write_u8(0x8000, command);
+        bus.cpu_write_u8(0xA000, value);
+    }
+
+    #[test]
+    fn test_prg_banking() {
+        let mut bus = init();
+        command(&mut bus, 0x9, 3);
+        command(&mut bus, 0xA, 5);
+        command(&mut bus, 0xB, 9);
+        assert_eq!(bus.cpu_peek_u8(0x8000), 3);
+        assert_eq!(bus.cpu_peek_u8(0xA000), 5);
+        assert_eq!(bus.cpu_peek_u8(0xC000), 9);
+        assert_eq!(bus.cpu_peek_u8(0xE000), 15);
+
+        // ROM at $6000
+        command(&mut bus, 0x8, 7);
+        assert_eq!(bus.cpu_peek_u8(0x6000), 7);
+        bus.cpu_write_u8(0x6000, 0x42);
+        assert_eq!(bus.cpu_peek_u8(0x6000), 7);
+
+        // RAM selected but disabled
+        command(&mut bus, 0x8, 0x40);
+        assert_eq!(bus.cpu_read_u8(0x6000, false), bus.open_bus);
+
+        // RAM enabled
+        command(&mut bus, 0x8, 0xC0);
+        bus.cpu_write_u8(0x6000, 0x42);
+        assert_eq!(bus.cpu_peek_u8(0x6000), 0x42);
+    }
+
+    #[test]
+    fn test_chr_banking_and_mirroring() {
+        let mut bus = init();
+        for slot in 0..8 {
+            command(&mut bus, slot, 40 + slot);
+        }
+        let banks: Vec<u8> = (0..8).map(|slot| bus.cartridge().ppu_peek(slot * 0x400)).collect();
+        assert_eq!(banks, vec![40, 41, 42, 43, 44, 45, 46, 47]);
+
+        command(&mut bus, 0xC, 1);
+        assert_eq!(bus.cartridge().mirroring(), Mirroring::Horizontal);
+        command(&mut bus, 0xC, 2);
+        assert_eq!(bus.cartridge().mirroring(), Mirroring::SingleScreenLower);
+    }
+
+    #[test]
+    fn test_irq_counter() {
+        let mut bus = init();
+        command(&mut bus, 0xE, 0x01);
+        command(&mut bus, 0xF, 0x00);
+        command(&mut bus, 0xD, 0x81);
+
+        // 1 -> 0 -> 0xFFFF
+        bus.cartridge.borrow_mut().cpu_clock();
+        assert!(!bus.cartridge().irq_asserted());
+        bus.cartridge.borrow_mut().cpu_clock();
+        assert!(bus.cartridge().irq_asserted());
+
+        // any write to the control acknowledges it
+        command(&mut bus, 0xD, 0x81);
+        assert!(!bus.cartridge().irq_asserted());
+
+        // counting without raising the IRQ
+        command(&mut bus, 0xD, 0x80);
+        for _ in 0..0x10000 {
+            bus.cartridge.borrow_mut().cpu_clock();
+        }
+        assert!(!bus.cartridge().irq_asserted());
+    }
+}
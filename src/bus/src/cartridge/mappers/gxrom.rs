@@ -0,0 +1,79 @@
+use crate::cartridge::mappers::{Mapped, Mapper};
+use crate::inesformat::format::{CHR_ROM_SIZE_FACTOR, PRG_ROM_SIZE_FACTOR};
+use crate::inesformat::header::Header;
+
+/// Mapper 66 (GxROM)
+///
+/// A single latch at $8000-$FFFF selects both the 32KB PRG-ROM bank (bits 4-5) and the 8KB
+/// CHR-ROM bank (bits 0-1).
+pub struct Gxrom {
+    // in 32KB units
+    prg_rom_banks: usize,
+    // in 8KB units
+    chr_banks: usize,
+    register: u8,
+}
+
+impl Gxrom {
+    pub fn new(header: &Header) -> Self {
+        Gxrom {
+            prg_rom_banks: (header.prg_rom_banks() / 2).max(1),
+            chr_banks: header.chr_banks(),
+            register: 0,
+        }
+    }
+
+    fn chr_offset(&self, addr: u16) -> usize {
+        let bank = (self.register & 0x03) as usize % self.chr_banks;
+        bank * CHR_ROM_SIZE_FACTOR + (addr & 0x1FFF) as usize
+    }
+}
+
+impl Mapper for Gxrom {
+    fn cpu_peek(&self, addr: u16) -> Mapped {
+        match addr {
+            0x8000..=0xFFFF => {
+                let bank = ((self.register >> 4) & 0x03) as usize % self.prg_rom_banks;
+                Mapped::PrgRom(bank * 2 * PRG_ROM_SIZE_FACTOR + (addr & 0x7FFF) as usize)
+            }
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) -> Mapped {
+        if addr >= 0x8000 {
+            self.register = value;
+        }
+        Mapped::Unmapped
+    }
+
+    fn ppu_peek(&self, addr: u16) -> Mapped {
+        Mapped::Chr(self.chr_offset(addr))
+    }
+
+    fn ppu_write(&mut self, addr: u16, _value: u8) -> Mapped {
+        Mapped::Chr(self.chr_offset(addr))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test::{banked_rom_image, bus_with_rom};
+
+    #[test]
+    fn test_combined_latch() {
+        let mut bus = bus_with_rom(&banked_rom_image(66, 8, 4));
+        assert_eq!(bus.cpu_peek_u8(0x8000), 0);
+        assert_eq!(bus.cartridge().ppu_peek(0x0000), 0);
+
+        bus.cpu_write_u8(0x8000, 0x21);
+        assert_eq!(bus.cpu_peek_u8(0x8000), 8);
+        assert_eq!(bus.cpu_peek_u8(0xE000), 11);
+        assert_eq!(bus.cartridge().ppu_peek(0x0000), 8);
+        assert_eq!(bus.cartridge().ppu_peek(0x1C00), 15);
+
+        bus.cpu_write_u8(0x8000, 0x13);
+        assert_eq!(bus.cpu_peek_u8(0x8000), 4);
+        assert_eq!(bus.cartridge().ppu_peek(0x0000), 24);
+    }
+}
@@ -0,0 +1,398 @@
+use crate::cartridge::Mirroring;
+use crate::cartridge::mappers::{Mapped, Mapper};
+use crate::inesformat::format::{CHR_ROM_SIZE_FACTOR, PRG_RAM_SIZE_FACTOR, PRG_ROM_SIZE_FACTOR};
+use crate::inesformat::header::Header;
+
+/// Mapper 1 (MMC1)
+///
+/// Registers are loaded serially: each write to $8000-$FFFF shifts bit 0 into a 5-bit shift
+/// register and the 5th write copies it into the register selected by address bits 13-14.
+/// Writing a value with bit 7 set resets the shift register instead.
+///
+/// SxROM boards with lots of PRG-ROM or PRG-RAM reuse the upper CHR bank bits:
+///   - SNROM: bit 4 disables PRG-RAM
+///   - SOROM: bit 3 selects the 8KB PRG-RAM bank (16KB total)
+///   - SUROM: bit 4 selects the 256KB PRG-ROM half (512KB total)
+///   - SXROM: same as SUROM plus bits 2-3 select the 8KB PRG-RAM bank (32KB total)
+pub struct Mmc1 {
+    // in 16KB units
+    prg_rom_banks: usize,
+    chr_size: usize,
+    prg_ram_size: usize,
+    shift_register: u8,
+    shift_count: u8,
+    // 43210
+    // |||++- Mirroring (0: one-screen lower, 1: one-screen upper, 2: vertical, 3: horizontal)
+    // |++--- PRG-ROM bank mode
+    // +----- CHR-ROM bank mode (0: 8KB, 1: two 4KB banks)
+    control: u8,
+    chr_bank_0: u8,
+    chr_bank_1: u8,
+    prg_bank: u8,
+    // writes on consecutive CPU cycles (the dummy write of RMW instructions) are ignored
+    cpu_cycle: u64,
+    last_write_cycle: Option<u64>,
+    // SxROM boards decode the extra bits from whichever CHR bank register the PPU used last
+    chr_a12: bool,
+}
+
+impl Mmc1 {
+    pub fn new(header: &Header) -> Self {
+        Mmc1 {
+            prg_rom_banks: header.prg_rom_banks(),
+            chr_size: header.chr_banks() * CHR_ROM_SIZE_FACTOR,
+            prg_ram_size: header.prg_ram_size(),
+            shift_register: 0,
+            shift_count: 0,
+            control: 0x0C,
+            chr_bank_0: 0,
+            chr_bank_1: 0,
+            prg_bank: 0,
+            cpu_cycle: 0,
+            last_write_cycle: None,
+            chr_a12: false,
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x8000..=0x9FFF => self.control = value,
+            0xA000..=0xBFFF => self.chr_bank_0 = value,
+            0xC000..=0xDFFF => self.chr_bank_1 = value,
+            _ => self.prg_bank = value,
+        }
+    }
+
+    /// CHR bank register currently driving the SxROM extra lines
+    fn sxrom_bits(&self) -> u8 {
+        if self.control & 0x10 == 0x10 && self.chr_a12 {
+            self.chr_bank_1
+        } else {
+            self.chr_bank_0
+        }
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        // SNROM uses the CHR bank bit 4 as an extra chip enable
+        let snrom_disabled = self.chr_size == CHR_ROM_SIZE_FACTOR
+            && self.prg_rom_banks <= 16
+            && self.prg_ram_size == PRG_RAM_SIZE_FACTOR
+            && self.sxrom_bits() & 0x10 == 0x10;
+        // NES 2.0 dumps can tell there's no PRG-RAM at all
+        self.prg_ram_size > 0 && self.prg_bank & 0x10 == 0 && !snrom_disabled
+    }
+
+    fn prg_ram_offset(&self, addr: u16) -> usize {
+        let bank = match self.prg_ram_size / PRG_RAM_SIZE_FACTOR {
+            // SOROM
+            2 => (self.sxrom_bits() >> 3) & 0x1,
+            // SXROM
+            4 => (self.sxrom_bits() >> 2) & 0x3,
+            _ => 0,
+        } as usize;
+        (bank * PRG_RAM_SIZE_FACTOR + (addr & 0x1FFF) as usize) % self.prg_ram_size
+    }
+
+    fn prg_rom_offset(&self, addr: u16) -> usize {
+        // SUROM/SXROM: 512KB boards pick the 256KB half with CHR bank bit 4
+        let outer = if self.prg_rom_banks > 16 {
+            (self.sxrom_bits() & 0x10) as usize
+        } else {
+            0
+        };
+        let bank = (self.prg_bank & 0x0F) as usize;
+
+        let bank = match (self.control >> 2) & 0x3 {
+            // 32KB mode ignores the lowest bank bit
+            0 | 1 => (bank & !0x1) | ((addr >> 14) & 0x1) as usize,
+            // first bank fixed at $8000, $C000 switchable
+            2 => if addr < 0xC000 { 0 } else { bank },
+            // $8000 switchable, last bank fixed at $C000
+            _ => if addr < 0xC000 { bank } else { 0x0F },
+        };
+
+        (((outer | bank) % self.prg_rom_banks) * PRG_ROM_SIZE_FACTOR) + (addr & 0x3FFF) as usize
+    }
+
+    fn chr_offset(&self, addr: u16) -> usize {
+        let offset = if self.control & 0x10 == 0 {
+            // 8KB mode ignores the lowest bank bit
+            (self.chr_bank_0 & 0x1E) as usize * 0x1000 + (addr & 0x1FFF) as usize
+        } else {
+            let bank = if addr < 0x1000 { self.chr_bank_0 } else { self.chr_bank_1 };
+            (bank & 0x1F) as usize * 0x1000 + (addr & 0x0FFF) as usize
+        };
+        offset % self.chr_size
+    }
+}
+
+impl Mapper for Mmc1 {
+    fn cpu_peek(&self, addr: u16) -> Mapped {
+        match addr {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => Mapped::PrgRam(self.prg_ram_offset(addr)),
+            0x8000..=0xFFFF => Mapped::PrgRom(self.prg_rom_offset(addr)),
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) -> Mapped {
+        match addr {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => Mapped::PrgRam(self.prg_ram_offset(addr)),
+            0x8000..=0xFFFF => {
+                let consecutive = matches!(self.last_write_cycle, Some(cycle) if self.cpu_cycle - cycle <= 1);
+                self.last_write_cycle = Some(self.cpu_cycle);
+                if consecutive {
+                    return Mapped::Unmapped;
+                }
+
+                if value & 0x80 == 0x80 {
+                    self.shift_register = 0;
+                    self.shift_count = 0;
+                    self.control |= 0x0C;
+                    return Mapped::Unmapped;
+                }
+
+                self.shift_register = (self.shift_register >> 1) | ((value & 0x1) << 4);
+                self.shift_count += 1;
+                if self.shift_count == 5 {
+                    self.write_register(addr, self.shift_register);
+                    self.shift_register = 0;
+                    self.shift_count = 0;
+                }
+                Mapped::Unmapped
+            }
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> Mapped {
+        self.chr_a12 = addr & 0x1000 == 0x1000;
+        self.ppu_peek(addr)
+    }
+
+    fn ppu_peek(&self, addr: u16) -> Mapped {
+        Mapped::Chr(self.chr_offset(addr))
+    }
+
+    fn ppu_write(&mut self, addr: u16, _value: u8) -> Mapped {
+        Mapped::Chr(self.chr_offset(addr))
+    }
+
+    fn cpu_clock(&mut self) {
+        self.cpu_cycle += 1;
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(match self.control & 0x3 {
+            0 => Mirroring::SingleScreenLower,
+            1 => Mirroring::SingleScreenUpper,
+            2 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Bus;
+    use crate::test::{banked_rom_image, bus_with_rom};
+
+    // time enough between writes for them not to be seen as consecutive
+    fn idle(bus: &mut Bus) {
+        for _ in 0..6 {
+            bus.clock();
+        }
+    }
+
+    fn serial_write(bus: &mut Bus, addr: u16, value: u8) {
+        for bit in 0..5 {
+            bus.cpu_write_u8(addr, (value >> bit) & 0x1);
+            idle(bus);
+        }
+    }
+
+    fn init(prg_rom_size: u8, chr_rom_size: u8, flags_8: u8) -> Bus {
+        let mut rom = banked_rom_image(1, prg_rom_size, chr_rom_size);
+        rom[8] = flags_8;
+        bus_with_rom(&rom)
+    }
+
+    // first byte of every 8KB of PRG-ROM holds its index
+    fn prg_bank_at(bus: &Bus, addr: u16) -> u8 {
+        bus.cpu_peek_u8(addr) / 2
+    }
+
+    #[test]
+    fn test_power_on_state() {
+        let bus = init(8, 2, 0);
+        assert_eq!(prg_bank_at(&bus, 0x8000), 0);
+        assert_eq!(prg_bank_at(&bus, 0xC000), 7);
+        assert_eq!(bus.cartridge().mirroring(), Mirroring::SingleScreenLower);
+    }
+
+    #[test]
+    fn test_shift_register() {
+        let mut bus = init(8, 2, 0);
+        serial_write(&mut bus, 0xE000, 0x03);
+        assert_eq!(prg_bank_at(&bus, 0x8000), 3);
+
+        // reset halfway through
+        bus.cpu_write_u8(0xE000, 0x1);
+        idle(&mut bus);
+        bus.cpu_write_u8(0xE000, 0x1);
+        idle(&mut bus);
+        bus.cpu_write_u8(0xE000, 0x80);
+        idle(&mut bus);
+        serial_write(&mut bus, 0xE000, 0x05);
+        assert_eq!(prg_bank_at(&bus, 0x8000), 5);
+    }
+
+    #[test]
+    fn test_reset_sets_prg_mode_3() {
+        let mut bus = init(8, 2, 0);
+        serial_write(&mut bus, 0x8000, 0x00);
+        serial_write(&mut bus, 0xE000, 0x03);
+        assert_eq!(prg_bank_at(&bus, 0x8000), 2);
+
+        bus.cpu_write_u8(0x8000, 0x80);
+        assert_eq!(prg_bank_at(&bus, 0x8000), 3);
+        assert_eq!(prg_bank_at(&bus, 0xC000), 7);
+    }
+
+    #[test]
+    fn test_consecutive_writes_are_ignored() {
+        let mut bus = init(8, 2, 0);
+        // second write of each pair is what a RMW instruction would do
+        for bit in 0..5 {
+            bus.cpu_write_u8(0xE000, (0x06 >> bit) & 0x1);
+            bus.cpu_write_u8(0xE000, 0x1);
+            idle(&mut bus);
+        }
+        assert_eq!(prg_bank_at(&bus, 0x8000), 6);
+
+        // a RMW on a register that reads 0xFF resets (first write) and ignores the second
+        bus.cpu_write_u8(0x8000, 0xFF);
+        bus.cpu_write_u8(0x8000, 0x00);
+        idle(&mut bus);
+        serial_write(&mut bus, 0xE000, 0x02);
+        assert_eq!(prg_bank_at(&bus, 0x8000), 2);
+    }
+
+    #[test]
+    fn test_prg_modes() {
+        let mut bus = init(8, 2, 0);
+        serial_write(&mut bus, 0xE000, 0x05);
+
+        // 32KB: lowest bit ignored
+        serial_write(&mut bus, 0x8000, 0x00);
+        assert_eq!(prg_bank_at(&bus, 0x8000), 4);
+        assert_eq!(prg_bank_at(&bus, 0xC000), 5);
+        serial_write(&mut bus, 0x8000, 0x04);
+        assert_eq!(prg_bank_at(&bus, 0x8000), 4);
+        assert_eq!(prg_bank_at(&bus, 0xC000), 5);
+
+        // fixed first bank
+        serial_write(&mut bus, 0x8000, 0x08);
+        assert_eq!(prg_bank_at(&bus, 0x8000), 0);
+        assert_eq!(prg_bank_at(&bus, 0xC000), 5);
+
+        // fixed last bank
+        serial_write(&mut bus, 0x8000, 0x0C);
+        assert_eq!(prg_bank_at(&bus, 0x8000), 5);
+        assert_eq!(prg_bank_at(&bus, 0xC000), 7);
+    }
+
+    #[test]
+    fn test_chr_modes() {
+        let mut bus = init(8, 4, 0);
+        serial_write(&mut bus, 0xA000, 0x03);
+        serial_write(&mut bus, 0xC000, 0x05);
+
+        // 8KB: lowest bit ignored, CHR is filled with the index of each 1KB
+        serial_write(&mut bus, 0x8000, 0x0C);
+        assert_eq!(bus.cartridge().ppu_peek(0x0000), 8);
+        assert_eq!(bus.cartridge().ppu_peek(0x1000), 12);
+
+        // 4KB
+        serial_write(&mut bus, 0x8000, 0x1C);
+        assert_eq!(bus.cartridge().ppu_peek(0x0000), 12);
+        assert_eq!(bus.cartridge().ppu_peek(0x1000), 20);
+    }
+
+    #[test]
+    fn test_mirroring() {
+        let mut bus = init(8, 2, 0);
+        for (control, mirroring) in [
+            (0x0C, Mirroring::SingleScreenLower),
+            (0x0D, Mirroring::SingleScreenUpper),
+            (0x0E, Mirroring::Vertical),
+            (0x0F, Mirroring::Horizontal),
+        ] {
+            serial_write(&mut bus, 0x8000, control);
+            assert_eq!(bus.cartridge().mirroring(), mirroring);
+        }
+    }
+
+    #[test]
+    fn test_prg_ram_disable() {
+        let mut bus = init(8, 2, 0);
+        bus.cpu_write_u8(0x6000, 0x42);
+        assert_eq!(bus.cpu_read_u8(0x6000, false), 0x42);
+
+        serial_write(&mut bus, 0xE000, 0x10);
+        bus.cpu_write_u8(0x0000, 0x00);
+        assert_eq!(bus.cpu_read_u8(0x6000, false), 0x00);
+
+        serial_write(&mut bus, 0xE000, 0x00);
+        assert_eq!(bus.cpu_read_u8(0x6000, false), 0x42);
+    }
+
+    #[test]
+    fn test_snrom_prg_ram_disable() {
+        let mut bus = init(8, 1, 0);
+        bus.cpu_write_u8(0x6000, 0x42);
+        serial_write(&mut bus, 0xA000, 0x10);
+        bus.cpu_write_u8(0x0000, 0x00);
+        assert_eq!(bus.cpu_read_u8(0x6000, false), 0x00);
+    }
+
+    #[test]
+    fn test_sorom_prg_ram_banks() {
+        let mut bus = init(8, 1, 2);
+        bus.cpu_write_u8(0x6000, 0x11);
+        serial_write(&mut bus, 0xA000, 0x08);
+        assert_eq!(bus.cpu_read_u8(0x6000, false), 0x00);
+        bus.cpu_write_u8(0x6000, 0x22);
+
+        serial_write(&mut bus, 0xA000, 0x00);
+        assert_eq!(bus.cpu_read_u8(0x6000, false), 0x11);
+    }
+
+    #[test]
+    fn test_surom_prg_rom_halves() {
+        let mut bus = init(32, 1, 0);
+        serial_write(&mut bus, 0xE000, 0x02);
+        assert_eq!(prg_bank_at(&bus, 0x8000), 2);
+        assert_eq!(prg_bank_at(&bus, 0xC000), 15);
+
+        serial_write(&mut bus, 0xA000, 0x10);
+        assert_eq!(prg_bank_at(&bus, 0x8000), 18);
+        assert_eq!(prg_bank_at(&bus, 0xC000), 31);
+    }
+
+    #[test]
+    fn test_sxrom_prg_ram_banks() {
+        let mut bus = init(32, 1, 4);
+        for bank in 0..4 {
+            serial_write(&mut bus, 0xA000, 0x10 | (bank << 2));
+            bus.cpu_write_u8(0x6000, bank);
+            // PRG-RAM stays enabled even though bit 4 is set
+            assert_eq!(bus.cpu_read_u8(0x6000, false), bank);
+        }
+
+        serial_write(&mut bus, 0xA000, 0x04);
+        assert_eq!(bus.cpu_read_u8(0x6000, false), 0x01);
+        assert_eq!(prg_bank_at(&bus, 0xC000), 15);
+    }
+}
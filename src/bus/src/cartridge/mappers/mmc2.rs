@@ -0,0 +1,232 @@
+use crate::cartridge::Mirroring;
+use crate::cartridge::mappers::{Mapped, Mapper};
+use crate::inesformat::format::{CHR_ROM_SIZE_FACTOR, PRG_ROM_SIZE_FACTOR};
+use crate::inesformat::header::Header;
+
+/// CHR latches shared by MMC2 and MMC4.
+///
+/// Each 4KB pattern table has two bank registers, one used after the PPU fetched tile $FD and
+/// one used after it fetched tile $FE. The latch flips once the fetch of the high bitplane of
+/// the first row ($xFD8/$xFE8) is done, so the fetch itself still comes from the old bank.
+pub(super) struct ChrLatches {
+    // [pattern table][0: $FD, 1: $FE]
+    banks: [[u8; 2]; 2],
+    latches: [usize; 2],
+    // MMC2 only reacts to the exact addresses on the first pattern table
+    exact_first_table: bool,
+    // in 4KB units
+    chr_banks: usize,
+}
+
+impl ChrLatches {
+    pub(super) fn new(header: &Header, exact_first_table: bool) -> Self {
+        ChrLatches {
+            banks: [[0; 2]; 2],
+            latches: [1, 1],
+            exact_first_table,
+            chr_banks: header.chr_banks() * CHR_ROM_SIZE_FACTOR / 0x1000,
+        }
+    }
+
+    pub(super) fn set_bank(&mut self, table: usize, tile: usize, value: u8) {
+        self.banks[table][tile] = value & 0x1F;
+    }
+
+    pub(super) fn chr_offset(&self, addr: u16) -> usize {
+        let table = ((addr >> 12) & 0x1) as usize;
+        let bank = self.banks[table][self.latches[table]] as usize % self.chr_banks;
+        bank * 0x1000 + (addr & 0x0FFF) as usize
+    }
+
+    pub(super) fn observe(&mut self, addr: u16) {
+        let addr = addr & 0x1FFF;
+        let table = (addr >> 12) as usize;
+        let tile_addr = if table == 0 && self.exact_first_table { addr } else { addr & 0xFFF8 };
+        match tile_addr & 0x0FFF {
+            0x0FD8 => self.latches[table] = 0,
+            0x0FE8 => self.latches[table] = 1,
+            _ => {}
+        }
+    }
+}
+
+/// Mapper 9 (MMC2)
+///
+/// 8KB switchable PRG-ROM bank at $8000 and the last three 8KB banks fixed from $A000. CHR is
+/// split into two 4KB pattern tables, each bank switched by the PPU fetching tiles $FD/$FE.
+pub struct Mmc2 {
+    // in 8KB units
+    prg_rom_banks: usize,
+    prg_bank: u8,
+    latches: ChrLatches,
+    mirroring: Mirroring,
+}
+
+impl Mmc2 {
+    pub fn new(header: &Header) -> Self {
+        Mmc2 {
+            prg_rom_banks: header.prg_rom_banks() * 2,
+            prg_bank: 0,
+            latches: ChrLatches::new(header, true),
+            mirroring: Mirroring::Vertical,
+        }
+    }
+}
+
+/// Register writes are the same on MMC2 and MMC4, only what $A000 does differs
+pub(super) fn write_latch_registers(addr: u16, value: u8, latches: &mut ChrLatches, mirroring: &mut Mirroring) {
+    match addr {
+        0xB000..=0xBFFF => latches.set_bank(0, 0, value),
+        0xC000..=0xCFFF => latches.set_bank(0, 1, value),
+        0xD000..=0xDFFF => latches.set_bank(1, 0, value),
+        0xE000..=0xEFFF => latches.set_bank(1, 1, value),
+        0xF000..=0xFFFF => {
+            *mirroring = if value & 0x1 == 0 { Mirroring::Vertical } else { Mirroring::Horizontal };
+        }
+        _ => {}
+    }
+}
+
+impl Mapper for Mmc2 {
+    fn cpu_peek(&self, addr: u16) -> Mapped {
+        let bank = match addr {
+            0x8000..=0x9FFF => self.prg_bank as usize,
+            // counted from the end, boards smaller than 32KB just wrap around
+            0xA000..=0xFFFF => self.prg_rom_banks * 4 - 4 + ((addr - 0x8000) >> 13) as usize,
+            _ => return Mapped::Unmapped,
+        };
+        Mapped::PrgRom((bank % self.prg_rom_banks) * (PRG_ROM_SIZE_FACTOR / 2) + (addr & 0x1FFF) as usize)
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) -> Mapped {
+        match addr {
+            0xA000..=0xAFFF => self.prg_bank = value & 0x0F,
+            _ => write_latch_registers(addr, value, &mut self.latches, &mut self.mirroring),
+        }
+        Mapped::Unmapped
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> Mapped {
+        let mapped = self.ppu_peek(addr);
+        self.latches.observe(addr);
+        mapped
+    }
+
+    fn ppu_peek(&self, addr: u16) -> Mapped {
+        Mapped::Chr(self.latches.chr_offset(addr))
+    }
+
+    fn ppu_write(&mut self, addr: u16, _value: u8) -> Mapped {
+        Mapped::Chr(self.latches.chr_offset(addr))
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(self.mirroring)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Bus;
+    use crate::test::{banked_rom_image, bus_with_rom};
+
+    fn init() -> Bus {
+        let mut bus = bus_with_rom(&banked_rom_image(9, 8, 8));
+        // $FD / $FE banks for both pattern tables (4KB units)
+        bus.cpu_write_u8(0xB000, 2);
+        bus.cpu_write_u8(0xC000, 4);
+        bus.cpu_write_u8(0xD000, 6);
+        bus.cpu_write_u8(0xE000, 8);
+        bus
+    }
+
+    // CHR is filled with the index of each 1KB, so 4KB banks start at multiples of 4
+    fn chr_bank_at(bus: &Bus, addr: u16) -> u8 {
+        bus.cartridge().ppu_peek(addr) / 4
+    }
+
+    /// What the PPU reads when fetching the first row of a tile: low then high bitplane
+    fn fetch_tile(bus: &mut Bus, table: u16, tile: u16) {
+        bus.ppu.ppu_read_u8(table | (tile << 4), false);
+        bus.ppu.ppu_read_u8(table | (tile << 4) | 0x8, false);
+    }
+
+    #[test]
+    fn test_prg_banking() {
+        let mut bus = init();
+        assert_eq!(bus.cpu_peek_u8(0x8000), 0);
+        assert_eq!(bus.cpu_peek_u8(0xA000), 13);
+        assert_eq!(bus.cpu_peek_u8(0xC000), 14);
+        assert_eq!(bus.cpu_peek_u8(0xE000), 15);
+
+        bus.cpu_write_u8(0xA000, 5);
+        assert_eq!(bus.cpu_peek_u8(0x8000), 5);
+    }
+
+    #[test]
+    fn test_small_prg_rom() {
+        let mut bus = bus_with_rom(&banked_rom_image(9, 1, 1));
+        bus.reset();
+        assert_eq!(bus.cpu().pc, 0xFF00);
+        assert_eq!(bus.cpu_peek_u8(0xA000), 1);
+        assert_eq!(bus.cpu_peek_u8(0xC000), 0);
+        assert_eq!(bus.cpu_peek_u8(0xE000), 1);
+    }
+
+    #[test]
+    fn test_latches_flip_after_fetching_fd_fe() {
+        let mut bus = init();
+        assert_eq!(chr_bank_at(&bus, 0x0000), 4);
+        assert_eq!(chr_bank_at(&bus, 0x1000), 8);
+
+        // the high bitplane fetch of tile $FD still comes from the $FE bank
+        bus.ppu.ppu_read_u8(0x0FD0, false);
+        assert_eq!(bus.ppu.ppu_read_u8(0x0FD8, false), 19);
+        assert_eq!(chr_bank_at(&bus, 0x0000), 2);
+        assert_eq!(chr_bank_at(&bus, 0x1000), 8);
+
+        fetch_tile(&mut bus, 0x1000, 0xFD);
+        assert_eq!(chr_bank_at(&bus, 0x1000), 6);
+
+        fetch_tile(&mut bus, 0x0000, 0xFE);
+        fetch_tile(&mut bus, 0x1000, 0xFE);
+        assert_eq!(chr_bank_at(&bus, 0x0000), 4);
+        assert_eq!(chr_bank_at(&bus, 0x1000), 8);
+
+        // other tiles leave the latches alone
+        fetch_tile(&mut bus, 0x0000, 0xFC);
+        fetch_tile(&mut bus, 0x1000, 0xFF);
+        assert_eq!(chr_bank_at(&bus, 0x0000), 4);
+        assert_eq!(chr_bank_at(&bus, 0x1000), 8);
+    }
+
+    #[test]
+    fn test_mmc2_first_table_only_reacts_to_first_row() {
+        let mut bus = init();
+        // the other rows of the tile (sprites, fine Y scroll) on the first pattern table
+        bus.ppu.ppu_read_u8(0x0FD9, false);
+        bus.ppu.ppu_read_u8(0x0FDF, false);
+        assert_eq!(chr_bank_at(&bus, 0x0000), 4);
+
+        // but any row flips the second one
+        bus.ppu.ppu_read_u8(0x1FDB, false);
+        assert_eq!(chr_bank_at(&bus, 0x1000), 6);
+    }
+
+    #[test]
+    fn test_peek_does_not_flip_latches() {
+        let mut bus = init();
+        bus.ppu.ppu_read_u8(0x0FD8, true);
+        assert_eq!(chr_bank_at(&bus, 0x0000), 4);
+    }
+
+    #[test]
+    fn test_mirroring() {
+        let mut bus = init();
+        bus.cpu_write_u8(0xF000, 1);
+        assert_eq!(bus.cartridge().mirroring(), Mirroring::Horizontal);
+        bus.cpu_write_u8(0xF000, 0);
+        assert_eq!(bus.cartridge().mirroring(), Mirroring::Vertical);
+    }
+}
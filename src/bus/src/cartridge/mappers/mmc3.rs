@@ -0,0 +1,520 @@
+use crate::cartridge::Mirroring;
+use crate::cartridge::mappers::{Mapped, Mapper};
+use crate::inesformat::format::{CHR_ROM_SIZE_FACTOR, PRG_ROM_SIZE_FACTOR};
+use crate::inesformat::header::Header;
+
+// PPU A12 has to stay low for this many CPU cycles before a rising edge clocks the IRQ counter,
+// which filters out the short dips happening in between sprite pattern fetches
+const A12_FILTER_CYCLES: u8 = 3;
+
+/// Chip revisions only differ in how the IRQ counter behaves when it gets reloaded with 0
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Mmc3Revision {
+    /// IRQ fires only when the counter is decremented to 0 or reloaded through $C001
+    A,
+    /// IRQ fires every time the counter is clocked and ends up being 0
+    BC,
+}
+
+impl Mmc3Revision {
+    /// NES 2.0 submapper 4 marks the few boards that still carry the original MMC3A, anything
+    /// else (including every iNES 1.0 file) gets the far more common B/C behaviour
+    pub fn from_header(header: &Header) -> Self {
+        match header.submapper() {
+            4 => Mmc3Revision::A,
+            _ => Mmc3Revision::BC,
+        }
+    }
+}
+
+/// Mapper 4 (MMC3)
+///
+/// Eight bank registers (R0-R7) written through $8000/$8001 select two 2KB and four 1KB CHR banks
+/// plus two 8KB PRG-ROM banks, and the PRG/CHR halves can be swapped around. The scanline
+/// counter is clocked by rising edges of PPU A12 which, with the usual setup of background
+/// patterns at $0000 and sprites at $1000, happens once per scanline.
+pub struct Mmc3 {
+    revision: Mmc3Revision,
+    // in 8KB units
+    prg_rom_banks: usize,
+    // in 1KB units
+    chr_banks: usize,
+    four_screen: bool,
+    bank_select: u8,
+    registers: [u8; 8],
+    mirroring: Option<Mirroring>,
+    prg_ram_enabled: bool,
+    prg_ram_write_protected: bool,
+    irq_latch: u8,
+    irq_counter: u8,
+    irq_reload: bool,
+    irq_enabled: bool,
+    irq_pending: bool,
+    a12: bool,
+    a12_low_cycles: u8,
+}
+
+impl Mmc3 {
+    pub fn new(header: &Header, revision: Mmc3Revision) -> Self {
+        Mmc3 {
+            revision,
+            prg_rom_banks: header.prg_rom_banks() * 2,
+            chr_banks: header.chr_banks() * CHR_ROM_SIZE_FACTOR / 0x400,
+            four_screen: header.flags_6 & 0x8 == 0x8,
+            bank_select: 0,
+            registers: [0; 8],
+            mirroring: None,
+            prg_ram_enabled: true,
+            prg_ram_write_protected: false,
+            irq_latch: 0,
+            irq_counter: 0,
+            irq_reload: false,
+            irq_enabled: false,
+            irq_pending: false,
+            a12: false,
+            a12_low_cycles: A12_FILTER_CYCLES,
+        }
+    }
+
+    fn prg_rom_offset(&self, addr: u16) -> usize {
+        let second_last = self.prg_rom_banks - 2;
+        let inverted = self.bank_select & 0x40 == 0x40;
+        let bank = match (addr >> 13) & 0x3 {
+            0 if inverted => second_last,
+            0 => self.registers[6] as usize,
+            1 => self.registers[7] as usize,
+            2 if inverted => self.registers[6] as usize,
+            2 => second_last,
+            _ => self.prg_rom_banks - 1,
+        };
+        (bank % self.prg_rom_banks) * (PRG_ROM_SIZE_FACTOR / 2) + (addr & 0x1FFF) as usize
+    }
+
+    fn chr_offset(&self, addr: u16) -> usize {
+        // CHR inversion swaps the 2KB banks with the 1KB ones
+        let addr = if self.bank_select & 0x80 == 0x80 { addr ^ 0x1000 } else { addr };
+        let bank = match addr >> 10 {
+            0 => self.registers[0] & 0xFE,
+            1 => self.registers[0] | 0x01,
+            2 => self.registers[1] & 0xFE,
+            3 => self.registers[1] | 0x01,
+            slot => self.registers[(slot - 2) as usize],
+        } as usize;
+        (bank % self.chr_banks) * 0x400 + (addr & 0x3FF) as usize
+    }
+
+    fn clock_irq_counter(&mut self) {
+        let previous = self.irq_counter;
+        let reload = self.irq_reload;
+        if self.irq_counter == 0 || self.irq_reload {
+            self.irq_counter = self.irq_latch;
+        } else {
+            self.irq_counter -= 1;
+        }
+        self.irq_reload = false;
+
+        let fire = match self.revision {
+            Mmc3Revision::A => self.irq_counter == 0 && (previous != 0 || reload),
+            Mmc3Revision::BC => self.irq_counter == 0,
+        };
+        if fire && self.irq_enabled {
+            self.irq_pending = true;
+        }
+    }
+
+    fn observe_a12(&mut self, addr: u16) {
+        let a12 = addr & 0x1000 == 0x1000;
+        if a12 && !self.a12 && self.a12_low_cycles >= A12_FILTER_CYCLES {
+            self.clock_irq_counter();
+        }
+        if !a12 && self.a12 {
+            self.a12_low_cycles = 0;
+        }
+        self.a12 = a12;
+    }
+}
+
+impl Mapper for Mmc3 {
+    fn cpu_peek(&self, addr: u16) -> Mapped {
+        match addr {
+            0x6000..=0x7FFF if self.prg_ram_enabled => Mapped::PrgRam((addr & 0x1FFF) as usize),
+            0x8000..=0xFFFF => Mapped::PrgRom(self.prg_rom_offset(addr)),
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) -> Mapped {
+        let even = addr & 0x1 == 0;
+        match addr {
+            0x6000..=0x7FFF if self.prg_ram_enabled && !self.prg_ram_write_protected => {
+                return Mapped::PrgRam((addr & 0x1FFF) as usize);
+            }
+            0x8000..=0x9FFF if even => self.bank_select = value,
+            0x8000..=0x9FFF => self.registers[(self.bank_select & 0x7) as usize] = value,
+            0xA000..=0xBFFF if even => {
+                self.mirroring = Some(if value & 0x1 == 0 { Mirroring::Vertical } else { Mirroring::Horizontal });
+            }
+            0xA000..=0xBFFF => {
+                self.prg_ram_enabled = value & 0x80 == 0x80;
+                self.prg_ram_write_protected = value & 0x40 == 0x40;
+            }
+            0xC000..=0xDFFF if even => self.irq_latch = value,
+            0xC000..=0xDFFF => {
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            }
+            0xE000..=0xFFFF if even => {
+                self.irq_enabled = false;
+                self.irq_pending = false;
+            }
+            0xE000..=0xFFFF => self.irq_enabled = true,
+            _ => {}
+        }
+        Mapped::Unmapped
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> Mapped {
+        self.observe_a12(addr);
+        self.ppu_peek(addr)
+    }
+
+    fn ppu_peek(&self, addr: u16) -> Mapped {
+        Mapped::Chr(self.chr_offset(addr))
+    }
+
+    fn ppu_write(&mut self, addr: u16, _value: u8) -> Mapped {
+        self.observe_a12(addr);
+        Mapped::Chr(self.chr_offset(addr))
+    }
+
+    fn cpu_clock(&mut self) {
+        if !self.a12 {
+            self.a12_low_cycles = self.a12_low_cycles.saturating_add(1);
+        }
+    }
+
+    fn irq_asserted(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        if self.four_screen {
+            return None;
+        }
+        self.mirroring
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Bus;
+    use crate::interrupt::InterruptSource;
+    use crate::test::{banked_rom_image, bus_with_rom, write_rom};
+
+    fn init() -> Bus {
+        bus_with_rom(&banked_rom_image(4, 8, 8))
+    }
+
+    fn init_with_revision(revision: Mmc3Revision) -> Bus {
+        let bus = init();
+        let header = Header::from(&banked_rom_image(4, 8, 8)).unwrap();
+        bus.cartridge.borrow_mut().mapper = Some(Box::new(Mmc3::new(&header, revision)));
+        bus
+    }
+
+    fn write_register(bus: &mut Bus, register: u8, value: u8) {
+        bus.cpu_write_u8(0x8000, register);
+        bus.cpu_write_u8(0x8001, value);
+    }
+
+    /// Mimics what the PPU does on every rendered scanline: background fetches from $0xxx for a
+    /// while, followed by sprite fetches from $1xxx
+    fn scanline(bus: &mut Bus) {
+        bus.ppu.ppu_read_u8(0x0000, false);
+        for _ in 0..A12_FILTER_CYCLES {
+            bus.cartridge.borrow_mut().cpu_clock();
+        }
+        bus.ppu.ppu_read_u8(0x1000, false);
+    }
+
+    fn irq(bus: &Bus) -> bool {
+        bus.cartridge().irq_asserted()
+    }
+
+    #[test]
+    fn test_prg_banking() {
+        let mut bus = init();
+        write_register(&mut bus, 6, 3);
+        write_register(&mut bus, 7, 5);
+        assert_eq!(bus.cpu_peek_u8(0x8000), 3);
+        assert_eq!(bus.cpu_peek_u8(0xA000), 5);
+        assert_eq!(bus.cpu_peek_u8(0xC000), 14);
+        assert_eq!(bus.cpu_peek_u8(0xE000), 15);
+
+        // PRG mode 1 swaps $8000 and $C000
+        bus.cpu_write_u8(0x8000, 0x40);
+        assert_eq!(bus.cpu_peek_u8(0x8000), 14);
+        assert_eq!(bus.cpu_peek_u8(0xA000), 5);
+        assert_eq!(bus.cpu_peek_u8(0xC000), 3);
+        assert_eq!(bus.cpu_peek_u8(0xE000), 15);
+    }
+
+    #[test]
+    fn test_chr_banking() {
+        let mut bus = init();
+        for (register, bank) in [(0, 8), (1, 11), (2, 20), (3, 21), (4, 22), (5, 63)] {
+            write_register(&mut bus, register, bank);
+        }
+
+        let banks = |bus: &Bus| -> Vec<u8> {
+            (0..8).map(|slot| bus.cartridge().ppu_peek(slot * 0x400)).collect()
+        };
+        // 2KB banks ignore the lowest bit
+        assert_eq!(banks(&bus), vec![8, 9, 10, 11, 20, 21, 22, 63]);
+
+        // CHR inversion
+        bus.cpu_write_u8(0x8000, 0x80);
+        assert_eq!(banks(&bus), vec![20, 21, 22, 63, 8, 9, 10, 11]);
+    }
+
+    #[test]
+    fn test_mirroring() {
+        let mut bus = init();
+        bus.cpu_write_u8(0xA000, 0x0);
+        assert_eq!(bus.cartridge().mirroring(), Mirroring::Vertical);
+        bus.cpu_write_u8(0xA000, 0x1);
+        assert_eq!(bus.cartridge().mirroring(), Mirroring::Horizontal);
+    }
+
+    #[test]
+    fn test_prg_ram_protect() {
+        let mut bus = init();
+        bus.cpu_write_u8(0xA001, 0x80);
+        bus.cpu_write_u8(0x6000, 0x42);
+        assert_eq!(bus.cpu_peek_u8(0x6000), 0x42);
+
+        // write protected
+        bus.cpu_write_u8(0xA001, 0xC0);
+        bus.cpu_write_u8(0x6000, 0x24);
+        assert_eq!(bus.cpu_peek_u8(0x6000), 0x42);
+
+        // disabled
+        bus.cpu_write_u8(0xA001, 0x00);
+        assert_eq!(bus.cpu_read_u8(0x6000, false), 0x00);
+    }
+
+    #[test]
+    fn test_irq_counter_clocking() {
+        let mut bus = init();
+        bus.cpu_write_u8(0xC000, 2);
+        bus.cpu_write_u8(0xC001, 0);
+        bus.cpu_write_u8(0xE001, 0);
+
+        // reload, then 2 decrements
+        scanline(&mut bus);
+        assert!(!irq(&bus));
+        scanline(&mut bus);
+        assert!(!irq(&bus));
+        scanline(&mut bus);
+        assert!(irq(&bus));
+
+        // acknowledging keeps it enabled only after $E001 is written again
+        bus.cpu_write_u8(0xE000, 0);
+        assert!(!irq(&bus));
+        bus.cpu_write_u8(0xE001, 0);
+        // counter reloads (2) and counts down again
+        scanline(&mut bus);
+        scanline(&mut bus);
+        assert!(!irq(&bus));
+        scanline(&mut bus);
+        assert!(irq(&bus));
+    }
+
+    #[test]
+    fn test_latch_change_only_takes_effect_on_reload() {
+        let mut bus = init();
+        bus.cpu_write_u8(0xC000, 5);
+        bus.cpu_write_u8(0xC001, 0);
+        bus.cpu_write_u8(0xE001, 0);
+        scanline(&mut bus);
+
+        // counter keeps counting down from 5
+        bus.cpu_write_u8(0xC000, 1);
+        for _ in 0..4 {
+            scanline(&mut bus);
+            assert!(!irq(&bus));
+        }
+        scanline(&mut bus);
+        assert!(irq(&bus));
+    }
+
+    #[test]
+    fn test_disabled_irq_does_not_fire() {
+        let mut bus = init();
+        bus.cpu_write_u8(0xC000, 1);
+        bus.cpu_write_u8(0xC001, 0);
+        bus.cpu_write_u8(0xE000, 0);
+        for _ in 0..4 {
+            scanline(&mut bus);
+            assert!(!irq(&bus));
+        }
+    }
+
+    #[test]
+    fn test_a12_clocking() {
+        let mut bus = init();
+        bus.cpu_write_u8(0xC000, 1);
+        bus.cpu_write_u8(0xC001, 0);
+        bus.cpu_write_u8(0xE001, 0);
+        scanline(&mut bus);
+
+        // A12 staying high doesn't clock the counter
+        bus.ppu.ppu_read_u8(0x1000, false);
+        bus.ppu.ppu_read_u8(0x1FFF, false);
+        assert!(!irq(&bus));
+
+        // short low periods get filtered out
+        bus.ppu.ppu_read_u8(0x0000, false);
+        bus.cartridge.borrow_mut().cpu_clock();
+        bus.ppu.ppu_read_u8(0x1000, false);
+        assert!(!irq(&bus));
+
+        // PPU writes put their address on the bus too
+        bus.ppu.ppu_write_u8(0x0000, 0);
+        for _ in 0..A12_FILTER_CYCLES {
+            bus.cartridge.borrow_mut().cpu_clock();
+        }
+        bus.ppu.ppu_write_u8(0x1000, 0);
+        assert!(irq(&bus));
+    }
+
+    #[test]
+    fn test_irq_clocked_by_rendering() {
+        let mut bus = init();
+        // background patterns from $0000 and sprites from $1000, the usual setup
+        bus.cpu_write_u8(0x2000, 0x08);
+        bus.cpu_write_u8(0x2001, 0x18);
+        bus.run_frame();
+
+        bus.cpu_write_u8(0xC000, 9);
+        bus.cpu_write_u8(0xC001, 0);
+        bus.cpu_write_u8(0xE001, 0);
+        // the pre-render scanline reloads the counter, then it's one clock per scanline even
+        // without any sprite on screen
+        while bus.ppu.position() != (8, 250) {
+            bus.clock();
+        }
+        assert!(!irq(&bus));
+        while bus.ppu.position() != (9, 0) {
+            bus.clock();
+        }
+        assert!(irq(&bus));
+    }
+
+    #[test]
+    fn test_peek_does_not_clock() {
+        let mut bus = init();
+        bus.cpu_write_u8(0xC000, 0);
+        bus.cpu_write_u8(0xE001, 0);
+        bus.ppu.ppu_read_u8(0x0000, true);
+        for _ in 0..A12_FILTER_CYCLES {
+            bus.cartridge.borrow_mut().cpu_clock();
+        }
+        bus.ppu.ppu_read_u8(0x1000, true);
+        assert!(!irq(&bus));
+    }
+
+    #[test]
+    fn test_zero_latch_rev_bc_fires_every_scanline() {
+        let mut bus = init_with_revision(Mmc3Revision::BC);
+        bus.cpu_write_u8(0xC000, 0);
+        bus.cpu_write_u8(0xC001, 0);
+        bus.cpu_write_u8(0xE001, 0);
+        for _ in 0..3 {
+            scanline(&mut bus);
+            assert!(irq(&bus));
+            bus.cpu_write_u8(0xE000, 0);
+            bus.cpu_write_u8(0xE001, 0);
+        }
+    }
+
+    #[test]
+    fn test_zero_latch_rev_a_fires_only_after_reload() {
+        let mut bus = init_with_revision(Mmc3Revision::A);
+        bus.cpu_write_u8(0xC000, 0);
+        bus.cpu_write_u8(0xC001, 0);
+        bus.cpu_write_u8(0xE001, 0);
+        scanline(&mut bus);
+        assert!(irq(&bus));
+
+        bus.cpu_write_u8(0xE000, 0);
+        bus.cpu_write_u8(0xE001, 0);
+        for _ in 0..3 {
+            scanline(&mut bus);
+            assert!(!irq(&bus));
+        }
+    }
+
+    /// Whether the IRQ fires again on the scanline after a reload with 0, which only B/C do
+    fn refires_with_zero_latch(bus: &mut Bus) -> bool {
+        bus.cpu_write_u8(0xC000, 0);
+        bus.cpu_write_u8(0xC001, 0);
+        bus.cpu_write_u8(0xE001, 0);
+        scanline(bus);
+        assert!(irq(bus));
+        bus.cpu_write_u8(0xE000, 0);
+        bus.cpu_write_u8(0xE001, 0);
+        scanline(bus);
+        irq(bus)
+    }
+
+    #[test]
+    fn test_revision_selection() {
+        // iNES 1.0 can't tell
+        let mut bus = init();
+        assert!(refires_with_zero_latch(&mut bus));
+
+        // NES 2.0 submapper 4
+        let mut contents = banked_rom_image(4, 8, 8);
+        contents[7] |= 0x08;
+        contents[8] = 0x40;
+        let mut bus = bus_with_rom(&contents);
+        assert!(!refires_with_zero_latch(&mut bus));
+
+        // picked by hand, it sticks across cartridges
+        let (_tmp_file, filename) = write_rom(&banked_rom_image(4, 8, 8));
+        let mut bus = Bus::new();
+        bus.set_mmc3_revision(Some(Mmc3Revision::A));
+        bus.load_cartridge(&filename).unwrap();
+        assert!(!refires_with_zero_latch(&mut bus));
+        bus.load_cartridge(&filename).unwrap();
+        assert!(!refires_with_zero_latch(&mut bus));
+
+        bus.set_mmc3_revision(None);
+        let (_tmp_file_v2, filename_v2) = write_rom(&contents);
+        bus.load_cartridge(&filename_v2).unwrap();
+        assert!(!refires_with_zero_latch(&mut bus));
+        bus.load_cartridge(&filename).unwrap();
+        assert!(refires_with_zero_latch(&mut bus));
+    }
+
+    #[test]
+    fn test_irq_reaches_the_cpu() {
+        let mut bus = init();
+        bus.cpu_write_u8(0xC000, 0);
+        bus.cpu_write_u8(0xC001, 0);
+        bus.cpu_write_u8(0xE001, 0);
+        scanline(&mut bus);
+        for _ in 0..3 {
+            bus.clock();
+        }
+        assert!(bus.interrupts().is_irq_asserted_by(InterruptSource::Mapper));
+
+        bus.cpu_write_u8(0xE000, 0);
+        for _ in 0..3 {
+            bus.clock();
+        }
+        assert!(!bus.interrupts().is_irq_asserted_by(InterruptSource::Mapper));
+    }
+}
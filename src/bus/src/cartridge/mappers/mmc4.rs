@@ -0,0 +1,118 @@
+use crate::cartridge::Mirroring;
+use crate::cartridge::mappers::mmc2::{write_latch_registers, ChrLatches};
+use crate::cartridge::mappers::{Mapped, Mapper};
+use crate::inesformat::format::PRG_ROM_SIZE_FACTOR;
+use crate::inesformat::header::Header;
+
+/// Mapper 10 (MMC4)
+///
+/// Same CHR latches as the MMC2 (reacting to every row of tiles $FD/$FE on both pattern tables)
+/// with a 16KB switchable PRG-ROM bank at $8000, the last bank fixed at $C000 and 8KB of PRG-RAM.
+pub struct Mmc4 {
+    // in 16KB units
+    prg_rom_banks: usize,
+    prg_bank: u8,
+    latches: ChrLatches,
+    mirroring: Mirroring,
+}
+
+impl Mmc4 {
+    pub fn new(header: &Header) -> Self {
+        Mmc4 {
+            prg_rom_banks: header.prg_rom_banks(),
+            prg_bank: 0,
+            latches: ChrLatches::new(header, false),
+            mirroring: Mirroring::Vertical,
+        }
+    }
+}
+
+impl Mapper for Mmc4 {
+    fn cpu_peek(&self, addr: u16) -> Mapped {
+        let bank = match addr {
+            0x6000..=0x7FFF => return Mapped::PrgRam((addr & 0x1FFF) as usize),
+            0x8000..=0xBFFF => self.prg_bank as usize,
+            0xC000..=0xFFFF => self.prg_rom_banks - 1,
+            _ => return Mapped::Unmapped,
+        };
+        Mapped::PrgRom((bank % self.prg_rom_banks) * PRG_ROM_SIZE_FACTOR + (addr & 0x3FFF) as usize)
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) -> Mapped {
+        match addr {
+            0x6000..=0x7FFF => return Mapped::PrgRam((addr & 0x1FFF) as usize),
+            0xA000..=0xAFFF => self.prg_bank = value & 0x0F,
+            _ => write_latch_registers(addr, value, &mut self.latches, &mut self.mirroring),
+        }
+        Mapped::Unmapped
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> Mapped {
+        let mapped = self.ppu_peek(addr);
+        self.latches.observe(addr);
+        mapped
+    }
+
+    fn ppu_peek(&self, addr: u16) -> Mapped {
+        Mapped::Chr(self.latches.chr_offset(addr))
+    }
+
+    fn ppu_write(&mut self, addr: u16, _value: u8) -> Mapped {
+        Mapped::Chr(self.latches.chr_offset(addr))
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(self.mirroring)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Bus;
+    use crate::test::{banked_rom_image, bus_with_rom};
+
+    fn init() -> Bus {
+        let mut bus = bus_with_rom(&banked_rom_image(10, 8, 8));
+        bus.cpu_write_u8(0xB000, 2);
+        bus.cpu_write_u8(0xC000, 4);
+        bus.cpu_write_u8(0xD000, 6);
+        bus.cpu_write_u8(0xE000, 8);
+        bus
+    }
+
+    fn chr_bank_at(bus: &Bus, addr: u16) -> u8 {
+        bus.cartridge().ppu_peek(addr) / 4
+    }
+
+    #[test]
+    fn test_prg_banking() {
+        let mut bus = init();
+        assert_eq!(bus.cpu_peek_u8(0x8000), 0);
+        assert_eq!(bus.cpu_peek_u8(0xC000), 14);
+
+        bus.cpu_write_u8(0xA000, 3);
+        assert_eq!(bus.cpu_peek_u8(0x8000), 6);
+        assert_eq!(bus.cpu_peek_u8(0xA000), 7);
+        assert_eq!(bus.cpu_peek_u8(0xE000), 15);
+
+        bus.cpu_write_u8(0x6000, 0x42);
+        assert_eq!(bus.cpu_peek_u8(0x6000), 0x42);
+    }
+
+    #[test]
+    fn test_latches_react_to_every_row() {
+        let mut bus = init();
+        // sprite fetch of the last row of tile $FD from the first pattern table
+        bus.ppu.ppu_read_u8(0x0FD7, false);
+        assert_eq!(bus.ppu.ppu_read_u8(0x0FDF, false), 19);
+        assert_eq!(chr_bank_at(&bus, 0x0000), 2);
+
+        bus.ppu.ppu_read_u8(0x1FE3, false);
+        assert_eq!(chr_bank_at(&bus, 0x1000), 8);
+        bus.ppu.ppu_read_u8(0x1FDC, false);
+        assert_eq!(chr_bank_at(&bus, 0x1000), 6);
+
+        bus.ppu.ppu_read_u8(0x0FEA, false);
+        assert_eq!(chr_bank_at(&bus, 0x0000), 4);
+    }
+}
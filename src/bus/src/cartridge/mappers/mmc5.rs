@@ -0,0 +1,734 @@
+use crate::cartridge::mappers::{Mapped, Mapper};
+use crate::inesformat::format::{CHR_ROM_SIZE_FACTOR, PRG_ROM_SIZE_FACTOR};
+use crate::inesformat::header::Header;
+
+// the MMC5 decides the PPU stopped rendering once it hasn't seen a read for this many CPU cycles
+const PPU_IDLE_CYCLES: u8 = 3;
+
+// PPU reads (counted from the first nametable fetch of a scanline) which belong to the sprite
+// pattern fetches in dots 257-320, and where the two tiles of the next scanline get prefetched
+const SPRITE_FETCHES: std::ops::Range<u16> = 128..160;
+const PREFETCHES: std::ops::Range<u16> = 160..168;
+
+/// Mapper 5 (MMC5)
+///
+/// Pretty much everything is configured through registers in $5100-$5206:
+/// * four PRG layouts, where anything but the last bank can also be PRG-RAM
+/// * 1KB to 8KB CHR banks, with a second set of registers used by background fetches when
+///   8x16 sprites are enabled
+/// * 1KB of ExRAM, which can act as an extra nametable, hold per tile attributes (extended
+///   attribute mode) or just be more RAM for the CPU
+/// * nametables picked per quadrant among the two CIRAM pages, ExRAM and a fill mode
+/// * a vertical split where part of the screen is drawn from ExRAM with its own scroll
+/// * a scanline IRQ and an 8x8 multiplier
+///
+/// There's no A12 trick here, scanlines are detected by looking for the PPU reading the same
+/// nametable address 3 times in a row, which only happens at the end of each rendered scanline.
+/// From there PPU reads are counted to tell background fetches apart from sprite ones.
+pub struct Mmc5 {
+    // in 8KB units
+    prg_rom_banks: usize,
+    prg_ram_size: usize,
+    chr_size: usize,
+    prg_mode: u8,
+    chr_mode: u8,
+    prg_ram_protect: [u8; 2],
+    exram_mode: u8,
+    nametables: u8,
+    fill_tile: u8,
+    fill_attribute: u8,
+    // $5113-$5117
+    prg_banks: [u8; 5],
+    // $5120-$512B, already combined with the upper bits from $5130
+    chr_banks: [u16; 12],
+    chr_upper: u8,
+    // outside of 8x16 sprite rendering, whichever set was written last is used for everything
+    last_chr_set_b: bool,
+    split_control: u8,
+    split_scroll: u8,
+    split_bank: u8,
+    irq_compare: u8,
+    irq_enabled: bool,
+    irq_pending: bool,
+    in_frame: bool,
+    scanline: u8,
+    multiplicand: u8,
+    multiplier: u8,
+    exram: [u8; 1024],
+    // snooped from $2000/$2001
+    sprites_8x16: bool,
+    rendering_enabled: bool,
+    last_nametable_read: u16,
+    matching_reads: u8,
+    // PPU reads since the current scanline started
+    fetches: u16,
+    ppu_idle_cycles: u8,
+    // ExRAM byte picked up by the last background nametable fetch, along with whether that tile
+    // belongs to the split region
+    tile_attribute: u8,
+    split_tile: bool,
+}
+
+impl Mmc5 {
+    pub fn new(header: &Header) -> Self {
+        Mmc5 {
+            prg_rom_banks: header.prg_rom_banks() * 2,
+            prg_ram_size: header.prg_ram_size(),
+            chr_size: header.chr_banks() * CHR_ROM_SIZE_FACTOR,
+            prg_mode: 3,
+            chr_mode: 3,
+            prg_ram_protect: [0; 2],
+            exram_mode: 0,
+            nametables: 0,
+            fill_tile: 0,
+            fill_attribute: 0,
+            prg_banks: [0, 0, 0, 0, 0xFF],
+            chr_banks: [0; 12],
+            chr_upper: 0,
+            last_chr_set_b: false,
+            split_control: 0,
+            split_scroll: 0,
+            split_bank: 0,
+            irq_compare: 0,
+            irq_enabled: false,
+            irq_pending: false,
+            in_frame: false,
+            scanline: 0,
+            multiplicand: 0xFF,
+            multiplier: 0xFF,
+            exram: [0; 1024],
+            sprites_8x16: false,
+            rendering_enabled: false,
+            last_nametable_read: 0,
+            matching_reads: 0,
+            fetches: 0,
+            ppu_idle_cycles: 0,
+            tile_attribute: 0,
+            split_tile: false,
+        }
+    }
+
+    fn status(&self) -> u8 {
+        (self.irq_pending as u8) << 7 | (self.in_frame as u8) << 6
+    }
+
+    fn product(&self) -> u16 {
+        self.multiplicand as u16 * self.multiplier as u16
+    }
+
+    fn prg_ram_writable(&self) -> bool {
+        self.prg_ram_protect == [0x2, 0x1]
+    }
+
+    fn prg_ram_offset(&self, bank: u8, addr: u16) -> Mapped {
+        if self.prg_ram_size == 0 {
+            return Mapped::Unmapped;
+        }
+        Mapped::PrgRam(((bank & 0x7) as usize * 0x2000 + (addr & 0x1FFF) as usize) % self.prg_ram_size)
+    }
+
+    fn prg_offset(&self, addr: u16) -> Mapped {
+        // (register, window size in 8KB units)
+        let (register, size) = match (self.prg_mode, addr) {
+            (0, _) => (4, 4),
+            (1, 0x8000..=0xBFFF) => (2, 2),
+            (1, _) => (4, 2),
+            (2, 0x8000..=0xBFFF) => (2, 2),
+            (2, 0xC000..=0xDFFF) => (3, 1),
+            (2, _) => (4, 1),
+            _ => (1 + ((addr - 0x8000) >> 13) as usize, 1),
+        };
+        let value = self.prg_banks[register];
+        // bit 7 picks ROM, except for the last bank which is always ROM
+        let rom = register == 4 || value & 0x80 == 0x80;
+        let bank = (value & 0x7F & !(size as u8 - 1)) + ((addr >> 13) as u8 & (size as u8 - 1));
+
+        if rom {
+            Mapped::PrgRom((bank as usize % self.prg_rom_banks) * (PRG_ROM_SIZE_FACTOR / 2) + (addr & 0x1FFF) as usize)
+        } else {
+            self.prg_ram_offset(bank, addr)
+        }
+    }
+
+    fn chr_offset(&self, addr: u16, set_b: bool) -> usize {
+        let addr = addr as usize & 0x1FFF;
+        let (register, size) = match (self.chr_mode, set_b) {
+            (0, false) => (7, 0x2000),
+            (0, true) => (11, 0x2000),
+            (1, false) => (3 + (addr >> 12) * 4, 0x1000),
+            (1, true) => (11, 0x1000),
+            (2, false) => (1 + (addr >> 11) * 2, 0x800),
+            (2, true) => (9 + ((addr >> 11) & 0x1) * 2, 0x800),
+            (_, false) => (addr >> 10, 0x400),
+            (_, true) => (8 + ((addr >> 10) & 0x3), 0x400),
+        };
+        (self.chr_banks[register] as usize * size + (addr & (size - 1))) % self.chr_size
+    }
+
+    // Background fetches use the B set only while 8x16 sprites are being rendered
+    fn use_chr_set_b(&self, fetch: Option<u16>) -> bool {
+        match fetch {
+            Some(fetch) if self.sprites_8x16 && self.rendering_enabled => !SPRITE_FETCHES.contains(&fetch),
+            _ => self.last_chr_set_b,
+        }
+    }
+
+    fn split_enabled(&self) -> bool {
+        self.split_control & 0x80 == 0x80 && self.exram_mode <= 1
+    }
+
+    // the split region is counted in tiles from the left edge, bit 6 picks which side of it
+    fn in_split(&self, tile: u16) -> bool {
+        let count = (self.split_control & 0x1F) as u16;
+        if self.split_control & 0x40 == 0x40 {
+            tile >= count
+        } else {
+            tile < count
+        }
+    }
+
+    // The split has its own vertical scroll which moves down along with the scanline counter
+    fn split_y(&self, fetch: u16) -> u16 {
+        let line = self.scanline as u16 + PREFETCHES.contains(&fetch) as u16;
+        (self.split_scroll as u16 + line) % 240
+    }
+
+    // Position (within the scanline) of the tile a background fetch belongs to
+    fn background_tile(fetch: u16) -> Option<u16> {
+        match fetch {
+            _ if fetch < SPRITE_FETCHES.start => Some(fetch / 4 + 2),
+            _ if PREFETCHES.contains(&fetch) => Some((fetch - PREFETCHES.start) / 4),
+            _ => None,
+        }
+    }
+
+    /// Every PPU read goes through here, returns how far into the scanline it happened while
+    /// the PPU is rendering
+    fn observe(&mut self, addr: u16) -> Option<u16> {
+        self.ppu_idle_cycles = 0;
+
+        let nametable = (0x2000..=0x2FFF).contains(&addr);
+        if nametable && addr == self.last_nametable_read {
+            self.matching_reads += 1;
+        } else {
+            self.matching_reads = 1;
+        }
+        self.last_nametable_read = if nametable { addr } else { 0 };
+
+        if self.matching_reads == 3 {
+            self.matching_reads = 0;
+            self.fetches = 0;
+            if self.in_frame {
+                self.scanline = self.scanline.wrapping_add(1);
+                if self.scanline == self.irq_compare {
+                    self.irq_pending = true;
+                }
+            } else {
+                self.in_frame = true;
+                self.scanline = 0;
+                self.irq_pending = false;
+            }
+        }
+
+        if !self.in_frame {
+            return None;
+        }
+        let fetch = self.fetches;
+        self.fetches = self.fetches.saturating_add(1);
+        Some(fetch)
+    }
+
+    fn leave_frame(&mut self) {
+        self.in_frame = false;
+        self.matching_reads = 0;
+        self.last_nametable_read = 0;
+    }
+
+    fn nametable(&self, addr: u16) -> Mapped {
+        let quadrant = (addr >> 10) & 0x3;
+        let attribute = addr & 0x3FF >= 0x3C0;
+        match (self.nametables >> (quadrant * 2)) & 0x3 {
+            page @ (0 | 1) => Mapped::Ciram(((page as usize) << 10) | (addr & 0x3FF) as usize),
+            2 if self.exram_mode <= 1 => Mapped::Data(self.exram[(addr & 0x3FF) as usize]),
+            2 => Mapped::Data(0),
+            _ if attribute => Mapped::Data(self.fill_attribute * 0x55),
+            _ => Mapped::Data(self.fill_tile),
+        }
+    }
+}
+
+impl Mapper for Mmc5 {
+    fn cpu_read(&mut self, addr: u16) -> Mapped {
+        let mapped = self.cpu_peek(addr);
+        match addr {
+            0x5204 => self.irq_pending = false,
+            // fetching the NMI vector means the PPU entered vblank
+            0xFFFA | 0xFFFB => {
+                self.leave_frame();
+                self.irq_pending = false;
+            }
+            _ => {}
+        }
+        mapped
+    }
+
+    fn cpu_peek(&self, addr: u16) -> Mapped {
+        match addr {
+            0x5204 => Mapped::Data(self.status()),
+            0x5205 => Mapped::Data(self.product() as u8),
+            0x5206 => Mapped::Data((self.product() >> 8) as u8),
+            // ExRAM is only readable by the CPU in the RAM modes
+            0x5C00..=0x5FFF if self.exram_mode >= 2 => Mapped::Data(self.exram[(addr & 0x3FF) as usize]),
+            0x6000..=0x7FFF => self.prg_ram_offset(self.prg_banks[0], addr),
+            0x8000..=0xFFFF => self.prg_offset(addr),
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) -> Mapped {
+        match addr {
+            0x5100 => self.prg_mode = value & 0x3,
+            0x5101 => self.chr_mode = value & 0x3,
+            0x5102 => self.prg_ram_protect[0] = value & 0x3,
+            0x5103 => self.prg_ram_protect[1] = value & 0x3,
+            0x5104 => self.exram_mode = value & 0x3,
+            0x5105 => self.nametables = value,
+            0x5106 => self.fill_tile = value,
+            0x5107 => self.fill_attribute = value & 0x3,
+            0x5113..=0x5117 => self.prg_banks[(addr - 0x5113) as usize] = value,
+            0x5120..=0x512B => {
+                let register = (addr - 0x5120) as usize;
+                self.chr_banks[register] = value as u16 | (self.chr_upper as u16) << 8;
+                self.last_chr_set_b = register >= 8;
+            }
+            0x5130 => self.chr_upper = value & 0x3,
+            0x5200 => self.split_control = value,
+            0x5201 => self.split_scroll = value,
+            0x5202 => self.split_bank = value,
+            0x5203 => self.irq_compare = value,
+            0x5204 => self.irq_enabled = value & 0x80 == 0x80,
+            0x5205 => self.multiplicand = value,
+            0x5206 => self.multiplier = value,
+            0x5C00..=0x5FFF => {
+                let cell = &mut self.exram[(addr & 0x3FF) as usize];
+                match self.exram_mode {
+                    // the PPU owns ExRAM in these modes, writes outside of rendering store 0
+                    0 | 1 => *cell = if self.in_frame { value } else { 0 },
+                    2 => *cell = value,
+                    _ => {}
+                }
+            }
+            0x6000..=0x7FFF if self.prg_ram_writable() => {
+                return self.prg_ram_offset(self.prg_banks[0], addr);
+            }
+            0x8000..=0xDFFF if self.prg_ram_writable() => {
+                if let mapped @ Mapped::PrgRam(_) = self.prg_offset(addr) {
+                    return mapped;
+                }
+            }
+            _ => {}
+        }
+        Mapped::Unmapped
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> Mapped {
+        let fetch = self.observe(addr);
+        let background = fetch.is_some_and(|fetch| !SPRITE_FETCHES.contains(&fetch));
+
+        if background && self.split_tile {
+            let y = self.split_y(fetch.unwrap_or(0));
+            let offset = self.split_bank as usize * 0x1000 + (addr as usize & 0x0FF8) + (y as usize & 0x7);
+            return Mapped::Chr(offset % self.chr_size);
+        }
+        if background && self.exram_mode == 1 {
+            let bank = (self.chr_upper as usize) << 6 | (self.tile_attribute & 0x3F) as usize;
+            return Mapped::Chr((bank * 0x1000 + (addr & 0x0FFF) as usize) % self.chr_size);
+        }
+        Mapped::Chr(self.chr_offset(addr, self.use_chr_set_b(fetch)))
+    }
+
+    fn ppu_peek(&self, addr: u16) -> Mapped {
+        Mapped::Chr(self.chr_offset(addr, self.last_chr_set_b))
+    }
+
+    fn ppu_write(&mut self, addr: u16, _value: u8) -> Mapped {
+        Mapped::Chr(self.chr_offset(addr, self.last_chr_set_b))
+    }
+
+    fn nametable_read(&mut self, addr: u16) -> Option<Mapped> {
+        let fetch = self.observe(addr);
+        let tile = fetch.and_then(Self::background_tile);
+
+        // background tiles go nametable, attribute, pattern low, pattern high
+        match (fetch.map(|fetch| fetch % 4), tile) {
+            (Some(0), Some(tile)) => {
+                self.split_tile = self.split_enabled() && self.in_split(tile);
+                if self.split_tile {
+                    let y = self.split_y(fetch.unwrap_or(0));
+                    self.tile_attribute = self.exram[((y / 8) * 32 + (tile & 0x1F)) as usize];
+                    return Some(Mapped::Data(self.tile_attribute));
+                }
+                self.tile_attribute = self.exram[(addr & 0x3FF) as usize];
+            }
+            (Some(1), Some(tile)) if self.split_tile => {
+                // the PPU picks a quadrant on its own, so the palette is repeated in all 4
+                let y = self.split_y(fetch.unwrap_or(0));
+                let attributes = self.exram[(0x3C0 + (y / 32) * 8 + (tile & 0x1F) / 4) as usize];
+                let shift = ((y / 16) & 0x1) * 4 + ((tile / 2) & 0x1) * 2;
+                return Some(Mapped::Data(((attributes >> shift) & 0x3) * 0x55));
+            }
+            (Some(1), Some(_)) if self.exram_mode == 1 => {
+                return Some(Mapped::Data((self.tile_attribute >> 6) * 0x55));
+            }
+            _ => {}
+        }
+        Some(self.nametable(addr))
+    }
+
+    fn nametable_peek(&self, addr: u16) -> Option<Mapped> {
+        Some(self.nametable(addr))
+    }
+
+    fn nametable_write(&mut self, addr: u16, value: u8) -> Option<Mapped> {
+        match (self.nametables >> (((addr >> 10) & 0x3) * 2)) & 0x3 {
+            page @ (0 | 1) => Some(Mapped::Ciram(((page as usize) << 10) | (addr & 0x3FF) as usize)),
+            2 if self.exram_mode <= 2 => {
+                self.exram[(addr & 0x3FF) as usize] = value;
+                Some(Mapped::Unmapped)
+            }
+            _ => Some(Mapped::Unmapped),
+        }
+    }
+
+    fn snoop_cpu_write(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x2000..=0x3FFF if addr & 0x7 == 0 => self.sprites_8x16 = value & 0x20 == 0x20,
+            0x2000..=0x3FFF if addr & 0x7 == 1 => {
+                self.rendering_enabled = value & 0x18 != 0;
+                if !self.rendering_enabled {
+                    self.leave_frame();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn cpu_clock(&mut self) {
+        if self.ppu_idle_cycles < PPU_IDLE_CYCLES {
+            self.ppu_idle_cycles += 1;
+            if self.ppu_idle_cycles == PPU_IDLE_CYCLES {
+                self.leave_frame();
+            }
+        }
+    }
+
+    fn irq_asserted(&self) -> bool {
+        self.irq_pending && self.irq_enabled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Bus;
+    use crate::test::{banked_rom_image, bus_with_rom};
+
+    fn init() -> Bus {
+        // 128KB of PRG-ROM and 64KB of CHR-ROM
+        let mut rom = banked_rom_image(5, 8, 8);
+        // 64KB of PRG-RAM
+        rom[8] = 8;
+        bus_with_rom(&rom)
+    }
+
+    fn read(bus: &mut Bus, addr: u16) -> u8 {
+        bus.ppu.ppu_read_u8(addr, false)
+    }
+
+    /// Fetches the PPU does at the end of a scanline: the first two tiles of the next one
+    /// followed by two dummy nametable reads
+    fn prefetch(bus: &mut Bus, line: u16) -> Vec<u8> {
+        let mut fetched = vec![];
+        for tile in 0..2 {
+            fetched.push(read(bus, 0x2000 + (line / 8) * 32 + tile));
+            fetched.push(read(bus, 0x23C0 + (line / 32) * 8));
+            fetched.push(read(bus, (tile << 4) | (line & 0x7)));
+            fetched.push(read(bus, (tile << 4) | (line & 0x7) | 0x8));
+        }
+        read(bus, 0x2000 + (line / 8) * 32 + 2);
+        read(bus, 0x2000 + (line / 8) * 32 + 2);
+        fetched
+    }
+
+    /// Everything the PPU reads during a rendered scanline, returns what the background and
+    /// sprite pattern fetches got back
+    fn scanline(bus: &mut Bus, line: u16) -> (Vec<u8>, Vec<u8>) {
+        let mut background = vec![];
+        for tile in 2..34 {
+            background.push(read(bus, 0x2000 + (line / 8) * 32 + (tile & 0x1F)));
+            background.push(read(bus, 0x23C0 + (line / 32) * 8 + (tile & 0x1F) / 4));
+            background.push(read(bus, line & 0x7));
+            background.push(read(bus, 0x0008 | (line & 0x7)));
+        }
+        let mut sprites = vec![];
+        for _ in 0..8 {
+            read(bus, 0x2FFF);
+            read(bus, 0x2FFF);
+            sprites.push(read(bus, 0x1000));
+            sprites.push(read(bus, 0x1008));
+        }
+        prefetch(bus, line + 1);
+        (background, sprites)
+    }
+
+    fn start_frame(bus: &mut Bus) {
+        bus.cpu_write_u8(0x2001, 0x18);
+        prefetch(bus, 0);
+    }
+
+    fn irq(bus: &Bus) -> bool {
+        bus.cartridge().irq_asserted()
+    }
+
+    #[test]
+    fn test_prg_modes() {
+        let mut bus = init();
+        // power up: mode 3 with the last bank at $E000
+        assert_eq!(bus.cpu_peek_u8(0xE000), 15);
+
+        bus.cpu_write_u8(0x5117, 0x85);
+        bus.cpu_write_u8(0x5100, 0);
+        // 32KB, lower bits of the register are ignored
+        assert_eq!(bus.cpu_peek_u8(0x8000), 4);
+        assert_eq!(bus.cpu_peek_u8(0xA000), 5);
+        assert_eq!(bus.cpu_peek_u8(0xC000), 6);
+        assert_eq!(bus.cpu_peek_u8(0xE000), 7);
+
+        bus.cpu_write_u8(0x5100, 1);
+        bus.cpu_write_u8(0x5115, 0x83);
+        assert_eq!(bus.cpu_peek_u8(0x8000), 2);
+        assert_eq!(bus.cpu_peek_u8(0xA000), 3);
+        assert_eq!(bus.cpu_peek_u8(0xC000), 4);
+        assert_eq!(bus.cpu_peek_u8(0xE000), 5);
+
+        bus.cpu_write_u8(0x5100, 2);
+        bus.cpu_write_u8(0x5116, 0x89);
+        assert_eq!(bus.cpu_peek_u8(0x8000), 2);
+        assert_eq!(bus.cpu_peek_u8(0xC000), 9);
+        assert_eq!(bus.cpu_peek_u8(0xE000), 5);
+
+        bus.cpu_write_u8(0x5100, 3);
+        bus.cpu_write_u8(0x5114, 0x8B);
+        assert_eq!(bus.cpu_peek_u8(0x8000), 11);
+        assert_eq!(bus.cpu_peek_u8(0xA000), 3);
+        assert_eq!(bus.cpu_peek_u8(0xC000), 9);
+        assert_eq!(bus.cpu_peek_u8(0xE000), 5);
+    }
+
+    #[test]
+    fn test_prg_ram() {
+        let mut bus = init();
+        bus.cpu_write_u8(0x5113, 2);
+        // write protected until $5102/$5103 hold 2 and 1
+        bus.cpu_write_u8(0x6000, 0x42);
+        assert_eq!(bus.cpu_peek_u8(0x6000), 0x00);
+
+        bus.cpu_write_u8(0x5102, 2);
+        bus.cpu_write_u8(0x5103, 1);
+        bus.cpu_write_u8(0x6000, 0x42);
+        assert_eq!(bus.cpu_peek_u8(0x6000), 0x42);
+
+        // the same RAM bank mapped into $8000 with bit 7 clear
+        bus.cpu_write_u8(0x5114, 0x02);
+        assert_eq!(bus.cpu_peek_u8(0x8000), 0x42);
+        bus.cpu_write_u8(0x8001, 0x24);
+        assert_eq!(bus.cpu_peek_u8(0x6001), 0x24);
+    }
+
+    #[test]
+    fn test_chr_modes() {
+        let mut bus = init();
+        let banks = |bus: &Bus| -> Vec<u8> {
+            (0..8).map(|slot| bus.cartridge().ppu_peek(slot * 0x400)).collect()
+        };
+        for register in 0..8 {
+            bus.cpu_write_u8(0x5120 + register, 8 + register as u8);
+        }
+        assert_eq!(banks(&bus), vec![8, 9, 10, 11, 12, 13, 14, 15]);
+
+        // 2KB banks from the odd registers
+        bus.cpu_write_u8(0x5101, 2);
+        assert_eq!(banks(&bus), vec![18, 19, 22, 23, 26, 27, 30, 31]);
+
+        // 4KB
+        bus.cpu_write_u8(0x5101, 1);
+        assert_eq!(banks(&bus), vec![44, 45, 46, 47, 60, 61, 62, 63]);
+
+        // 8KB wraps around the 64KB of CHR-ROM
+        bus.cpu_write_u8(0x5101, 0);
+        assert_eq!(banks(&bus), vec![56, 57, 58, 59, 60, 61, 62, 63]);
+
+        // the B set is repeated in both halves and takes over since it was written last
+        bus.cpu_write_u8(0x5101, 3);
+        for register in 0..4 {
+            bus.cpu_write_u8(0x5128 + register, 32 + register as u8);
+        }
+        assert_eq!(banks(&bus), vec![32, 33, 34, 35, 32, 33, 34, 35]);
+
+        // upper bits
+        bus.cpu_write_u8(0x5130, 1);
+        bus.cpu_write_u8(0x5120, 2);
+        assert_eq!(bus.cartridge().ppu_peek(0x0000), 2);
+    }
+
+    #[test]
+    fn test_8x16_sprites_use_separate_chr_sets() {
+        let mut bus = init();
+        bus.cpu_write_u8(0x5120 + 4, 20);
+        bus.cpu_write_u8(0x5128, 40);
+        bus.cpu_write_u8(0x2000, 0x20);
+        start_frame(&mut bus);
+
+        let (background, sprites) = scanline(&mut bus, 0);
+        assert!(background.chunks(4).all(|tile| tile[2] == 40 && tile[3] == 40));
+        assert!(sprites.iter().all(|value| *value == 20));
+
+        // with 8x8 sprites the last written set is used for everything
+        bus.cpu_write_u8(0x2000, 0x00);
+        let (background, sprites) = scanline(&mut bus, 1);
+        assert_eq!(background[2], 40);
+        assert_eq!(sprites[0], 40);
+    }
+
+    #[test]
+    fn test_exram_modes() {
+        let mut bus = init();
+        // plain RAM
+        bus.cpu_write_u8(0x5104, 2);
+        bus.cpu_write_u8(0x5C10, 0x42);
+        assert_eq!(bus.cpu_peek_u8(0x5C10), 0x42);
+
+        // read-only
+        bus.cpu_write_u8(0x5104, 3);
+        bus.cpu_write_u8(0x5C10, 0x24);
+        assert_eq!(bus.cpu_peek_u8(0x5C10), 0x42);
+
+        // owned by the PPU: not readable and writes outside of rendering store 0
+        bus.cpu_write_u8(0x5104, 0);
+        assert_eq!(bus.cpu_read_u8(0x5C10, false), bus.open_bus);
+        bus.cpu_write_u8(0x5C10, 0x24);
+        bus.cpu_write_u8(0x5104, 2);
+        assert_eq!(bus.cpu_peek_u8(0x5C10), 0x00);
+    }
+
+    #[test]
+    fn test_nametable_mapping() {
+        let mut bus = init();
+        bus.cpu_write_u8(0x5104, 2);
+        bus.cpu_write_u8(0x5C05, 0x77);
+        bus.cpu_write_u8(0x5104, 0);
+        bus.cpu_write_u8(0x5106, 0x31);
+        bus.cpu_write_u8(0x5107, 0x2);
+        // CIRAM page 1, CIRAM page 0, ExRAM and fill mode
+        bus.cpu_write_u8(0x5105, 0b11_10_00_01);
+
+        bus.ppu.ppu_write_u8(0x2005, 0x11);
+        assert_eq!(bus.ppu.ppu_read_u8(0x2005, true), 0x11);
+        assert_eq!(bus.ppu.ppu_read_u8(0x2405, true), 0x00);
+        bus.ppu.ppu_write_u8(0x2405, 0x22);
+        assert_eq!(bus.ppu.ppu_read_u8(0x2405, true), 0x22);
+        assert_eq!(bus.ppu.ppu_read_u8(0x2005, true), 0x11);
+
+        assert_eq!(bus.ppu.ppu_read_u8(0x2805, true), 0x77);
+        assert_eq!(bus.ppu.ppu_read_u8(0x2C05, true), 0x31);
+        assert_eq!(bus.ppu.ppu_read_u8(0x2FC5, true), 0xAA);
+    }
+
+    #[test]
+    fn test_scanline_irq() {
+        let mut bus = init();
+        bus.cpu_write_u8(0x5203, 3);
+        bus.cpu_write_u8(0x5204, 0x80);
+        start_frame(&mut bus);
+
+        for line in 0..3 {
+            assert!(!irq(&bus));
+            scanline(&mut bus, line);
+        }
+        // scanline 3 just started
+        read(&mut bus, 0x2002);
+        assert!(irq(&bus));
+        assert_eq!(bus.cpu_peek_u8(0x5204), 0xC0);
+
+        // reading the status acknowledges it
+        bus.cpu_read_u8(0x5204, false);
+        assert!(!irq(&bus));
+        assert_eq!(bus.cpu_peek_u8(0x5204), 0x40);
+    }
+
+    #[test]
+    fn test_leaving_frame_when_ppu_goes_idle() {
+        let mut bus = init();
+        start_frame(&mut bus);
+        scanline(&mut bus, 0);
+        assert_eq!(bus.cpu_peek_u8(0x5204) & 0x40, 0x40);
+
+        for _ in 0..PPU_IDLE_CYCLES {
+            bus.cartridge.borrow_mut().cpu_clock();
+        }
+        assert_eq!(bus.cpu_peek_u8(0x5204) & 0x40, 0x00);
+    }
+
+    #[test]
+    fn test_extended_attributes() {
+        let mut bus = init();
+        bus.cpu_write_u8(0x5104, 1);
+        start_frame(&mut bus);
+        scanline(&mut bus, 0);
+
+        // tile 2 of scanline 1 gets palette 3 and its pattern from 4KB bank 5
+        bus.cpu_write_u8(0x5C02, 0xC5);
+        let (background, _) = scanline(&mut bus, 1);
+        assert_eq!(background[1], 0xFF);
+        assert_eq!(background[2], 20);
+        // the rest of the row still has ExRAM zeroed
+        assert_eq!(background[5], 0x00);
+        assert_eq!(background[6], 0);
+    }
+
+    #[test]
+    fn test_vertical_split() {
+        let mut bus = init();
+        bus.cpu_write_u8(0x5104, 2);
+        // scanline 1 of the split shows row 2 (split scroll 15)
+        bus.cpu_write_u8(0x5C45, 0x99);
+        bus.cpu_write_u8(0x5C45 + 1, 0x98);
+        bus.cpu_write_u8(0x5104, 1);
+        // everything right of tile 5 comes from the split, with its patterns from bank 3
+        bus.cpu_write_u8(0x5200, 0xC5);
+        bus.cpu_write_u8(0x5201, 15);
+        bus.cpu_write_u8(0x5202, 3);
+        start_frame(&mut bus);
+        scanline(&mut bus, 0);
+
+        let (background, _) = scanline(&mut bus, 1);
+        let tile = |x: usize| &background[(x - 2) * 4..(x - 1) * 4];
+        // tile 4 is still regular background
+        assert_eq!(tile(4)[0], 0x00);
+        assert_eq!(tile(5)[0], 0x99);
+        assert_eq!(tile(6)[0], 0x98);
+        assert_eq!(tile(5)[2], 12);
+    }
+
+    #[test]
+    fn test_multiplier() {
+        let mut bus = init();
+        assert_eq!(bus.cpu_peek_u8(0x5205), 0x01);
+        assert_eq!(bus.cpu_peek_u8(0x5206), 0xFE);
+
+        bus.cpu_write_u8(0x5205, 12);
+        bus.cpu_write_u8(0x5206, 34);
+        assert_eq!(bus.cpu_peek_u8(0x5205), 0x98);
+        assert_eq!(bus.cpu_peek_u8(0x5206), 0x01);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::cartridge::Mirroring;
+use crate::inesformat::error::RomError;
+use crate::inesformat::header::Header;
+use self::axrom::Axrom;
+use self::cnrom::Cnrom;
+use self::fme7::Fme7;
+use self::gxrom::Gxrom;
+use self::mmc1::Mmc1;
+use self::mmc2::Mmc2;
+use self::mmc3::{Mmc3, Mmc3Revision};
+use self::mmc4::Mmc4;
+use self::mmc5::Mmc5;
+use self::namco163::Namco163;
+use self::nrom::Nrom;
+use self::uxrom::Uxrom;
+use self::vrc4::{Vrc4, VrcBoard};
+use self::vrc6::Vrc6;
+use self::vrc7::Vrc7;
+
+pub mod axrom;
+pub mod cnrom;
+pub mod fme7;
+pub mod gxrom;
+pub mod mmc1;
+pub mod mmc2;
+pub mod mmc3;
+pub mod mmc4;
+pub mod mmc5;
+pub mod namco163;
+pub mod nrom;
+pub mod uxrom;
+pub mod vrc4;
+pub mod vrc6;
+pub mod vrc7;
+mod vrc_irq;
+
+/// Where an access ends up once the mapper has decoded it. Memory itself lives in the cartridge,
+/// mappers only pick which bank (and offset) an address refers to.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Mapped {
+    PrgRom(usize),
+    PrgRam(usize),
+    Chr(usize),
+    /// Offset into the PPU's own 2KB of nametable RAM (CIRAM)
+    Ciram(usize),
+    /// The mapper answered by itself (registers, internal RAM and so on)
+    Data(u8),
+    /// Nothing drives the data bus (or, for writes, nothing else needs to be stored)
+    Unmapped,
+}
+
+/// Cartridge boards are identified by their iNES mapper number. CPU addresses are only handed
+/// over for $4020-$FFFF and PPU addresses for $0000-$1FFF, nametable accesses ($2000-$2FFF) go
+/// through their own hooks.
+pub trait Mapper {
+    fn cpu_read(&mut self, addr: u16) -> Mapped {
+        self.cpu_peek(addr)
+    }
+    /// Same as cpu_read minus side effects (latches, IRQ acknowledge and so on)
+    fn cpu_peek(&self, addr: u16) -> Mapped;
+    fn cpu_write(&mut self, addr: u16, value: u8) -> Mapped;
+
+    fn ppu_read(&mut self, addr: u16) -> Mapped {
+        self.ppu_peek(addr)
+    }
+    fn ppu_peek(&self, addr: u16) -> Mapped;
+    fn ppu_write(&mut self, addr: u16, value: u8) -> Mapped;
+
+    /// Boards which take over the nametables return where an access goes, None leaves it to
+    /// the CIRAM arrangement reported by mirroring()
+    fn nametable_read(&mut self, addr: u16) -> Option<Mapped> {
+        self.nametable_peek(addr)
+    }
+    fn nametable_peek(&self, _addr: u16) -> Option<Mapped> {
+        None
+    }
+    fn nametable_write(&mut self, _addr: u16, _value: u8) -> Option<Mapped> {
+        None
+    }
+
+    /// Every CPU write outside of the cartridge space, some mappers keep an eye on the PPU
+    /// registers this way
+    fn snoop_cpu_write(&mut self, _addr: u16, _value: u8) {}
+
+    fn cpu_clock(&mut self) {}
+
+    /// Whether the mapper is pulling the CPU IRQ line low
+    fn irq_asserted(&self) -> bool {
+        false
+    }
+
+    /// Nametable arrangement selected by the mapper, None if it's hardwired on the board
+    fn mirroring(&self) -> Option<Mirroring> {
+        None
+    }
+
+    /// Expansion audio (VRC6, VRC7 and friends) mixed in with the APU channels, in the same -1.0
+    /// to 1.0 range. None of the extra sound chips are emulated yet so they all stay silent.
+    fn audio_output(&self) -> f32 {
+        0.0
+    }
+
+    /// RAM inside the mapper chip itself, kept alive by the battery along with PRG-RAM
+    fn internal_ram(&self) -> &[u8] {
+        &[]
+    }
+    fn internal_ram_mut(&mut self) -> &mut [u8] {
+        &mut []
+    }
+}
+
+/// `mmc3_revision` overrides the MMC3 revision the header implies
+pub fn new_mapper(header: &Header, mmc3_revision: Option<Mmc3Revision>) -> Result<Box<dyn Mapper>, RomError> {
+    match header.mapper_id() {
+        0 => Ok(Box::new(Nrom::new(header))),
+        1 => Ok(Box::new(Mmc1::new(header))),
+        2 => Ok(Box::new(Uxrom::new(header))),
+        3 => Ok(Box::new(Cnrom::new(header))),
+        4 => {
+            let revision = mmc3_revision.unwrap_or_else(|| Mmc3Revision::from_header(header));
+            Ok(Box::new(Mmc3::new(header, revision)))
+        }
+        5 => Ok(Box::new(Mmc5::new(header))),
+        7 => Ok(Box::new(Axrom::new(header))),
+        9 => Ok(Box::new(Mmc2::new(header))),
+        10 => Ok(Box::new(Mmc4::new(header))),
+        19 => Ok(Box::new(Namco163::new(header))),
+        21 | 22 | 23 | 25 => {
+            let board = VrcBoard::from_ids(header.mapper_id(), header.submapper());
+            Ok(Box::new(Vrc4::new(header, board)))
+        }
+        24 | 26 => Ok(Box::new(Vrc6::new(header))),
+        66 => Ok(Box::new(Gxrom::new(header))),
+        69 => Ok(Box::new(Fme7::new(header))),
+        85 => Ok(Box::new(Vrc7::new(header))),
+        mapper_id => Err(RomError::UnsupportedMapper(mapper_id)),
+    }
+}
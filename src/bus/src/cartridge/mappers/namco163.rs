@@ -0,0 +1,291 @@
+use crate::cartridge::mappers::{Mapped, Mapper};
+use crate::inesformat::format::{CHR_ROM_SIZE_FACTOR, PRG_ROM_SIZE_FACTOR};
+use crate::inesformat::header::Header;
+
+// the IRQ fires once the 15-bit counter reaches its maximum
+const IRQ_COUNTER_MAX: u16 = 0x7FFF;
+
+/// Mapper 19 (Namco 129/163)
+///
+/// Three switchable 8KB PRG-ROM banks with the last one fixed, eight 1KB pattern banks and four
+/// 1KB nametable banks. Bank values $E0-$FF point at CIRAM instead of CHR-ROM, which lets the
+/// pattern tables use nametable RAM and the nametables use CHR-ROM. The chip also has 128 bytes
+/// of internal RAM reached through an auto-incrementing port at $4800 (the sound channels, which
+/// aren't emulated, keep their registers there) and a 15-bit IRQ counter going up every CPU
+/// cycle.
+pub struct Namco163 {
+    // in 8KB units
+    prg_rom_banks: usize,
+    // in 1KB units
+    chr_banks: usize,
+    prg_banks: [u8; 3],
+    // 8 pattern banks followed by 4 nametable banks
+    chr_registers: [u8; 12],
+    // bit 6 keeps $0000-$0FFF and bit 7 $1000-$1FFF on CHR-ROM even for $E0-$FF
+    ciram_disabled: u8,
+    // $F800: bits 4-7 have to be 0100 for writes, then bits 0-3 protect each 2KB of PRG-RAM
+    prg_ram_protect: u8,
+    internal_ram: [u8; 128],
+    internal_ram_addr: u8,
+    auto_increment: bool,
+    irq_counter: u16,
+    irq_enabled: bool,
+    irq_pending: bool,
+}
+
+impl Namco163 {
+    pub fn new(header: &Header) -> Self {
+        Namco163 {
+            prg_rom_banks: header.prg_rom_banks() * 2,
+            chr_banks: header.chr_banks() * CHR_ROM_SIZE_FACTOR / 0x400,
+            prg_banks: [0; 3],
+            chr_registers: [0; 12],
+            ciram_disabled: 0,
+            prg_ram_protect: 0,
+            internal_ram: [0; 128],
+            internal_ram_addr: 0,
+            auto_increment: false,
+            irq_counter: 0,
+            irq_enabled: false,
+            irq_pending: false,
+        }
+    }
+
+    fn prg_rom_offset(&self, addr: u16) -> usize {
+        let bank = match addr {
+            0x8000..=0xDFFF => self.prg_banks[((addr - 0x8000) >> 13) as usize] as usize,
+            _ => self.prg_rom_banks - 1,
+        };
+        (bank % self.prg_rom_banks) * (PRG_ROM_SIZE_FACTOR / 2) + (addr & 0x1FFF) as usize
+    }
+
+    fn prg_ram_writable(&self, addr: u16) -> bool {
+        let segment = (addr - 0x6000) >> 11;
+        self.prg_ram_protect & 0xF0 == 0x40 && self.prg_ram_protect & (1 << segment) == 0
+    }
+
+    fn bank(&self, slot: usize, addr: u16, ciram_allowed: bool) -> Mapped {
+        let bank = self.chr_registers[slot];
+        if bank >= 0xE0 && ciram_allowed {
+            return Mapped::Ciram(((bank & 0x1) as usize) << 10 | (addr & 0x3FF) as usize);
+        }
+        Mapped::Chr((bank as usize % self.chr_banks) * 0x400 + (addr & 0x3FF) as usize)
+    }
+
+    fn pattern(&self, addr: u16) -> Mapped {
+        let slot = (addr >> 10) as usize & 0x7;
+        let disabled_bit = if addr < 0x1000 { 0x40 } else { 0x80 };
+        self.bank(slot, addr, self.ciram_disabled & disabled_bit == 0)
+    }
+
+    fn irq_counter_register(&self, high: bool) -> u8 {
+        match high {
+            true => (self.irq_enabled as u8) << 7 | (self.irq_counter >> 8) as u8,
+            false => self.irq_counter as u8,
+        }
+    }
+}
+
+impl Mapper for Namco163 {
+    fn cpu_read(&mut self, addr: u16) -> Mapped {
+        let mapped = self.cpu_peek(addr);
+        if let 0x4800..=0x4FFF = addr {
+            if self.auto_increment {
+                self.internal_ram_addr = (self.internal_ram_addr + 1) & 0x7F;
+            }
+        }
+        mapped
+    }
+
+    fn cpu_peek(&self, addr: u16) -> Mapped {
+        match addr {
+            0x4800..=0x4FFF => Mapped::Data(self.internal_ram[self.internal_ram_addr as usize]),
+            0x5000..=0x57FF => Mapped::Data(self.irq_counter_register(false)),
+            0x5800..=0x5FFF => Mapped::Data(self.irq_counter_register(true)),
+            0x6000..=0x7FFF => Mapped::PrgRam((addr & 0x1FFF) as usize),
+            0x8000..=0xFFFF => Mapped::PrgRom(self.prg_rom_offset(addr)),
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) -> Mapped {
+        match addr {
+            0x4800..=0x4FFF => {
+                self.internal_ram[self.internal_ram_addr as usize] = value;
+                if self.auto_increment {
+                    self.internal_ram_addr = (self.internal_ram_addr + 1) & 0x7F;
+                }
+            }
+            0x5000..=0x57FF => {
+                self.irq_counter = (self.irq_counter & 0x7F00) | value as u16;
+                self.irq_pending = false;
+            }
+            0x5800..=0x5FFF => {
+                self.irq_counter = (self.irq_counter & 0x00FF) | ((value & 0x7F) as u16) << 8;
+                self.irq_enabled = value & 0x80 == 0x80;
+                self.irq_pending = false;
+            }
+            0x6000..=0x7FFF if self.prg_ram_writable(addr) => return Mapped::PrgRam((addr & 0x1FFF) as usize),
+            0x8000..=0xDFFF => self.chr_registers[((addr - 0x8000) >> 11) as usize] = value,
+            // bit 6 mutes the sound channels, which aren't emulated anyway
+            0xE000..=0xE7FF => self.prg_banks[0] = value & 0x3F,
+            0xE800..=0xEFFF => {
+                self.prg_banks[1] = value & 0x3F;
+                self.ciram_disabled = value & 0xC0;
+            }
+            0xF000..=0xF7FF => self.prg_banks[2] = value & 0x3F,
+            0xF800..=0xFFFF => {
+                self.prg_ram_protect = value;
+                self.internal_ram_addr = value & 0x7F;
+                self.auto_increment = value & 0x80 == 0x80;
+            }
+            _ => {}
+        }
+        Mapped::Unmapped
+    }
+
+    fn ppu_peek(&self, addr: u16) -> Mapped {
+        self.pattern(addr)
+    }
+
+    fn ppu_write(&mut self, addr: u16, _value: u8) -> Mapped {
+        self.pattern(addr)
+    }
+
+    fn nametable_peek(&self, addr: u16) -> Option<Mapped> {
+        Some(self.bank(8 + ((addr >> 10) & 0x3) as usize, addr, true))
+    }
+
+    fn nametable_write(&mut self, addr: u16, _value: u8) -> Option<Mapped> {
+        // CHR-ROM can't be written to, so only CIRAM comes back
+        self.nametable_peek(addr)
+    }
+
+    fn cpu_clock(&mut self) {
+        if self.irq_enabled && self.irq_counter < IRQ_COUNTER_MAX {
+            self.irq_counter += 1;
+            if self.irq_counter == IRQ_COUNTER_MAX {
+                self.irq_pending = true;
+            }
+        }
+    }
+
+    fn irq_asserted(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn internal_ram(&self) -> &[u8] {
+        &self.internal_ram
+    }
+
+    fn internal_ram_mut(&mut self) -> &mut [u8] {
+        &mut self.internal_ram
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Bus;
+    use crate::test::{banked_rom_image, bus_with_rom};
+
+    fn init() -> Bus {
+        bus_with_rom(&banked_rom_image(19, 8, 8))
+    }
+
+    #[test]
+    fn test_prg_banking_and_ram_protect() {
+        let mut bus = init();
+        bus.cpu_write_u8(0xE000, 3);
+        bus.cpu_write_u8(0xE800, 5);
+        bus.cpu_write_u8(0xF000, 9);
+        assert_eq!(bus.cpu_peek_u8(0x8000), 3);
+        assert_eq!(bus.cpu_peek_u8(0xA000), 5);
+        assert_eq!(bus.cpu_peek_u8(0xC000), 9);
+        assert_eq!(bus.cpu_peek_u8(0xE000), 15);
+
+        bus.cpu_write_u8(0x6000, 0x42);
+        assert_eq!(bus.cpu_peek_u8(0x6000), 0x00);
+        // writable except for the second 2KB
+        bus.cpu_write_u8(0xF800, 0x42);
+        bus.cpu_write_u8(0x6000, 0x42);
+        bus.cpu_write_u8(0x6800, 0x42);
+        assert_eq!(bus.cpu_peek_u8(0x6000), 0x42);
+        assert_eq!(bus.cpu_peek_u8(0x6800), 0x00);
+    }
+
+    #[test]
+    fn test_pattern_tables() {
+        let mut bus = init();
+        for slot in 0..8u16 {
+            bus.cpu_write_u8(0x8000 + slot * 0x800, 50 + slot as u8);
+        }
+        let banks: Vec<u8> = (0..8).map(|slot| bus.ppu.ppu_read_u8(slot * 0x400, true)).collect();
+        assert_eq!(banks, vec![50, 51, 52, 53, 54, 55, 56, 57]);
+
+        // $E0-$FF point at CIRAM, which the PPU resolves itself
+        bus.cpu_write_u8(0x8000, 0xE1);
+        bus.cpu_write_u8(0xC800, 0xE1);
+        bus.ppu.ppu_write_u8(0x0005, 0x42);
+        assert_eq!(bus.ppu.ppu_read_u8(0x2405, true), 0x42);
+        assert_eq!(bus.ppu.ppu_read_u8(0x0005, false), 0x42);
+
+        // unless the left pattern table is forced on CHR-ROM
+        bus.cpu_write_u8(0xE800, 0x40);
+        assert_eq!(bus.ppu.ppu_read_u8(0x0005, true), 0xE1 % 64);
+    }
+
+    #[test]
+    fn test_nametables() {
+        let mut bus = init();
+        // CIRAM page 1, CHR-ROM bank 7, CIRAM page 0 and CHR-ROM bank 9
+        for (slot, bank) in [0xE1, 7, 0xE0, 9].iter().enumerate() {
+            bus.cpu_write_u8(0xC000 + slot as u16 * 0x800, *bank);
+        }
+        bus.ppu.ppu_write_u8(0x2005, 0x11);
+        bus.ppu.ppu_write_u8(0x2805, 0x22);
+        assert_eq!(bus.ppu.ppu_read_u8(0x2005, true), 0x11);
+        assert_eq!(bus.ppu.ppu_read_u8(0x2805, true), 0x22);
+        assert_eq!(bus.ppu.ppu_read_u8(0x2405, true), 7);
+        assert_eq!(bus.ppu.ppu_read_u8(0x2C05, true), 9);
+
+        // ROM nametables can't be written to
+        bus.ppu.ppu_write_u8(0x2405, 0x33);
+        assert_eq!(bus.ppu.ppu_read_u8(0x2405, true), 7);
+    }
+
+    #[test]
+    fn test_internal_ram_port() {
+        let mut bus = init();
+        bus.cpu_write_u8(0xF800, 0x80 | 0x10);
+        for value in [1, 2, 3] {
+            bus.cpu_write_u8(0x4800, value);
+        }
+        bus.cpu_write_u8(0xF800, 0x10);
+        assert_eq!(bus.cpu_read_u8(0x4800, false), 1);
+        // no auto-increment this time
+        assert_eq!(bus.cpu_read_u8(0x4800, false), 1);
+
+        bus.cpu_write_u8(0xF800, 0x91);
+        assert_eq!(bus.cpu_read_u8(0x4800, false), 2);
+        assert_eq!(bus.cpu_read_u8(0x4800, false), 3);
+        assert_eq!(&bus.cartridge.borrow().mapper.as_ref().unwrap().internal_ram()[0x10..0x13], &[1, 2, 3]);
+    }
+
+    #[test]
+    fn test_irq_counter() {
+        let mut bus = init();
+        bus.cpu_write_u8(0x5000, 0xFE);
+        bus.cpu_write_u8(0x5800, 0xFF);
+        assert_eq!(bus.cpu_peek_u8(0x5000), 0xFE);
+        assert_eq!(bus.cpu_peek_u8(0x5800), 0xFF);
+
+        bus.cartridge.borrow_mut().cpu_clock();
+        assert!(bus.cartridge().irq_asserted());
+        // the counter stops at its maximum
+        bus.cartridge.borrow_mut().cpu_clock();
+        assert_eq!(bus.cpu_peek_u8(0x5000), 0xFF);
+
+        bus.cpu_write_u8(0x5800, 0x00);
+        assert!(!bus.cartridge().irq_asserted());
+    }
+}
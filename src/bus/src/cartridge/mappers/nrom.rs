@@ -0,0 +1,84 @@
+use crate::cartridge::mappers::{Mapped, Mapper};
+use crate::inesformat::header::Header;
+
+/// Mapper 0 (NROM)
+///
+/// No bank switching at all. NROM-128 boards have a single 16KB PRG-ROM bank which is mirrored
+/// at $C000 whereas NROM-256 boards fill the whole $8000-$FFFF range. Some boards (Family Basic)
+/// also come with PRG-RAM at $6000-$7FFF.
+pub struct Nrom {
+    prg_rom_mask: u16,
+}
+
+impl Nrom {
+    pub fn new(header: &Header) -> Self {
+        Nrom {
+            prg_rom_mask: if header.prg_rom_banks() > 1 { 0x7FFF } else { 0x3FFF },
+        }
+    }
+}
+
+impl Mapper for Nrom {
+    fn cpu_peek(&self, addr: u16) -> Mapped {
+        match addr {
+            0x6000..=0x7FFF => Mapped::PrgRam((addr & 0x1FFF) as usize),
+            0x8000..=0xFFFF => Mapped::PrgRom((addr & self.prg_rom_mask) as usize),
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, _value: u8) -> Mapped {
+        match addr {
+            0x6000..=0x7FFF => Mapped::PrgRam((addr & 0x1FFF) as usize),
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn ppu_peek(&self, addr: u16) -> Mapped {
+        Mapped::Chr((addr & 0x1FFF) as usize)
+    }
+
+    fn ppu_write(&mut self, addr: u16, _value: u8) -> Mapped {
+        Mapped::Chr((addr & 0x1FFF) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(prg_rom_size: u8) -> Header {
+        let mut header = Header::new();
+        header.prg_rom_size = prg_rom_size;
+        header.chr_rom_size = 1;
+        header
+    }
+
+    #[test]
+    fn test_nrom_128_mirrors_prg_rom() {
+        let mapper = Nrom::new(&header(1));
+        assert_eq!(mapper.cpu_peek(0x8000), Mapped::PrgRom(0x0000));
+        assert_eq!(mapper.cpu_peek(0xBFFF), Mapped::PrgRom(0x3FFF));
+        assert_eq!(mapper.cpu_peek(0xC000), Mapped::PrgRom(0x0000));
+        assert_eq!(mapper.cpu_peek(0xFFFC), Mapped::PrgRom(0x3FFC));
+    }
+
+    #[test]
+    fn test_nrom_256_maps_whole_range() {
+        let mapper = Nrom::new(&header(2));
+        assert_eq!(mapper.cpu_peek(0x8000), Mapped::PrgRom(0x0000));
+        assert_eq!(mapper.cpu_peek(0xC000), Mapped::PrgRom(0x4000));
+        assert_eq!(mapper.cpu_peek(0xFFFC), Mapped::PrgRom(0x7FFC));
+    }
+
+    #[test]
+    fn test_nrom_prg_ram_and_chr() {
+        let mut mapper = Nrom::new(&header(1));
+        assert_eq!(mapper.cpu_peek(0x4020), Mapped::Unmapped);
+        assert_eq!(mapper.cpu_peek(0x6001), Mapped::PrgRam(0x0001));
+        assert_eq!(mapper.cpu_write(0x7FFF, 0x42), Mapped::PrgRam(0x1FFF));
+        assert_eq!(mapper.cpu_write(0x8000, 0x42), Mapped::Unmapped);
+        assert_eq!(mapper.ppu_peek(0x1234), Mapped::Chr(0x1234));
+        assert_eq!(mapper.ppu_read(0x0010), Mapped::Chr(0x0010));
+    }
+}
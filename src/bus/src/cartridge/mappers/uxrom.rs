@@ -0,0 +1,88 @@
+use crate::cartridge::mappers::{Mapped, Mapper};
+use crate::inesformat::format::PRG_ROM_SIZE_FACTOR;
+use crate::inesformat::header::Header;
+
+/// Mapper 2 (UxROM)
+///
+/// Writes to $8000-$FFFF select the 16KB PRG-ROM bank at $8000 while the last bank is fixed at
+/// $C000. CHR isn't banked.
+pub struct Uxrom {
+    // in 16KB units
+    prg_rom_banks: usize,
+    prg_bank: u8,
+}
+
+impl Uxrom {
+    pub fn new(header: &Header) -> Self {
+        Uxrom {
+            prg_rom_banks: header.prg_rom_banks(),
+            prg_bank: 0,
+        }
+    }
+}
+
+impl Mapper for Uxrom {
+    fn cpu_peek(&self, addr: u16) -> Mapped {
+        match addr {
+            0x8000..=0xBFFF => {
+                let bank = self.prg_bank as usize % self.prg_rom_banks;
+                Mapped::PrgRom(bank * PRG_ROM_SIZE_FACTOR + (addr & 0x3FFF) as usize)
+            }
+            0xC000..=0xFFFF => {
+                let bank = self.prg_rom_banks - 1;
+                Mapped::PrgRom(bank * PRG_ROM_SIZE_FACTOR + (addr & 0x3FFF) as usize)
+            }
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) -> Mapped {
+        if addr >= 0x8000 {
+            self.prg_bank = value;
+        }
+        Mapped::Unmapped
+    }
+
+    fn ppu_peek(&self, addr: u16) -> Mapped {
+        Mapped::Chr((addr & 0x1FFF) as usize)
+    }
+
+    fn ppu_write(&mut self, addr: u16, _value: u8) -> Mapped {
+        Mapped::Chr((addr & 0x1FFF) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test::{banked_rom_image, bus_with_rom};
+
+    #[test]
+    fn test_prg_banking() {
+        let mut bus = bus_with_rom(&banked_rom_image(2, 8, 1));
+        assert_eq!(bus.cpu_peek_u8(0x8000), 0);
+        assert_eq!(bus.cpu_peek_u8(0xC000), 14);
+
+        bus.cpu_write_u8(0x8000, 0x05);
+        assert_eq!(bus.cpu_peek_u8(0x8000), 10);
+        assert_eq!(bus.cpu_peek_u8(0xA000), 11);
+        assert_eq!(bus.cpu_peek_u8(0xC000), 14);
+        assert_eq!(bus.cpu_peek_u8(0xE000), 15);
+
+        // banks wrap around
+        bus.cpu_write_u8(0xFFF0, 0x09);
+        assert_eq!(bus.cpu_peek_u8(0x8000), 2);
+    }
+
+    #[test]
+    fn test_bus_conflicts() {
+        let mut rom = banked_rom_image(2, 8, 1);
+        rom[10] |= 0x20;
+        let mut bus = bus_with_rom(&rom);
+
+        // $8001 holds 0xEA so the written value gets ANDed with it
+        bus.cpu_write_u8(0x8001, 0x05);
+        assert_eq!(bus.cpu_peek_u8(0x8000), 0);
+        bus.cpu_write_u8(0x8001, 0x07);
+        assert_eq!(bus.cpu_peek_u8(0x8000), 4);
+    }
+}
@@ -0,0 +1,320 @@
+use crate::cartridge::Mirroring;
+use crate::cartridge::mappers::vrc_irq::VrcIrq;
+use crate::cartridge::mappers::{Mapped, Mapper};
+use crate::inesformat::format::{CHR_ROM_SIZE_FACTOR, PRG_ROM_SIZE_FACTOR};
+use crate::inesformat::header::Header;
+
+/// VRC2 and VRC4 boards. The chips only have two register select pins (A0/A1), each board
+/// connects them to different CPU address lines.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum VrcBoard {
+    Vrc2a,
+    Vrc2b,
+    Vrc2c,
+    Vrc4a,
+    Vrc4b,
+    Vrc4c,
+    Vrc4d,
+    Vrc4e,
+    Vrc4f,
+    /// iNES 1.0 dumps (or submapper 0) don't say which board they came from, so both wirings
+    /// sharing the mapper number are listened to at once. Holds the mapper number.
+    Unknown(u16),
+}
+
+impl VrcBoard {
+    pub fn from_ids(mapper_id: u16, submapper: u8) -> Self {
+        match (mapper_id, submapper) {
+            (21, 1) => VrcBoard::Vrc4a,
+            (21, 2) => VrcBoard::Vrc4c,
+            (22, _) => VrcBoard::Vrc2a,
+            (23, 1) => VrcBoard::Vrc4f,
+            (23, 2) => VrcBoard::Vrc4e,
+            (23, 3) => VrcBoard::Vrc2b,
+            (25, 1) => VrcBoard::Vrc4b,
+            (25, 2) => VrcBoard::Vrc4d,
+            (25, 3) => VrcBoard::Vrc2c,
+            (mapper_id, _) => VrcBoard::Unknown(mapper_id),
+        }
+    }
+
+    /// CPU address lines (as masks) connected to A0 and A1
+    fn wiring(&self) -> (u16, u16) {
+        match self {
+            VrcBoard::Vrc4a => (0x02, 0x04),
+            VrcBoard::Vrc4c => (0x40, 0x80),
+            VrcBoard::Vrc2a | VrcBoard::Vrc4b | VrcBoard::Vrc2c => (0x02, 0x01),
+            VrcBoard::Vrc4d => (0x08, 0x04),
+            VrcBoard::Vrc4e => (0x04, 0x08),
+            VrcBoard::Vrc2b | VrcBoard::Vrc4f => (0x01, 0x02),
+            VrcBoard::Unknown(21) => (0x42, 0x84),
+            VrcBoard::Unknown(25) => (0x0A, 0x05),
+            VrcBoard::Unknown(_) => (0x05, 0x0A),
+        }
+    }
+
+    fn is_vrc2(&self) -> bool {
+        matches!(self, VrcBoard::Vrc2a | VrcBoard::Vrc2b | VrcBoard::Vrc2c)
+    }
+}
+
+/// Mappers 21, 22, 23 and 25 (VRC2 and VRC4)
+///
+/// Two switchable 8KB PRG-ROM banks plus the last two fixed (VRC4 can swap which of $8000 and
+/// $C000 is switchable), eight 1KB CHR banks each written a nibble at a time and switchable
+/// mirroring. VRC4 adds the IRQ counter, VRC2 is a subset of it.
+pub struct Vrc4 {
+    board: VrcBoard,
+    // in 8KB units
+    prg_rom_banks: usize,
+    // in 1KB units
+    chr_banks: usize,
+    prg_banks: [u8; 2],
+    prg_swap: bool,
+    chr_registers: [u16; 8],
+    mirroring: Mirroring,
+    irq: VrcIrq,
+}
+
+impl Vrc4 {
+    pub fn new(header: &Header, board: VrcBoard) -> Self {
+        Vrc4 {
+            board,
+            prg_rom_banks: header.prg_rom_banks() * 2,
+            chr_banks: header.chr_banks() * CHR_ROM_SIZE_FACTOR / 0x400,
+            prg_banks: [0; 2],
+            prg_swap: false,
+            chr_registers: [0; 8],
+            mirroring: Mirroring::Vertical,
+            irq: VrcIrq::new(),
+        }
+    }
+
+    /// Turns a CPU address into the register the chip sees ($x000-$x003)
+    fn register(&self, addr: u16) -> u16 {
+        let (a0, a1) = self.board.wiring();
+        (addr & 0xF000) | (addr & a0 != 0) as u16 | ((addr & a1 != 0) as u16) << 1
+    }
+
+    fn prg_rom_offset(&self, addr: u16) -> usize {
+        let second_last = self.prg_rom_banks - 2;
+        let bank = match (addr >> 13) & 0x3 {
+            0 if self.prg_swap => second_last,
+            0 => self.prg_banks[0] as usize,
+            1 => self.prg_banks[1] as usize,
+            2 if self.prg_swap => self.prg_banks[0] as usize,
+            2 => second_last,
+            _ => self.prg_rom_banks - 1,
+        };
+        (bank % self.prg_rom_banks) * (PRG_ROM_SIZE_FACTOR / 2) + (addr & 0x1FFF) as usize
+    }
+
+    fn chr_offset(&self, addr: u16) -> usize {
+        let mut bank = self.chr_registers[(addr >> 10) as usize & 0x7] as usize;
+        // VRC2a doesn't connect the lowest bit
+        if self.board == VrcBoard::Vrc2a {
+            bank >>= 1;
+        }
+        (bank % self.chr_banks) * 0x400 + (addr & 0x3FF) as usize
+    }
+}
+
+impl Mapper for Vrc4 {
+    fn cpu_peek(&self, addr: u16) -> Mapped {
+        match addr {
+            0x6000..=0x7FFF => Mapped::PrgRam((addr & 0x1FFF) as usize),
+            0x8000..=0xFFFF => Mapped::PrgRom(self.prg_rom_offset(addr)),
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) -> Mapped {
+        if addr < 0x8000 {
+            return match addr {
+                0x6000..=0x7FFF => Mapped::PrgRam((addr & 0x1FFF) as usize),
+                _ => Mapped::Unmapped,
+            };
+        }
+
+        let vrc2 = self.board.is_vrc2();
+        match self.register(addr) {
+            0x8000..=0x8003 => self.prg_banks[0] = value & 0x1F,
+            0x9000..=0x9003 if vrc2 => {
+                self.mirroring = if value & 0x1 == 0 { Mirroring::Vertical } else { Mirroring::Horizontal };
+            }
+            0x9000 | 0x9001 => {
+                self.mirroring = match value & 0x3 {
+                    0 => Mirroring::Vertical,
+                    1 => Mirroring::Horizontal,
+                    2 => Mirroring::SingleScreenLower,
+                    _ => Mirroring::SingleScreenUpper,
+                };
+            }
+            0x9002 | 0x9003 => self.prg_swap = value & 0x2 == 0x2,
+            0xA000..=0xA003 => self.prg_banks[1] = value & 0x1F,
+            register @ 0xB000..=0xEFFF => {
+                // each bank takes two registers: low nibble first, then the high bits
+                let slot = (((register - 0xB000) >> 12) * 2 + ((register & 0x2) >> 1)) as usize;
+                let bank = &mut self.chr_registers[slot];
+                *bank = match register & 0x1 {
+                    0 => (*bank & 0x1F0) | (value & 0x0F) as u16,
+                    _ => (*bank & 0x00F) | ((value & 0x1F) as u16) << 4,
+                };
+            }
+            _ if vrc2 => {}
+            0xF000 => self.irq.write_latch_nibble(false, value),
+            0xF001 => self.irq.write_latch_nibble(true, value),
+            0xF002 => self.irq.write_control(value),
+            0xF003 => self.irq.acknowledge(),
+            _ => {}
+        }
+        Mapped::Unmapped
+    }
+
+    fn ppu_peek(&self, addr: u16) -> Mapped {
+        Mapped::Chr(self.chr_offset(addr))
+    }
+
+    fn ppu_write(&mut self, addr: u16, _value: u8) -> Mapped {
+        Mapped::Chr(self.chr_offset(addr))
+    }
+
+    fn cpu_clock(&mut self) {
+        self.irq.cpu_clock();
+    }
+
+    fn irq_asserted(&self) -> bool {
+        self.irq.pending()
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(self.mirroring)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Bus;
+    use crate::test::{banked_rom_image, bus_with_rom};
+
+    fn init(mapper_id: u8, board: VrcBoard) -> Bus {
+        let rom = banked_rom_image(mapper_id, 8, 8);
+        let bus = bus_with_rom(&rom);
+        let header = Header::from(&rom).unwrap();
+        bus.cartridge.borrow_mut().mapper = Some(Box::new(Vrc4::new(&header, board)));
+        bus
+    }
+
+    /// CPU address for register $x000-$x003 on a given board
+    fn addr(board: VrcBoard, base: u16, register: u16) -> u16 {
+        let (a0, a1) = board.wiring();
+        // boards of unknown origin listen to both wirings, use the first one
+        let (a0, a1) = (a0 & a0.wrapping_neg(), a1 & a1.wrapping_neg());
+        base | if register & 0x1 == 1 { a0 } else { 0 } | if register & 0x2 == 2 { a1 } else { 0 }
+    }
+
+    fn boards() -> Vec<(u8, VrcBoard)> {
+        vec![
+            (21, VrcBoard::Vrc4a),
+            (21, VrcBoard::Vrc4c),
+            (21, VrcBoard::Unknown(21)),
+            (22, VrcBoard::Vrc2a),
+            (23, VrcBoard::Vrc4f),
+            (23, VrcBoard::Vrc4e),
+            (23, VrcBoard::Vrc2b),
+            (23, VrcBoard::Unknown(23)),
+            (25, VrcBoard::Vrc4b),
+            (25, VrcBoard::Vrc4d),
+            (25, VrcBoard::Vrc2c),
+            (25, VrcBoard::Unknown(25)),
+        ]
+    }
+
+    #[test]
+    fn test_board_selection() {
+        assert_eq!(VrcBoard::from_ids(21, 0), VrcBoard::Unknown(21));
+        assert_eq!(VrcBoard::from_ids(21, 2), VrcBoard::Vrc4c);
+        assert_eq!(VrcBoard::from_ids(22, 0), VrcBoard::Vrc2a);
+        assert_eq!(VrcBoard::from_ids(23, 3), VrcBoard::Vrc2b);
+        assert_eq!(VrcBoard::from_ids(25, 2), VrcBoard::Vrc4d);
+    }
+
+    #[test]
+    fn test_address_wirings() {
+        for (mapper_id, board) in boards() {
+            let mut bus = init(mapper_id, board);
+            bus.cpu_write_u8(addr(board, 0x8000, 0), 3);
+            bus.cpu_write_u8(addr(board, 0xA000, 0), 5);
+            assert_eq!(bus.cpu_peek_u8(0x8000), 3, "{:?}", board);
+            assert_eq!(bus.cpu_peek_u8(0xA000), 5, "{:?}", board);
+            assert_eq!(bus.cpu_peek_u8(0xC000), 14, "{:?}", board);
+            assert_eq!(bus.cpu_peek_u8(0xE000), 15, "{:?}", board);
+
+            // every CHR slot through its low and high registers: $B000/$B001 is slot 0,
+            // $B002/$B003 slot 1 and so on
+            for slot in 0..8u16 {
+                let base = 0xB000 + (slot / 2) * 0x1000;
+                let register = (slot % 2) * 2;
+                bus.cpu_write_u8(addr(board, base, register), (slot as u8 * 2 + 4) & 0x0F);
+                bus.cpu_write_u8(addr(board, base, register + 1), (slot as u8 * 2 + 4) >> 4);
+            }
+            let banks: Vec<u8> = (0..8).map(|slot| bus.cartridge().ppu_peek(slot * 0x400)).collect();
+            let expected: Vec<u8> = match board {
+                VrcBoard::Vrc2a => (0..8).map(|slot| slot + 2).collect(),
+                _ => (0..8).map(|slot| slot * 2 + 4).collect(),
+            };
+            assert_eq!(banks, expected, "{:?}", board);
+
+            bus.cpu_write_u8(addr(board, 0x9000, 0), 1);
+            assert_eq!(bus.cartridge().mirroring(), Mirroring::Horizontal, "{:?}", board);
+        }
+    }
+
+    #[test]
+    fn test_vrc4_prg_swap_and_mirroring() {
+        let board = VrcBoard::Vrc4a;
+        let mut bus = init(21, board);
+        bus.cpu_write_u8(addr(board, 0x8000, 0), 3);
+        bus.cpu_write_u8(addr(board, 0x9000, 2), 0x2);
+        assert_eq!(bus.cpu_peek_u8(0x8000), 14);
+        assert_eq!(bus.cpu_peek_u8(0xC000), 3);
+
+        bus.cpu_write_u8(addr(board, 0x9000, 0), 3);
+        assert_eq!(bus.cartridge().mirroring(), Mirroring::SingleScreenUpper);
+
+        // VRC2 only has the lowest mirroring bit and no swapping
+        let board = VrcBoard::Vrc2b;
+        let mut bus = init(23, board);
+        bus.cpu_write_u8(addr(board, 0x8000, 0), 3);
+        bus.cpu_write_u8(addr(board, 0x9000, 2), 0x2);
+        assert_eq!(bus.cpu_peek_u8(0x8000), 3);
+        assert_eq!(bus.cartridge().mirroring(), Mirroring::Vertical);
+    }
+
+    #[test]
+    fn test_vrc4_irq() {
+        let board = VrcBoard::Vrc4e;
+        let mut bus = init(23, board);
+        bus.cpu_write_u8(addr(board, 0xF000, 0), 0xE);
+        bus.cpu_write_u8(addr(board, 0xF000, 1), 0xF);
+        bus.cpu_write_u8(addr(board, 0xF000, 2), 0x6);
+        // 0xFE, 0xFF, overflow
+        bus.cartridge.borrow_mut().cpu_clock();
+        assert!(!bus.cartridge().irq_asserted());
+        bus.cartridge.borrow_mut().cpu_clock();
+        assert!(bus.cartridge().irq_asserted());
+
+        bus.cpu_write_u8(addr(board, 0xF000, 3), 0);
+        assert!(!bus.cartridge().irq_asserted());
+
+        // VRC2 doesn't have one
+        let board = VrcBoard::Vrc2c;
+        let mut bus = init(25, board);
+        bus.cpu_write_u8(addr(board, 0xF000, 2), 0x6);
+        for _ in 0..0x100 {
+            bus.cartridge.borrow_mut().cpu_clock();
+        }
+        assert!(!bus.cartridge().irq_asserted());
+    }
+}
@@ -0,0 +1,210 @@
+use crate::cartridge::Mirroring;
+use crate::cartridge::mappers::vrc_irq::VrcIrq;
+use crate::cartridge::mappers::{Mapped, Mapper};
+use crate::inesformat::format::{CHR_ROM_SIZE_FACTOR, PRG_ROM_SIZE_FACTOR};
+use crate::inesformat::header::Header;
+
+/// Mappers 24 and 26 (VRC6a and VRC6b, which swap A0 and A1 around)
+///
+/// A switchable 16KB PRG-ROM bank at $8000, a switchable 8KB bank at $C000 and the last 8KB
+/// fixed. CHR is made of eight 1KB registers which $B003 arranges as 1KB or 2KB banks. The chip
+/// also has three extra sound channels at $9000-$B002, see Mapper::audio_output.
+pub struct Vrc6 {
+    // mapper 26 connects A0 to the chip's A1 and vice versa
+    swapped_lines: bool,
+    // in 8KB units
+    prg_rom_banks: usize,
+    // in 1KB units
+    chr_banks: usize,
+    prg_16k_bank: u8,
+    prg_8k_bank: u8,
+    chr_registers: [u8; 8],
+    banking_mode: u8,
+    irq: VrcIrq,
+}
+
+impl Vrc6 {
+    pub fn new(header: &Header) -> Self {
+        Vrc6 {
+            swapped_lines: header.mapper_id() == 26,
+            prg_rom_banks: header.prg_rom_banks() * 2,
+            chr_banks: header.chr_banks() * CHR_ROM_SIZE_FACTOR / 0x400,
+            prg_16k_bank: 0,
+            prg_8k_bank: 0,
+            chr_registers: [0; 8],
+            banking_mode: 0,
+            irq: VrcIrq::new(),
+        }
+    }
+
+    fn register(&self, addr: u16) -> u16 {
+        match self.swapped_lines {
+            true => (addr & 0xF000) | (addr & 0x1) << 1 | (addr & 0x2) >> 1,
+            false => addr & 0xF003,
+        }
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        self.banking_mode & 0x80 == 0x80
+    }
+
+    fn prg_rom_offset(&self, addr: u16) -> usize {
+        let bank = match addr {
+            0x8000..=0xBFFF => (self.prg_16k_bank as usize) * 2 + ((addr >> 13) & 0x1) as usize,
+            0xC000..=0xDFFF => self.prg_8k_bank as usize,
+            _ => self.prg_rom_banks - 1,
+        };
+        (bank % self.prg_rom_banks) * (PRG_ROM_SIZE_FACTOR / 2) + (addr & 0x1FFF) as usize
+    }
+
+    fn chr_offset(&self, addr: u16) -> usize {
+        let slot = (addr >> 10) as usize & 0x7;
+        // with bit 5 set, PPU A10 picks the half of a 2KB bank, otherwise the 1KB bank repeats
+        let two_k = |register: usize| -> usize {
+            let bank = self.chr_registers[register] as usize;
+            match self.banking_mode & 0x20 {
+                0 => bank,
+                _ => (bank & !0x1) | (slot & 0x1),
+            }
+        };
+        let bank = match (self.banking_mode & 0x3, slot) {
+            (0, _) => self.chr_registers[slot] as usize,
+            (1, _) => two_k(slot / 2),
+            (_, 0..=3) => self.chr_registers[slot] as usize,
+            (_, _) => two_k(4 + (slot - 4) / 2),
+        };
+        (bank % self.chr_banks) * 0x400 + (addr & 0x3FF) as usize
+    }
+}
+
+impl Mapper for Vrc6 {
+    fn cpu_peek(&self, addr: u16) -> Mapped {
+        match addr {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => Mapped::PrgRam((addr & 0x1FFF) as usize),
+            0x8000..=0xFFFF => Mapped::PrgRom(self.prg_rom_offset(addr)),
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) -> Mapped {
+        match addr {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => return Mapped::PrgRam((addr & 0x1FFF) as usize),
+            0x8000..=0xFFFF => {}
+            _ => return Mapped::Unmapped,
+        }
+
+        match self.register(addr) {
+            0x8000..=0x8003 => self.prg_16k_bank = value & 0x0F,
+            // expansion audio isn't emulated, its registers are ignored for now
+            0x9000..=0xB002 => {}
+            0xB003 => self.banking_mode = value,
+            0xC000..=0xC003 => self.prg_8k_bank = value & 0x1F,
+            register @ (0xD000..=0xD003 | 0xE000..=0xE003) => {
+                let slot = ((register - 0xD000) >> 12) * 4 + (register & 0x3);
+                self.chr_registers[slot as usize] = value;
+            }
+            0xF000 => self.irq.write_latch(value),
+            0xF001 => self.irq.write_control(value),
+            0xF002 => self.irq.acknowledge(),
+            _ => {}
+        }
+        Mapped::Unmapped
+    }
+
+    fn ppu_peek(&self, addr: u16) -> Mapped {
+        Mapped::Chr(self.chr_offset(addr))
+    }
+
+    fn ppu_write(&mut self, addr: u16, _value: u8) -> Mapped {
+        Mapped::Chr(self.chr_offset(addr))
+    }
+
+    fn cpu_clock(&mut self) {
+        self.irq.cpu_clock();
+    }
+
+    fn irq_asserted(&self) -> bool {
+        self.irq.pending()
+    }
+
+    // only the usual CIRAM arrangements are supported, not CHR-ROM nametables (bit 4)
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(match (self.banking_mode >> 2) & 0x3 {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Bus;
+    use crate::test::{banked_rom_image, bus_with_rom};
+
+    /// Register $x000-$x003 as seen from the CPU on each board
+    fn addr(mapper_id: u8, base: u16, register: u16) -> u16 {
+        match mapper_id {
+            26 => base | (register & 0x1) << 1 | (register & 0x2) >> 1,
+            _ => base | register,
+        }
+    }
+
+    #[test]
+    fn test_address_wirings() {
+        for mapper_id in [24, 26] {
+            let mut bus = bus_with_rom(&banked_rom_image(mapper_id, 8, 8));
+            bus.cpu_write_u8(addr(mapper_id, 0x8000, 0), 3);
+            bus.cpu_write_u8(addr(mapper_id, 0xC000, 0), 9);
+            assert_eq!(bus.cpu_peek_u8(0x8000), 6);
+            assert_eq!(bus.cpu_peek_u8(0xA000), 7);
+            assert_eq!(bus.cpu_peek_u8(0xC000), 9);
+            assert_eq!(bus.cpu_peek_u8(0xE000), 15);
+
+            for slot in 0..8u16 {
+                let base = if slot < 4 { 0xD000 } else { 0xE000 };
+                bus.cpu_write_u8(addr(mapper_id, base, slot & 0x3), 20 + slot as u8);
+            }
+            let banks: Vec<u8> = (0..8).map(|slot| bus.cartridge().ppu_peek(slot * 0x400)).collect();
+            assert_eq!(banks, vec![20, 21, 22, 23, 24, 25, 26, 27], "mapper {}", mapper_id);
+
+            // $B003 is only reachable through both lines being high
+            bus.cpu_write_u8(addr(mapper_id, 0xB000, 3), 0x84);
+            assert_eq!(bus.cartridge().mirroring(), Mirroring::Horizontal);
+            bus.cpu_write_u8(0x6000, 0x42);
+            assert_eq!(bus.cpu_peek_u8(0x6000), 0x42);
+
+            bus.cpu_write_u8(addr(mapper_id, 0xF000, 0), 0xFF);
+            bus.cpu_write_u8(addr(mapper_id, 0xF000, 1), 0x6);
+            bus.cartridge.borrow_mut().cpu_clock();
+            assert!(bus.cartridge().irq_asserted());
+            bus.cpu_write_u8(addr(mapper_id, 0xF000, 2), 0);
+            assert!(!bus.cartridge().irq_asserted());
+        }
+    }
+
+    #[test]
+    fn test_chr_banking_modes() {
+        let mut bus = bus_with_rom(&banked_rom_image(24, 8, 8));
+        for slot in 0..8u16 {
+            let base = if slot < 4 { 0xD000 } else { 0xE000 };
+            bus.cpu_write_u8(base | (slot & 0x3), 20 + slot as u8);
+        }
+        let banks = |bus: &Bus| -> Vec<u8> {
+            (0..8).map(|slot| bus.cartridge().ppu_peek(slot * 0x400)).collect()
+        };
+
+        // 2KB banks from R0-R3, A10 comes from the PPU
+        bus.cpu_write_u8(0xB003, 0x21);
+        assert_eq!(banks(&bus), vec![20, 21, 20, 21, 22, 23, 22, 23]);
+        // without bit 5 the same 1KB shows up twice
+        bus.cpu_write_u8(0xB003, 0x01);
+        assert_eq!(banks(&bus), vec![20, 20, 21, 21, 22, 22, 23, 23]);
+
+        // 1KB banks on the left and 2KB banks from R4-R5 on the right
+        bus.cpu_write_u8(0xB003, 0x22);
+        assert_eq!(banks(&bus), vec![20, 21, 22, 23, 24, 25, 24, 25]);
+    }
+}
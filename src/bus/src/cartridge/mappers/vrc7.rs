@@ -0,0 +1,157 @@
+use crate::cartridge::Mirroring;
+use crate::cartridge::mappers::vrc_irq::VrcIrq;
+use crate::cartridge::mappers::{Mapped, Mapper};
+use crate::inesformat::format::{CHR_ROM_SIZE_FACTOR, PRG_ROM_SIZE_FACTOR};
+use crate::inesformat::header::Header;
+
+/// Mapper 85 (VRC7)
+///
+/// Three switchable 8KB PRG-ROM banks with the last one fixed and eight 1KB CHR banks. Each
+/// register pair is told apart by a single address line: A4 on VRC7a (Lagrange Point) and A3 on
+/// VRC7b (Tiny Toon Adventures 2), both are listened to. The FM synthesizer at $9010/$9030 is
+/// left out, see Mapper::audio_output.
+pub struct Vrc7 {
+    // in 8KB units
+    prg_rom_banks: usize,
+    // in 1KB units
+    chr_banks: usize,
+    prg_banks: [u8; 3],
+    chr_registers: [u8; 8],
+    control: u8,
+    irq: VrcIrq,
+}
+
+// A4 (VRC7a) or A3 (VRC7b)
+const SECOND_REGISTER_LINES: u16 = 0x18;
+
+impl Vrc7 {
+    pub fn new(header: &Header) -> Self {
+        Vrc7 {
+            prg_rom_banks: header.prg_rom_banks() * 2,
+            chr_banks: header.chr_banks() * CHR_ROM_SIZE_FACTOR / 0x400,
+            prg_banks: [0; 3],
+            chr_registers: [0; 8],
+            control: 0,
+            irq: VrcIrq::new(),
+        }
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        self.control & 0x80 == 0x80
+    }
+
+    fn prg_rom_offset(&self, addr: u16) -> usize {
+        let bank = match addr {
+            0x8000..=0xDFFF => self.prg_banks[((addr - 0x8000) >> 13) as usize] as usize,
+            _ => self.prg_rom_banks - 1,
+        };
+        (bank % self.prg_rom_banks) * (PRG_ROM_SIZE_FACTOR / 2) + (addr & 0x1FFF) as usize
+    }
+
+    fn chr_offset(&self, addr: u16) -> usize {
+        let bank = self.chr_registers[(addr >> 10) as usize & 0x7] as usize;
+        (bank % self.chr_banks) * 0x400 + (addr & 0x3FF) as usize
+    }
+}
+
+impl Mapper for Vrc7 {
+    fn cpu_peek(&self, addr: u16) -> Mapped {
+        match addr {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => Mapped::PrgRam((addr & 0x1FFF) as usize),
+            0x8000..=0xFFFF => Mapped::PrgRom(self.prg_rom_offset(addr)),
+            _ => Mapped::Unmapped,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, value: u8) -> Mapped {
+        match addr {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => return Mapped::PrgRam((addr & 0x1FFF) as usize),
+            0x8000..=0xFFFF => {}
+            _ => return Mapped::Unmapped,
+        }
+
+        let second = addr & SECOND_REGISTER_LINES != 0;
+        match (addr & 0xF000, second) {
+            (0x8000, false) => self.prg_banks[0] = value & 0x3F,
+            (0x8000, true) => self.prg_banks[1] = value & 0x3F,
+            (0x9000, false) => self.prg_banks[2] = value & 0x3F,
+            // expansion audio isn't emulated, its registers are ignored for now
+            (0x9000, true) => {}
+            (base @ 0xA000..=0xD000, second) => {
+                let slot = ((base - 0xA000) >> 12) * 2 + second as u16;
+                self.chr_registers[slot as usize] = value;
+            }
+            (0xE000, false) => self.control = value,
+            (0xE000, true) => self.irq.write_latch(value),
+            (0xF000, false) => self.irq.write_control(value),
+            (0xF000, true) => self.irq.acknowledge(),
+            _ => {}
+        }
+        Mapped::Unmapped
+    }
+
+    fn ppu_peek(&self, addr: u16) -> Mapped {
+        Mapped::Chr(self.chr_offset(addr))
+    }
+
+    fn ppu_write(&mut self, addr: u16, _value: u8) -> Mapped {
+        Mapped::Chr(self.chr_offset(addr))
+    }
+
+    fn cpu_clock(&mut self) {
+        self.irq.cpu_clock();
+    }
+
+    fn irq_asserted(&self) -> bool {
+        self.irq.pending()
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(match self.control & 0x3 {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::{banked_rom_image, bus_with_rom};
+
+    #[test]
+    fn test_address_wirings() {
+        // VRC7a uses A4 and VRC7b A3 for the second register of each pair
+        for line in [0x10, 0x08] {
+            let mut bus = bus_with_rom(&banked_rom_image(85, 8, 8));
+            bus.cpu_write_u8(0x8000, 3);
+            bus.cpu_write_u8(0x8000 | line, 5);
+            bus.cpu_write_u8(0x9000, 9);
+            assert_eq!(bus.cpu_peek_u8(0x8000), 3);
+            assert_eq!(bus.cpu_peek_u8(0xA000), 5);
+            assert_eq!(bus.cpu_peek_u8(0xC000), 9);
+            assert_eq!(bus.cpu_peek_u8(0xE000), 15);
+
+            for slot in 0..8u16 {
+                let addr = 0xA000 + (slot / 2) * 0x1000 + if slot % 2 == 1 { line } else { 0 };
+                bus.cpu_write_u8(addr, 30 + slot as u8);
+            }
+            let banks: Vec<u8> = (0..8).map(|slot| bus.cartridge().ppu_peek(slot * 0x400)).collect();
+            assert_eq!(banks, vec![30, 31, 32, 33, 34, 35, 36, 37]);
+
+            bus.cpu_write_u8(0xE000, 0x81);
+            assert_eq!(bus.cartridge().mirroring(), Mirroring::Horizontal);
+            bus.cpu_write_u8(0x6000, 0x42);
+            assert_eq!(bus.cpu_peek_u8(0x6000), 0x42);
+
+            bus.cpu_write_u8(0xE000 | line, 0xFF);
+            bus.cpu_write_u8(0xF000, 0x6);
+            bus.cartridge.borrow_mut().cpu_clock();
+            assert!(bus.cartridge().irq_asserted());
+            bus.cpu_write_u8(0xF000 | line, 0);
+            assert!(!bus.cartridge().irq_asserted());
+        }
+    }
+}
@@ -0,0 +1,133 @@
+// the prescaler counts down by 3 every CPU cycle, which adds up to one PPU scanline (341 dots)
+const PRESCALER_RELOAD: i16 = 341;
+
+/// IRQ counter shared by VRC4, VRC6 and VRC7.
+///
+/// Konami didn't look at the PPU at all, the 8-bit counter counts up from the latch and fires
+/// when it overflows. It's either clocked every CPU cycle (cycle mode) or by a prescaler which
+/// approximates scanlines out of CPU cycles (scanline mode).
+pub(super) struct VrcIrq {
+    latch: u8,
+    counter: u8,
+    prescaler: i16,
+    enabled: bool,
+    enable_after_ack: bool,
+    cycle_mode: bool,
+    pending: bool,
+}
+
+impl VrcIrq {
+    pub(super) fn new() -> Self {
+        VrcIrq {
+            latch: 0,
+            counter: 0,
+            prescaler: PRESCALER_RELOAD,
+            enabled: false,
+            enable_after_ack: false,
+            cycle_mode: false,
+            pending: false,
+        }
+    }
+
+    pub(super) fn write_latch(&mut self, value: u8) {
+        self.latch = value;
+    }
+
+    /// VRC4 splits the latch in two nibble sized registers
+    pub(super) fn write_latch_nibble(&mut self, high: bool, value: u8) {
+        self.latch = match high {
+            true => (self.latch & 0x0F) | (value << 4),
+            false => (self.latch & 0xF0) | (value & 0x0F),
+        };
+    }
+
+    pub(super) fn write_control(&mut self, value: u8) {
+        self.enable_after_ack = value & 0x1 == 0x1;
+        self.enabled = value & 0x2 == 0x2;
+        self.cycle_mode = value & 0x4 == 0x4;
+        self.pending = false;
+        if self.enabled {
+            self.counter = self.latch;
+            self.prescaler = PRESCALER_RELOAD;
+        }
+    }
+
+    pub(super) fn acknowledge(&mut self) {
+        self.pending = false;
+        self.enabled = self.enable_after_ack;
+    }
+
+    fn clock_counter(&mut self) {
+        if self.counter == 0xFF {
+            self.counter = self.latch;
+            self.pending = true;
+        } else {
+            self.counter += 1;
+        }
+    }
+
+    pub(super) fn cpu_clock(&mut self) {
+        if !self.enabled {
+            return;
+        }
+        if self.cycle_mode {
+            self.clock_counter();
+            return;
+        }
+        self.prescaler -= 3;
+        if self.prescaler <= 0 {
+            self.prescaler += PRESCALER_RELOAD;
+            self.clock_counter();
+        }
+    }
+
+    pub(super) fn pending(&self) -> bool {
+        self.pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cycle_mode() {
+        let mut irq = VrcIrq::new();
+        irq.write_latch(0xFD);
+        irq.write_control(0x6);
+        // 0xFD -> 0xFE -> 0xFF -> overflow
+        irq.cpu_clock();
+        irq.cpu_clock();
+        assert!(!irq.pending());
+        irq.cpu_clock();
+        assert!(irq.pending());
+
+        // acknowledging without the A bit disables the counter
+        irq.acknowledge();
+        for _ in 0..0x100 {
+            irq.cpu_clock();
+        }
+        assert!(!irq.pending());
+    }
+
+    #[test]
+    fn test_scanline_mode() {
+        let mut irq = VrcIrq::new();
+        irq.write_latch(0xFE);
+        irq.write_control(0x3);
+        // two scanlines worth of CPU cycles (113.667 each)
+        for _ in 0..227 {
+            irq.cpu_clock();
+        }
+        assert!(!irq.pending());
+        irq.cpu_clock();
+        assert!(irq.pending());
+
+        // the A bit keeps it going after the acknowledge, and the counter was reloaded
+        irq.acknowledge();
+        for _ in 0..228 {
+            irq.cpu_clock();
+        }
+        assert!(irq.pending());
+    }
+}
@@ -1,10 +1,57 @@
+use crate::inesformat::error::RomError;
 use crate::inesformat::format::INESFormat;
+use crate::inesformat::header::Timing;
+use crate::memory::BusDevice;
+use self::mappers::{new_mapper, Mapped, Mapper};
+use self::mappers::mmc3::Mmc3Revision;
+use std::fs;
+use std::io::{self, ErrorKind, Read};
 use std::mem::swap;
+use std::path::{Path, PathBuf};
+
+pub mod mappers;
+
+const TRAINER_OFFSET: usize = 0x1000;
+
+/// How the PPU's 2KB of nametable RAM is arranged in the $2000-$2FFF range
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Mirroring {
+    Horizontal,
+    Vertical,
+    SingleScreenLower,
+    SingleScreenUpper,
+    FourScreen,
+}
 
 pub struct Cartridge {
     prg_rom: Vec<u8>,
     chr_rom: Vec<u8>,
-    mapper_id: u8,
+    // boards without CHR-ROM have RAM the CPU fills in through the PPU instead
+    chr_ram: Vec<u8>,
+    prg_ram: Vec<u8>,
+    // 512 bytes some dumps want at $7000-$71FF, left over from copier devices
+    trainer: Vec<u8>,
+    mapper_id: u16,
+    mirroring: Mirroring,
+    // the CPU and PRG-ROM both drive the data bus when writing to ROM, so the value the mapper
+    // sees is the AND of the two
+    bus_conflicts: bool,
+    battery: bool,
+    // .sav file next to the ROM and what it currently holds, so unchanged RAM isn't rewritten
+    save_path: Option<PathBuf>,
+    saved_battery_ram: Vec<u8>,
+    timing: Timing,
+    // four-screen boards bring 2KB of their own for the nametables the PPU doesn't have
+    vram: Vec<u8>,
+    mapper: Option<Box<dyn Mapper>>,
+    // iNES headers can't tell MMC3 revisions apart, this one is used instead when set
+    mmc3_revision: Option<Mmc3Revision>,
+}
+
+impl Default for Cartridge {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Cartridge {
@@ -12,27 +59,441 @@ impl Cartridge {
         Cartridge {
             prg_rom: vec![],
             chr_rom: vec![],
+            chr_ram: vec![],
+            prg_ram: vec![],
+            trainer: vec![],
             mapper_id: 0,
+            mirroring: Mirroring::Horizontal,
+            bus_conflicts: false,
+            battery: false,
+            save_path: None,
+            saved_battery_ram: vec![],
+            timing: Timing::Ntsc,
+            vram: vec![],
+            mapper: None,
+            mmc3_revision: None,
         }
     }
 
     // everything leads me to believe that I might have to save more data into the cartridge
     // structure but right now I can't think of anything else I need... so future Paulo, take
     // a look at that.
-    pub fn load(&mut self, filename: &str) -> Result<(), &str> {
-        let mut rom = INESFormat::from(filename).expect("failed to parse rom");
+    pub fn load(&mut self, filename: &str) -> Result<(), RomError> {
+        let rom = INESFormat::from(filename)?;
+        // the save is read before anything is inserted, failing leaves the previous cartridge in
+        let save_path = Path::new(filename).with_extension("sav");
+        let save = match rom.header.has_battery() {
+            true => match fs::read(&save_path) {
+                Ok(data) => Some(data),
+                Err(err) if err.kind() == ErrorKind::NotFound => None,
+                Err(err) => return Err(err.into()),
+            },
+            false => None,
+        };
+
+        self.insert(rom)?;
+        if let Some(data) = save {
+            self.restore_battery_ram(&data);
+            // the trainer is copied over the save, as it would be into RAM on real hardware
+            self.load_trainer();
+        }
+        if self.battery {
+            self.saved_battery_ram = self.battery_ram().unwrap_or_default();
+            self.save_path = Some(save_path);
+        }
+        Ok(())
+    }
+
+    pub fn load_from_reader(&mut self, reader: impl Read) -> Result<(), RomError> {
+        self.insert(INESFormat::from_reader(reader)?)
+    }
+
+    pub fn load_from_bytes(&mut self, bytes: &[u8]) -> Result<(), RomError> {
+        self.insert(INESFormat::from_bytes(bytes)?)
+    }
+
+    fn insert(&mut self, mut rom: INESFormat) -> Result<(), RomError> {
+        let mapper = new_mapper(&rom.header, self.mmc3_revision)?;
+        // whatever was inserted before gets to keep its save
+        self.unload()?;
+
         swap(&mut self.prg_rom, &mut rom.prg_rom);
         swap(&mut self.chr_rom, &mut rom.chr_rom);
+        self.chr_ram = vec![0; rom.header.chr_ram_size()];
+        self.prg_ram = vec![0; rom.header.prg_ram_size()];
+        swap(&mut self.trainer, &mut rom.trainer);
+        self.load_trainer();
         self.mapper_id = rom.header.mapper_id();
+        self.mirroring = if rom.header.flags_6 & 0x8 == 0x8 {
+            Mirroring::FourScreen
+        } else if rom.header.flags_6 & 0x1 == 0x1 {
+            Mirroring::Vertical
+        } else {
+            Mirroring::Horizontal
+        };
+        self.bus_conflicts = rom.header.has_bus_conflicts();
+        self.battery = rom.header.has_battery();
+        self.timing = rom.header.timing();
+        self.vram = match self.mirroring {
+            Mirroring::FourScreen => vec![0; 2048],
+            _ => vec![],
+        };
+        self.mapper = Some(mapper);
+        Ok(())
+    }
+
+    /// Takes the cartridge out, writing its battery-backed RAM back to the .sav file first
+    pub fn unload(&mut self) -> io::Result<()> {
+        self.flush_battery_ram()?;
+        *self = Cartridge {
+            mmc3_revision: self.mmc3_revision,
+            ..Cartridge::new()
+        };
+        Ok(())
+    }
+
+    /// Forces the MMC3 revision of the cartridges loaded from now on, `None` going back to what
+    /// their header says
+    pub fn set_mmc3_revision(&mut self, revision: Option<Mmc3Revision>) {
+        self.mmc3_revision = revision;
+    }
+
+    /// Writes the battery-backed RAM to the .sav file next to the ROM if it changed since it was
+    /// last saved. Cartridges loaded from memory don't have one, see battery_ram for those.
+    pub fn flush_battery_ram(&mut self) -> io::Result<()> {
+        let (Some(save_path), Some(ram)) = (self.save_path.as_ref(), self.battery_ram()) else {
+            return Ok(());
+        };
+        if ram != self.saved_battery_ram {
+            fs::write(save_path, &ram)?;
+            self.saved_battery_ram = ram;
+        }
+        Ok(())
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.mapper.is_some()
+    }
+
+    pub fn mapper_id(&self) -> u16 {
+        self.mapper_id
+    }
+
+    pub fn mirroring(&self) -> Mirroring {
+        self.mapper
+            .as_ref()
+            .and_then(|m| m.mirroring())
+            .unwrap_or(self.mirroring)
+    }
+
+    pub fn has_battery(&self) -> bool {
+        self.battery
+    }
+
+    pub fn has_trainer(&self) -> bool {
+        !self.trainer.is_empty()
+    }
+
+    // $7000 is 4KB into the first PRG-RAM bank
+    fn load_trainer(&mut self) {
+        let end = TRAINER_OFFSET + self.trainer.len();
+        if let Some(ram) = self.prg_ram.get_mut(TRAINER_OFFSET..end) {
+            ram.copy_from_slice(&self.trainer);
+        }
+    }
+
+    /// Console reset. PRG-RAM keeps its contents except for the trainer, which is put back in
+    /// case the game overwrote it. Battery-backed RAM is left alone as it holds the player's save.
+    pub fn reset(&mut self) {
+        if !self.battery {
+            self.load_trainer();
+        }
+    }
+
+    /// Region the game was made for, as far as the header can tell
+    pub fn timing(&self) -> Timing {
+        self.timing
+    }
+
+    /// Everything the battery keeps alive: PRG-RAM followed by the mapper's internal RAM. None
+    /// when the board doesn't have a battery.
+    pub fn battery_ram(&self) -> Option<Vec<u8>> {
+        if !self.battery {
+            return None;
+        }
+        let mut ram = self.prg_ram.clone();
+        if let Some(mapper) = self.mapper.as_ref() {
+            ram.extend_from_slice(mapper.internal_ram());
+        }
+        Some(ram)
+    }
+
+    /// Counterpart of battery_ram, anything past the end of the data is left alone
+    pub fn restore_battery_ram(&mut self, data: &[u8]) {
+        let (prg_ram, internal_ram) = data.split_at(data.len().min(self.prg_ram.len()));
+        self.prg_ram[..prg_ram.len()].copy_from_slice(prg_ram);
+        if let Some(mapper) = self.mapper.as_mut() {
+            let target = mapper.internal_ram_mut();
+            let len = target.len().min(internal_ram.len());
+            target[..len].copy_from_slice(&internal_ram[..len]);
+        }
+    }
+
+    /// Snapshot of everything writable on the board for save states: PRG-RAM, CHR-RAM, the
+    /// four-screen VRAM and the mapper's internal RAM, in that order. Mapper registers aren't
+    /// part of it yet.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut state = [&self.prg_ram[..], &self.chr_ram[..], &self.vram[..]].concat();
+        if let Some(mapper) = self.mapper.as_ref() {
+            state.extend_from_slice(mapper.internal_ram());
+        }
+        state
+    }
+
+    /// Counterpart of save_state, the snapshot has to come from the same ROM
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), &'static str> {
+        if state.len() != self.save_state().len() {
+            return Err("save state doesn't match the cartridge");
+        }
+        let (prg_ram, rest) = state.split_at(self.prg_ram.len());
+        let (chr_ram, rest) = rest.split_at(self.chr_ram.len());
+        let (vram, internal_ram) = rest.split_at(self.vram.len());
+        self.prg_ram.copy_from_slice(prg_ram);
+        self.chr_ram.copy_from_slice(chr_ram);
+        self.vram.copy_from_slice(vram);
+        if let Some(mapper) = self.mapper.as_mut() {
+            mapper.internal_ram_mut().copy_from_slice(internal_ram);
+        }
         Ok(())
     }
+
+    fn fetch(&self, mapped: Mapped, open_bus: u8) -> u8 {
+        let value = match mapped {
+            Mapped::PrgRom(offset) => self.prg_rom.get(offset),
+            Mapped::PrgRam(offset) => self.prg_ram.get(offset),
+            Mapped::Chr(offset) => self.chr().get(offset),
+            Mapped::Data(value) => return value,
+            Mapped::Ciram(_) | Mapped::Unmapped => None,
+        };
+        value.copied().unwrap_or(open_bus)
+    }
+
+    fn store(&mut self, mapped: Mapped, value: u8) {
+        let cell = match mapped {
+            Mapped::PrgRam(offset) => self.prg_ram.get_mut(offset),
+            // writes to CHR-ROM go nowhere
+            Mapped::Chr(offset) if self.chr_rom.is_empty() => self.chr_ram.get_mut(offset),
+            _ => None,
+        };
+        if let Some(cell) = cell {
+            *cell = value;
+        }
+    }
+
+    fn chr(&self) -> &[u8] {
+        match self.chr_rom.is_empty() {
+            true => &self.chr_ram,
+            false => &self.chr_rom,
+        }
+    }
+
+    pub fn cpu_read(&mut self, addr: u16, open_bus: u8) -> u8 {
+        match self.mapper.as_mut() {
+            Some(mapper) => {
+                let mapped = mapper.cpu_read(addr);
+                self.fetch(mapped, open_bus)
+            }
+            None => open_bus,
+        }
+    }
+
+    pub fn cpu_peek(&self, addr: u16, open_bus: u8) -> u8 {
+        match self.mapper.as_ref() {
+            Some(mapper) => self.fetch(mapper.cpu_peek(addr), open_bus),
+            None => open_bus,
+        }
+    }
+
+    /// Called once per CPU cycle for mappers that keep track of time (IRQ counters and such)
+    pub fn cpu_clock(&mut self) {
+        if let Some(mapper) = self.mapper.as_mut() {
+            mapper.cpu_clock();
+        }
+    }
+
+    pub fn irq_asserted(&self) -> bool {
+        self.mapper.as_ref().is_some_and(|m| m.irq_asserted())
+    }
+
+    /// Expansion audio for the APU mixer to add to its own channels
+    pub fn audio_output(&self) -> f32 {
+        self.mapper.as_ref().map_or(0.0, |m| m.audio_output())
+    }
+
+    pub fn cpu_write(&mut self, addr: u16, value: u8) {
+        let value = match self.mapper.as_ref() {
+            Some(mapper) if self.bus_conflicts => match mapper.cpu_peek(addr) {
+                Mapped::PrgRom(offset) => value & self.prg_rom.get(offset).copied().unwrap_or(0xFF),
+                _ => value,
+            },
+            _ => value,
+        };
+
+        if let Some(mapper) = self.mapper.as_mut() {
+            let mapped = mapper.cpu_write(addr, value);
+            self.store(mapped, value);
+        }
+    }
+
+    // The PPU multiplexes the low byte of the address and the data on the same pins, so reading
+    // from somewhere nothing answers returns the low byte of the address.
+    pub fn ppu_read(&mut self, addr: u16) -> u8 {
+        match self.pattern_read(addr) {
+            Mapped::Data(value) => value,
+            _ => addr as u8,
+        }
+    }
+
+    pub fn ppu_peek(&self, addr: u16) -> u8 {
+        match self.pattern_peek(addr) {
+            Mapped::Data(value) => value,
+            _ => addr as u8,
+        }
+    }
+
+    pub fn ppu_write(&mut self, addr: u16, value: u8) {
+        self.pattern_write(addr, value);
+    }
+
+    // Pattern table accesses ($0000-$1FFF) work like the nametable ones: some boards (Namco 163)
+    // can point them at CIRAM, in which case the PPU resolves them.
+    fn resolve_ppu(&self, addr: u16, mapped: Mapped) -> Mapped {
+        match mapped {
+            Mapped::Ciram(offset) => Mapped::Ciram(offset),
+            mapped => Mapped::Data(self.fetch(mapped, addr as u8)),
+        }
+    }
+
+    pub fn pattern_read(&mut self, addr: u16) -> Mapped {
+        match self.mapper.as_mut() {
+            Some(mapper) => {
+                let mapped = mapper.ppu_read(addr);
+                self.resolve_ppu(addr, mapped)
+            }
+            None => Mapped::Data(addr as u8),
+        }
+    }
+
+    pub fn pattern_peek(&self, addr: u16) -> Mapped {
+        match self.mapper.as_ref() {
+            Some(mapper) => self.resolve_ppu(addr, mapper.ppu_peek(addr)),
+            None => Mapped::Data(addr as u8),
+        }
+    }
+
+    /// Returns Mapped::Ciram when the PPU should store the value itself
+    pub fn pattern_write(&mut self, addr: u16, value: u8) -> Mapped {
+        match self.mapper.as_mut().map(|m| m.ppu_write(addr, value)) {
+            Some(Mapped::Ciram(offset)) => Mapped::Ciram(offset),
+            Some(mapped) => {
+                self.store(mapped, value);
+                Mapped::Unmapped
+            }
+            None => Mapped::Unmapped,
+        }
+    }
+
+    // the upper two nametables of four-screen boards live on the cartridge
+    fn vram_offset(&self, addr: u16) -> Option<usize> {
+        let table = (addr as usize >> 10) & 0x3;
+        match self.mirroring() {
+            Mirroring::FourScreen if table >= 2 && !self.vram.is_empty() => {
+                Some(((table - 2) << 10) | (addr as usize & 0x3FF))
+            }
+            _ => None,
+        }
+    }
+
+    fn ciram(&self, addr: u16) -> Mapped {
+        let table = (addr as usize >> 10) & 0x3;
+        let page = match self.mirroring() {
+            Mirroring::Horizontal => table >> 1,
+            Mirroring::Vertical => table & 0x1,
+            Mirroring::SingleScreenLower => 0,
+            Mirroring::SingleScreenUpper => 1,
+            // without the extra RAM the board behaves as if it were vertically mirrored
+            Mirroring::FourScreen => match self.vram_offset(addr) {
+                Some(offset) => return Mapped::Data(self.vram[offset]),
+                None => table & 0x1,
+            },
+        };
+        Mapped::Ciram((page << 10) | (addr as usize & 0x3FF))
+    }
+
+    // Nametable accesses come back either as Mapped::Ciram, for the PPU to resolve against its
+    // own RAM, or as Mapped::Data when the cartridge answered by itself.
+    fn resolve_nametable(&self, addr: u16, mapped: Option<Mapped>) -> Mapped {
+        match mapped {
+            None => self.ciram(addr),
+            Some(mapped) => self.resolve_ppu(addr, mapped),
+        }
+    }
+
+    pub fn nametable_read(&mut self, addr: u16) -> Mapped {
+        let mapped = self.mapper.as_mut().and_then(|m| m.nametable_read(addr));
+        self.resolve_nametable(addr, mapped)
+    }
+
+    pub fn nametable_peek(&self, addr: u16) -> Mapped {
+        let mapped = self.mapper.as_ref().and_then(|m| m.nametable_peek(addr));
+        self.resolve_nametable(addr, mapped)
+    }
+
+    /// Returns Mapped::Ciram when the PPU should store the value itself
+    pub fn nametable_write(&mut self, addr: u16, value: u8) -> Mapped {
+        match self.mapper.as_mut().and_then(|m| m.nametable_write(addr, value)) {
+            Some(Mapped::Ciram(offset)) => Mapped::Ciram(offset),
+            Some(mapped) => {
+                self.store(mapped, value);
+                Mapped::Unmapped
+            }
+            None => match self.vram_offset(addr) {
+                Some(offset) => {
+                    self.vram[offset] = value;
+                    Mapped::Unmapped
+                }
+                None => self.ciram(addr),
+            },
+        }
+    }
+
+    /// Lets the mapper see CPU writes which don't land on the cartridge (PPU registers and such)
+    pub fn snoop_cpu_write(&mut self, addr: u16, value: u8) {
+        if let Some(mapper) = self.mapper.as_mut() {
+            mapper.snoop_cpu_write(addr, value);
+        }
+    }
+}
+
+impl BusDevice for Cartridge {
+    fn read(&mut self, addr: u16, open_bus: u8) -> u8 {
+        self.cpu_read(addr, open_bus)
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        self.cpu_write(addr, value);
+    }
+
+    fn peek(&self, addr: u16, open_bus: u8) -> u8 {
+        self.cpu_peek(addr, open_bus)
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::inesformat::format::{CHR_ROM_SIZE_FACTOR, PRG_ROM_SIZE_FACTOR};
-    use crate::test::generate_rom;
+    use crate::test::{generate_nrom, generate_rom};
 
     #[test]
     fn test_load_cartridge() {
@@ -40,26 +501,301 @@ mod test {
         let mut cartridge = Cartridge::new();
         cartridge.load(filename.as_str()).expect("Failed loading file");
 
-        assert_eq!(cartridge.prg_rom.len(), 1 * PRG_ROM_SIZE_FACTOR);
-        assert_eq!(cartridge.chr_rom.len(), 1 * CHR_ROM_SIZE_FACTOR);
+        assert_eq!(cartridge.prg_rom.len(), PRG_ROM_SIZE_FACTOR);
+        assert_eq!(cartridge.chr_rom.len(), CHR_ROM_SIZE_FACTOR);
         assert_eq!(cartridge.mapper_id, 0);
-        assert_eq!(&[0xEE as u8; 1 * PRG_ROM_SIZE_FACTOR], &cartridge.prg_rom[..]);
-        assert_eq!(&[0xDD as u8; 1 * CHR_ROM_SIZE_FACTOR], &cartridge.chr_rom[..]);
+        assert_eq!(&[0xEE_u8; PRG_ROM_SIZE_FACTOR], &cartridge.prg_rom[..]);
+        assert_eq!(&[0xDD_u8; CHR_ROM_SIZE_FACTOR], &cartridge.chr_rom[..]);
+    }
+
+    #[test]
+    fn test_load_cartridge_from_memory() {
+        let contents = crate::test::banked_rom_image(2, 4, 1);
+        let mut cartridge = Cartridge::new();
+        cartridge.load_from_bytes(&contents).expect("Failed loading ROM");
+        assert!(cartridge.is_loaded());
+        assert_eq!(cartridge.mapper_id(), 2);
+        assert_eq!(cartridge.cpu_peek(0xC000, 0), 6);
+
+        let mut cartridge = Cartridge::new();
+        cartridge.load_from_reader(contents.as_slice()).expect("Failed loading ROM");
+        assert_eq!(cartridge.cpu_peek(0xC000, 0), 6);
     }
 
     #[test]
     fn test_mapper_id_value_retrieval() {
         let (_tmp_file, filename) = generate_rom(false, 1, 1);
-        let mut cartridge = Cartridge::new();
-        cartridge.load(filename.as_str()).expect("Failed loading file");
+        let rom = INESFormat::from(filename.as_str()).expect("Failed loading file");
 
-        assert_eq!(cartridge.mapper_id, 1);
+        assert_eq!(rom.header.mapper_id(), 1);
 
         // test mappers which contains two nibbles
         let (_tmp_file, filename) = generate_rom(false, 0xfe, 1);
+        let rom = INESFormat::from(filename.as_str()).expect("Failed loading file");
+
+        assert_eq!(rom.header.mapper_id(), 0xfe);
+    }
+
+    #[test]
+    fn test_unsupported_mapper() {
+        let (_tmp_file, filename) = generate_rom(false, 0xfe, 1);
+        let mut cartridge = Cartridge::new();
+        assert!(matches!(cartridge.load(filename.as_str()), Err(RomError::UnsupportedMapper(0xfe))));
+        assert!(!cartridge.is_loaded());
+    }
+
+    #[test]
+    fn test_mirroring() {
+        let (_tmp_file, filename) = generate_rom(false, 0, 1);
         let mut cartridge = Cartridge::new();
         cartridge.load(filename.as_str()).expect("Failed loading file");
+        assert_eq!(cartridge.mirroring(), Mirroring::Horizontal);
 
-        assert_eq!(cartridge.mapper_id, 0xfe);
+        let (_tmp_file, filename) = generate_nrom(&[0xEE; PRG_ROM_SIZE_FACTOR], 0x1);
+        cartridge.load(filename.as_str()).expect("Failed loading file");
+        assert_eq!(cartridge.mirroring(), Mirroring::Vertical);
+    }
+
+    #[test]
+    fn test_cpu_and_ppu_accesses() {
+        let mut cartridge = Cartridge::new();
+        assert_eq!(cartridge.cpu_read(0x8000, 0x12), 0x12);
+        assert_eq!(cartridge.ppu_read(0x0034), 0x34);
+
+        let (_tmp_file, filename) = generate_rom(false, 0, 1);
+        cartridge.load(filename.as_str()).expect("Failed loading file");
+        assert_eq!(cartridge.cpu_read(0x8000, 0x12), 0xEE);
+        assert_eq!(cartridge.cpu_peek(0xFFFF, 0x12), 0xEE);
+        assert_eq!(cartridge.ppu_read(0x0034), 0xDD);
+
+        // PRG-RAM is writable, ROMs aren't
+        cartridge.cpu_write(0x6000, 0x42);
+        cartridge.cpu_write(0x8000, 0x42);
+        cartridge.ppu_write(0x0000, 0x42);
+        assert_eq!(cartridge.cpu_read(0x6000, 0x12), 0x42);
+        assert_eq!(cartridge.cpu_read(0x8000, 0x12), 0xEE);
+        assert_eq!(cartridge.ppu_read(0x0000), 0xDD);
+    }
+
+    #[test]
+    fn test_nametable_mirroring() {
+        let mut cartridge = Cartridge::new();
+        let (_tmp_file, filename) = generate_rom(false, 0, 1);
+        cartridge.load(filename.as_str()).expect("Failed loading file");
+        assert_eq!(cartridge.nametable_read(0x2405), Mapped::Ciram(0x005));
+        assert_eq!(cartridge.nametable_read(0x2805), Mapped::Ciram(0x405));
+
+        let (_tmp_file, filename) = generate_nrom(&[0xEE; PRG_ROM_SIZE_FACTOR], 0x1);
+        cartridge.load(filename.as_str()).expect("Failed loading file");
+        assert_eq!(cartridge.nametable_read(0x2405), Mapped::Ciram(0x405));
+        assert_eq!(cartridge.nametable_read(0x2C05), Mapped::Ciram(0x405));
+
+        // the bottom two nametables of four-screen boards live on the cartridge
+        let (_tmp_file, filename) = generate_nrom(&[0xEE; PRG_ROM_SIZE_FACTOR], 0x8);
+        cartridge.load(filename.as_str()).expect("Failed loading file");
+        assert_eq!(cartridge.nametable_write(0x2405, 0x42), Mapped::Ciram(0x405));
+        assert_eq!(cartridge.nametable_write(0x2C05, 0x24), Mapped::Unmapped);
+        assert_eq!(cartridge.nametable_read(0x2C05), Mapped::Data(0x24));
+        assert_eq!(cartridge.nametable_peek(0x2805), Mapped::Data(0x00));
+    }
+
+    #[test]
+    fn test_nes2_header_sizes_ram_and_region() {
+        // 8KB of battery-backed PRG-RAM on a PAL board
+        let mut contents = crate::test::banked_rom_image(0, 2, 1);
+        contents[6] |= 0x2;
+        contents[7] |= 0x08;
+        contents[10] = 0x70;
+        contents[12] = 0x01;
+        let mut cartridge = Cartridge::new();
+        cartridge.load_from_bytes(&contents).expect("Failed loading ROM");
+        assert_eq!(cartridge.timing(), Timing::Pal);
+        cartridge.cpu_write(0x6000, 0x42);
+        assert_eq!(cartridge.cpu_peek(0x6000, 0), 0x42);
+        assert_eq!(cartridge.battery_ram().unwrap().len(), 8192);
+
+        // no PRG-RAM at all, unlike iNES which would assume 8KB
+        contents[6] &= !0x2;
+        contents[10] = 0;
+        contents[12] = 0;
+        cartridge.load_from_bytes(&contents).expect("Failed loading ROM");
+        assert_eq!(cartridge.timing(), Timing::Ntsc);
+        cartridge.cpu_write(0x6000, 0x42);
+        assert_eq!(cartridge.cpu_peek(0x6000, 0x5A), 0x5A);
+    }
+
+    #[test]
+    fn test_chr_ram() {
+        let mut cartridge = Cartridge::new();
+        cartridge.load_from_bytes(&crate::test::banked_rom_image(2, 2, 0)).expect("Failed loading ROM");
+        assert_eq!(cartridge.chr_ram.len(), CHR_ROM_SIZE_FACTOR);
+        assert_eq!(cartridge.ppu_peek(0x1FFF), 0);
+        cartridge.ppu_write(0x1FFF, 0x42);
+        assert_eq!(cartridge.ppu_peek(0x1FFF), 0x42);
+
+        // NES 2.0 gives the size, 32KB here
+        let mut contents = crate::test::banked_rom_image(2, 2, 0);
+        contents[7] |= 0x08;
+        contents[11] = 0x09;
+        cartridge.load_from_bytes(&contents).expect("Failed loading ROM");
+        assert_eq!(cartridge.chr_ram.len(), 4 * CHR_ROM_SIZE_FACTOR);
+    }
+
+    #[test]
+    fn test_save_state_includes_chr_ram() {
+        let mut cartridge = Cartridge::new();
+        cartridge.load_from_bytes(&crate::test::banked_rom_image(0, 2, 0)).expect("Failed loading ROM");
+        cartridge.ppu_write(0x0010, 0x42);
+        cartridge.cpu_write(0x6000, 0x24);
+        let state = cartridge.save_state();
+        assert_eq!(state.len(), 8192 + CHR_ROM_SIZE_FACTOR);
+
+        cartridge.ppu_write(0x0010, 0);
+        cartridge.cpu_write(0x6000, 0);
+        cartridge.load_state(&state).unwrap();
+        assert_eq!(cartridge.ppu_peek(0x0010), 0x42);
+        assert_eq!(cartridge.cpu_peek(0x6000, 0), 0x24);
+
+        assert!(cartridge.load_state(&state[1..]).is_err());
+    }
+
+    #[test]
+    fn test_battery_ram_persistence() {
+        let dir = tempfile::tempdir().unwrap();
+        let rom_path = dir.path().join("game.nes");
+        let save_path = dir.path().join("game.sav");
+        let mut contents = crate::test::banked_rom_image(0, 2, 1);
+        contents[6] |= 0x2;
+        fs::write(&rom_path, &contents).unwrap();
+        let filename = rom_path.to_str().unwrap();
+
+        // nothing gets written until the RAM changes
+        let mut cartridge = Cartridge::new();
+        cartridge.load(filename).expect("Failed loading file");
+        cartridge.flush_battery_ram().unwrap();
+        assert!(!save_path.exists());
+
+        cartridge.cpu_write(0x6000, 0x42);
+        cartridge.flush_battery_ram().unwrap();
+        assert_eq!(fs::read(&save_path).unwrap()[0], 0x42);
+
+        // unloading flushes too, and the next load picks the save up
+        cartridge.cpu_write(0x6001, 0x24);
+        cartridge.unload().unwrap();
+        assert!(!cartridge.is_loaded());
+        cartridge.load(filename).expect("Failed loading file");
+        assert_eq!(cartridge.cpu_peek(0x6000, 0), 0x42);
+        assert_eq!(cartridge.cpu_peek(0x6001, 0), 0x24);
+
+        // so does inserting another cartridge
+        cartridge.cpu_write(0x6002, 0x11);
+        cartridge.load_from_bytes(&crate::test::banked_rom_image(0, 2, 1)).unwrap();
+        assert_eq!(fs::read(&save_path).unwrap()[2], 0x11);
+    }
+
+    #[test]
+    fn test_unreadable_save_leaves_cartridge_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let rom_path = dir.path().join("game.nes");
+        let mut contents = crate::test::banked_rom_image(0, 2, 1);
+        contents[6] |= 0x2;
+        fs::write(&rom_path, &contents).unwrap();
+        // reading a directory fails with something else than NotFound
+        fs::create_dir(dir.path().join("game.sav")).unwrap();
+
+        let mut cartridge = Cartridge::new();
+        cartridge.load_from_bytes(&crate::test::banked_rom_image(2, 2, 1)).unwrap();
+        assert!(matches!(cartridge.load(rom_path.to_str().unwrap()), Err(RomError::Io(_))));
+        assert_eq!(cartridge.mapper_id(), 2);
+        assert!(!cartridge.has_battery());
+    }
+
+    #[test]
+    fn test_trainer_is_mapped_at_7000() {
+        let mut contents = crate::test::banked_rom_image(0, 2, 1);
+        contents[6] |= 0x4;
+        let trainer: Vec<u8> = (0..512).map(|i| (i % 251) as u8 + 1).collect();
+        contents.splice(16..16, trainer.iter().copied());
+
+        let mut cartridge = Cartridge::new();
+        cartridge.load_from_bytes(&contents).expect("Failed loading ROM");
+        assert!(cartridge.has_trainer());
+        assert_eq!(cartridge.cpu_peek(0x6FFF, 0), 0);
+        assert_eq!(cartridge.cpu_peek(0x7000, 0), trainer[0]);
+        assert_eq!(cartridge.cpu_peek(0x71FF, 0), trainer[511]);
+        assert_eq!(cartridge.cpu_peek(0x7200, 0), 0);
+
+        // put back on reset, the rest of PRG-RAM survives it
+        cartridge.cpu_write(0x7000, 0xAA);
+        cartridge.cpu_write(0x6000, 0x55);
+        cartridge.reset();
+        assert_eq!(cartridge.cpu_peek(0x7000, 0), trainer[0]);
+        assert_eq!(cartridge.cpu_peek(0x6000, 0), 0x55);
+
+        cartridge.load_from_bytes(&crate::test::banked_rom_image(0, 2, 1)).unwrap();
+        assert!(!cartridge.has_trainer());
+        assert_eq!(cartridge.cpu_peek(0x7000, 0), 0);
+
+        // a NES 2.0 header without PRG-RAM still gets room for the trainer
+        contents[7] |= 0x08;
+        cartridge.load_from_bytes(&contents).expect("Failed loading ROM");
+        assert_eq!(cartridge.prg_ram.len(), 8192);
+        assert_eq!(cartridge.cpu_peek(0x7000, 0), trainer[0]);
+    }
+
+    #[test]
+    fn test_save_does_not_overwrite_trainer() {
+        let dir = tempfile::tempdir().unwrap();
+        let rom_path = dir.path().join("game.nes");
+        let mut contents = crate::test::banked_rom_image(0, 2, 1);
+        contents[6] |= 0x2 | 0x4;
+        contents.splice(16..16, [0xAB; 512]);
+        fs::write(&rom_path, &contents).unwrap();
+        fs::write(dir.path().join("game.sav"), [0x42; 8192]).unwrap();
+
+        let mut cartridge = Cartridge::new();
+        cartridge.load(rom_path.to_str().unwrap()).expect("Failed loading file");
+        assert_eq!(cartridge.cpu_peek(0x6000, 0), 0x42);
+        assert_eq!(cartridge.cpu_peek(0x7000, 0), 0xAB);
+        assert_eq!(cartridge.cpu_peek(0x71FF, 0), 0xAB);
+        assert_eq!(cartridge.cpu_peek(0x7200, 0), 0x42);
+
+        // importing a save is a plain copy though
+        let save = cartridge.battery_ram().unwrap();
+        cartridge.restore_battery_ram(&[0x42; 8192]);
+        assert_eq!(cartridge.cpu_peek(0x7000, 0), 0x42);
+        cartridge.restore_battery_ram(&save);
+        assert_eq!(cartridge.battery_ram().unwrap(), save);
+    }
+
+    #[test]
+    fn test_battery_ram_includes_mapper_ram() {
+        let mut contents = crate::test::banked_rom_image(19, 2, 1);
+        contents[6] |= 0x2;
+        let mut cartridge = Cartridge::new();
+        cartridge.load_from_bytes(&contents).expect("Failed loading ROM");
+        assert!(cartridge.has_battery());
+
+        // PRG-RAM writes enabled, internal RAM port at $40 without auto-increment
+        cartridge.cpu_write(0xF800, 0x40);
+        cartridge.cpu_write(0x6000, 0x42);
+        cartridge.cpu_write(0x4800, 0x24);
+        let ram = cartridge.battery_ram().unwrap();
+        assert_eq!(ram.len(), 8192 + 128);
+        assert_eq!(ram[0], 0x42);
+        assert_eq!(ram[8192 + 0x40], 0x24);
+
+        let mut saved = ram.clone();
+        saved[1] = 0x11;
+        saved[8192] = 0x22;
+        cartridge.restore_battery_ram(&saved);
+        assert_eq!(cartridge.cpu_peek(0x6001, 0), 0x11);
+        cartridge.cpu_write(0xF800, 0x00);
+        assert_eq!(cartridge.cpu_read(0x4800, 0), 0x22);
+
+        // no battery, nothing to save
+        let (_tmp_file, filename) = generate_rom(false, 0, 1);
+        cartridge.load(filename.as_str()).expect("Failed loading file");
+        assert!(cartridge.battery_ram().is_none());
     }
 }
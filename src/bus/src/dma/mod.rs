@@ -0,0 +1,97 @@
+/// What the DMA unit does with the CPU cycle it took over
+#[derive(Debug, PartialEq, Eq)]
+pub enum DmaCycle {
+    /// The CPU is being halted, nothing happens on the bus
+    Halt,
+    /// Waiting for a read cycle to come around
+    Align,
+    Read(u16),
+    Write(u8),
+}
+
+/// OAM DMA, started by writing a page number to $4014.
+///
+/// Once the CPU is halted the 256 bytes of the page are copied to $2004 one read/write pair at
+/// a time. Reads only happen on even ("get") CPU cycles and writes on odd ("put") ones, so on
+/// top of the halt cycle the transfer takes an extra alignment cycle when the halt lands on an
+/// even cycle: 513 or 514 cycles in total. The APU isn't emulated yet, once it is DMC DMA
+/// will have to steal cycles from this one.
+pub struct OamDma {
+    page: u8,
+    offset: u16,
+    halted: bool,
+    // byte read on the last get cycle, waiting for the next put cycle
+    value: Option<u8>,
+}
+
+impl OamDma {
+    pub fn new(page: u8) -> Self {
+        OamDma {
+            page,
+            offset: 0,
+            halted: false,
+            value: None,
+        }
+    }
+
+    /// Picks what happens on the next CPU cycle, `get` telling whether it's an even one. Read
+    /// values have to be handed back through `latch`.
+    pub fn next_cycle(&mut self, get: bool) -> DmaCycle {
+        if !self.halted {
+            self.halted = true;
+            return DmaCycle::Halt;
+        }
+        match self.value.take() {
+            Some(value) if !get => {
+                self.offset += 1;
+                DmaCycle::Write(value)
+            }
+            None if get => DmaCycle::Read((self.page as u16) << 8 | self.offset),
+            value => {
+                self.value = value;
+                DmaCycle::Align
+            }
+        }
+    }
+
+    pub fn latch(&mut self, value: u8) {
+        self.value = Some(value);
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.offset == 0x100
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cycles(start_on_get: bool) -> Vec<DmaCycle> {
+        let mut dma = OamDma::new(0x02);
+        let mut cycles = vec![];
+        let mut get = start_on_get;
+        while !dma.is_done() {
+            let cycle = dma.next_cycle(get);
+            if let DmaCycle::Read(addr) = cycle {
+                dma.latch(addr as u8);
+            }
+            cycles.push(cycle);
+            get = !get;
+        }
+        cycles
+    }
+
+    #[test]
+    fn test_cycle_sequence() {
+        let sequence = cycles(false);
+        assert_eq!(sequence.len(), 513);
+        assert_eq!(sequence[..5], [DmaCycle::Halt, DmaCycle::Read(0x0200), DmaCycle::Write(0x00), DmaCycle::Read(0x0201), DmaCycle::Write(0x01)]);
+        assert_eq!(sequence[512], DmaCycle::Write(0xFF));
+
+        // halting on a get cycle pushes the first read one cycle later
+        let sequence = cycles(true);
+        assert_eq!(sequence.len(), 514);
+        assert_eq!(sequence[..3], [DmaCycle::Halt, DmaCycle::Align, DmaCycle::Read(0x0200)]);
+    }
+}
@@ -0,0 +1,58 @@
+use std::fmt;
+use std::io;
+
+/// Everything that can go wrong while turning a file into a cartridge
+#[derive(Debug)]
+pub enum RomError {
+    Io(io::Error),
+    /// The file doesn't start with "NES\x1A"
+    BadMagic,
+    /// Less than the 16 bytes of the header
+    TruncatedHeader,
+    TruncatedTrainer,
+    TruncatedPrgRom { expected: usize, found: usize },
+    TruncatedChrRom { expected: usize, found: usize },
+    /// A NES 2.0 ROM size too large to be addressed, holds which ROM
+    InvalidRomSize(&'static str),
+    UnsupportedMapper(u16),
+    /// The header is valid but describes something the emulator can't run
+    UnsupportedFormat(&'static str),
+    /// Bytes left over after everything the header describes, holds how many
+    TrailingData(usize),
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomError::Io(err) => write!(f, "failed to read the ROM: {}", err),
+            RomError::BadMagic => write!(f, "not an iNES file, the magic constant doesn't match"),
+            RomError::TruncatedHeader => write!(f, "the file is too short to hold an iNES header"),
+            RomError::TruncatedTrainer => write!(f, "the file ends in the middle of the trainer"),
+            RomError::TruncatedPrgRom { expected, found } => {
+                write!(f, "PRG-ROM is truncated, expected {} bytes but found {}", expected, found)
+            }
+            RomError::TruncatedChrRom { expected, found } => {
+                write!(f, "CHR-ROM is truncated, expected {} bytes but found {}", expected, found)
+            }
+            RomError::InvalidRomSize(rom) => write!(f, "the header gives an oversized {} size", rom),
+            RomError::UnsupportedMapper(mapper_id) => write!(f, "mapper {} isn't supported yet", mapper_id),
+            RomError::UnsupportedFormat(reason) => write!(f, "unsupported ROM: {}", reason),
+            RomError::TrailingData(len) => write!(f, "{} unexpected bytes after the end of the ROM", len),
+        }
+    }
+}
+
+impl std::error::Error for RomError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RomError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for RomError {
+    fn from(err: io::Error) -> Self {
+        RomError::Io(err)
+    }
+}
@@ -1,8 +1,12 @@
 use super::*;
+use super::error::RomError;
 use super::header::*;
 
 pub const PRG_ROM_SIZE_FACTOR: usize = 16384;
 pub const CHR_ROM_SIZE_FACTOR: usize = 8192;
+pub const PRG_RAM_SIZE_FACTOR: usize = 8192;
+const PLC_INST_ROM_SIZE: usize = 8192;
+const PLC_PROM_SIZE: usize = 32;
 
 pub struct INESFormat {
     // Header (16 bytes)
@@ -13,12 +17,20 @@ pub struct INESFormat {
     pub prg_rom: Vec<u8>,
     // CHR ROM data, if present (8192 * y bytes)
     pub chr_rom: Vec<u8>,
+    // Miscellaneous ROMs (NES 2.0 only), everything after CHR ROM
+    pub misc_rom: Vec<u8>,
     // PlayChoice INST-ROM, if present (0 or 8192 bytes)
     pub plc_inst_rom: Vec<u8>,
     // PlayChoice PROM, if present (16 bytes Data, 16 bytes CounterOut) (this is often missing)
     pub plc_prom: Vec<u8>,
 }
 
+impl Default for INESFormat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl INESFormat {
     pub fn new() -> Self {
         INESFormat {
@@ -26,55 +38,83 @@ impl INESFormat {
             trainer: vec![],
             prg_rom: vec![],
             chr_rom: vec![],
+            misc_rom: vec![],
             plc_inst_rom: vec![],
             plc_prom: vec![],
         }
     }
 
-    pub fn from(filename: &str) -> Result<Self, &str> {
+    pub fn from(filename: &str) -> Result<Self, RomError> {
+        INESFormat::from_reader(BufReader::new(File::open(filename)?))
+    }
+
+    /// Reads a whole iNES file out of anything readable, an archive entry for instance
+    pub fn from_reader(mut reader: impl Read) -> Result<Self, RomError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        INESFormat::from_bytes(&bytes)
+    }
+
+    /// Parses an iNES file already in memory, e.g. one embedded with include_bytes!
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, RomError> {
         let mut rom = INESFormat::new();
-        let bytes = rom.read_file(filename).expect("err reading file");
-        let mut pos = 0 as usize;
+        let mut pos = 0_usize;
 
-        rom.header = Header::from(&bytes).expect("invalid iNES Header");
+        rom.header = Header::from(bytes)?;
         pos += 16;
 
-        match rom.header.format_version() {
-            HeaderVersion::V1 => {
-                if rom.header.flags_6 & 0x4 == 0x4 {
-                    rom.trainer.resize(512, 0);
-                    rom.trainer.copy_from_slice(&bytes[pos..(pos + 512)]);
-                    pos += 512;
-                }
-
-                // ines format v 1
-                rom.prg_rom.resize(rom.header.prg_rom_size as usize * PRG_ROM_SIZE_FACTOR, 0);
-                rom.prg_rom.copy_from_slice(&bytes[pos..(pos + rom.header.prg_rom_size as usize * PRG_ROM_SIZE_FACTOR)]);
-                pos += rom.prg_rom.len();
-
-                rom.chr_rom.resize(rom.header.chr_rom_size as usize * CHR_ROM_SIZE_FACTOR, 0);
-                rom.chr_rom.copy_from_slice(&bytes[pos..(pos + rom.header.chr_rom_size as usize * CHR_ROM_SIZE_FACTOR)]);
-            },
-            _  => panic!("not supported/invalid ines format version")
+        if rom.header.has_trainer() {
+            let trainer = pos.checked_add(512).and_then(|end| bytes.get(pos..end)).ok_or(RomError::TruncatedTrainer)?;
+            rom.trainer.extend_from_slice(trainer);
+            pos += 512;
         }
 
-        Ok(rom)
-    }
+        // both versions lay the data out the same way, NES 2.0 only sizes it differently
+        let prg_rom_size = rom.header.prg_rom_bytes();
+        let prg_rom = pos.checked_add(prg_rom_size).and_then(|end| bytes.get(pos..end)).ok_or(RomError::TruncatedPrgRom {
+            expected: prg_rom_size,
+            found: bytes.len() - pos,
+        })?;
+        rom.prg_rom.extend_from_slice(prg_rom);
+        pos += rom.prg_rom.len();
 
-    fn read_file(&self, filename: &str) -> Result<Vec<u8>, &str> {
-        let file = File::open(filename).expect("file doesn't exist");
-        let mut buf = BufReader::new(file);
-        let mut bytes = Vec::new();
-        buf.read_to_end(&mut bytes)
-            .expect("failed to read the file");
-        Ok(bytes)
+        let chr_rom_size = rom.header.chr_rom_bytes();
+        let chr_rom = pos.checked_add(chr_rom_size).and_then(|end| bytes.get(pos..end)).ok_or(RomError::TruncatedChrRom {
+            expected: chr_rom_size,
+            found: bytes.len() - pos,
+        })?;
+        rom.chr_rom.extend_from_slice(chr_rom);
+        pos += rom.chr_rom.len();
+
+        // PlayChoice boards carry the INST-ROM and PROM after CHR-ROM, the PROM is often missing
+        if rom.header.flags_7 & 0x3 == 0x2 {
+            let inst_rom = &bytes[pos..bytes.len().min(pos + PLC_INST_ROM_SIZE)];
+            rom.plc_inst_rom.extend_from_slice(inst_rom);
+            pos += inst_rom.len();
+
+            let prom = &bytes[pos..bytes.len().min(pos + PLC_PROM_SIZE)];
+            rom.plc_prom.extend_from_slice(prom);
+            pos += prom.len();
+        }
+
+        // NES 2.0 keeps whatever miscellaneous ROMs the board has until the end of the file
+        if rom.header.misc_rom_count() > 0 {
+            rom.misc_rom.extend_from_slice(&bytes[pos..]);
+            pos = bytes.len();
+        }
+
+        if pos < bytes.len() {
+            return Err(RomError::TrailingData(bytes.len() - pos));
+        }
+
+        Ok(rom)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test::generate_rom;
+    use crate::test::{banked_rom_image, generate_rom};
 
     #[test]
     fn test_ines_parsing() {
@@ -89,11 +129,11 @@ mod tests {
         assert_eq!(rom.plc_inst_rom.len(), 0);
         assert_eq!(rom.plc_prom.len(), 0);
 
-        assert_eq!(rom.prg_rom.len(), 1 * PRG_ROM_SIZE_FACTOR);
-        assert_eq!(&[0xEE as u8; 1 * PRG_ROM_SIZE_FACTOR], &rom.prg_rom[..]);
+        assert_eq!(rom.prg_rom.len(), PRG_ROM_SIZE_FACTOR);
+        assert_eq!(&[0xEE_u8; PRG_ROM_SIZE_FACTOR], &rom.prg_rom[..]);
 
-        assert_eq!(rom.chr_rom.len(), 1 * CHR_ROM_SIZE_FACTOR);
-        assert_eq!(&[0xDD as u8; 1 * CHR_ROM_SIZE_FACTOR], &rom.chr_rom[..]);
+        assert_eq!(rom.chr_rom.len(), CHR_ROM_SIZE_FACTOR);
+        assert_eq!(&[0xDD_u8; CHR_ROM_SIZE_FACTOR], &rom.chr_rom[..]);
 
         // With trainer
         let (tmp_file, filename) = generate_rom(true, 0, 1);
@@ -106,20 +146,121 @@ mod tests {
         assert_eq!(rom.plc_inst_rom.len(), 0);
         assert_eq!(rom.plc_prom.len(), 0);
 
-        assert_eq!(rom.prg_rom.len(), 1 * PRG_ROM_SIZE_FACTOR);
-        assert_eq!(&[0xEE as u8; 1 * PRG_ROM_SIZE_FACTOR], &rom.prg_rom[..]);
+        assert_eq!(rom.prg_rom.len(), PRG_ROM_SIZE_FACTOR);
+        assert_eq!(&[0xEE_u8; PRG_ROM_SIZE_FACTOR], &rom.prg_rom[..]);
 
-        assert_eq!(rom.chr_rom.len(), 1 * CHR_ROM_SIZE_FACTOR);
-        assert_eq!(&[0xDD as u8; 1 * CHR_ROM_SIZE_FACTOR], &rom.chr_rom[..]);
+        assert_eq!(rom.chr_rom.len(), CHR_ROM_SIZE_FACTOR);
+        assert_eq!(&[0xDD_u8; CHR_ROM_SIZE_FACTOR], &rom.chr_rom[..]);
     }
 
     #[test]
-    #[should_panic]
     fn test_ines_format_v2() {
         let (tmp_file, filename) = generate_rom(false, 0, 2);
-        let result = INESFormat::from(filename.as_str());
+        let rom = INESFormat::from(filename.as_str()).unwrap();
+
+        assert_eq!(tmp_file.as_file().metadata().unwrap().len(), 24592);
+        assert_eq!(rom.header.format_version(), HeaderVersion::V2);
+        assert_eq!(rom.prg_rom.len(), PRG_ROM_SIZE_FACTOR);
+        assert_eq!(rom.chr_rom.len(), CHR_ROM_SIZE_FACTOR);
+        assert_eq!(rom.misc_rom.len(), 0);
+    }
+
+    #[test]
+    fn test_ines_format_v2_misc_rom() {
+        let mut contents = banked_rom_image(0, 1, 1);
+        contents[7] |= 0x08;
+        contents[14] = 1;
+        contents.extend_from_slice(&[0xCC; 32]);
+        let rom = INESFormat::from_bytes(&contents).unwrap();
+
+        assert_eq!(rom.misc_rom, vec![0xCC; 32]);
+    }
+
+    #[test]
+    fn test_from_bytes_and_reader() {
+        let contents = banked_rom_image(0, 2, 1);
+        let rom = INESFormat::from_bytes(&contents).unwrap();
+        assert_eq!(rom.prg_rom.len(), 2 * PRG_ROM_SIZE_FACTOR);
+        assert_eq!(rom.chr_rom.len(), CHR_ROM_SIZE_FACTOR);
+
+        let rom = INESFormat::from_reader(std::io::Cursor::new(&contents)).unwrap();
+        assert_eq!(rom.prg_rom.len(), 2 * PRG_ROM_SIZE_FACTOR);
+        assert_eq!(rom.chr_rom[..], contents[16 + 2 * PRG_ROM_SIZE_FACTOR..]);
+
+        // same errors whichever way the data comes in
+        let result = INESFormat::from_reader(&contents[..100]);
+        assert!(matches!(result, Err(RomError::TruncatedPrgRom { .. })));
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let result = INESFormat::from("/nonexistent/rom.nes");
+        assert!(matches!(result, Err(RomError::Io(_))));
+    }
+
+    #[test]
+    fn test_bad_header() {
+        let mut contents = banked_rom_image(0, 1, 1);
+        contents[3] = 0x1B;
+        assert!(matches!(INESFormat::from_bytes(&contents), Err(RomError::BadMagic)));
+
+        assert!(matches!(INESFormat::from_bytes(&[0x4E, 0x45]), Err(RomError::TruncatedHeader)));
+        assert!(matches!(INESFormat::from_bytes(&banked_rom_image(0, 1, 1)[..10]), Err(RomError::TruncatedHeader)));
+
+        let mut contents = banked_rom_image(0, 1, 1);
+        contents[4] = 0;
+        assert!(matches!(INESFormat::from_bytes(&contents), Err(RomError::UnsupportedFormat(_))));
+    }
+
+    #[test]
+    fn test_truncated_sections() {
+        // trainer flag set but the file ends right after the header
+        let mut contents = banked_rom_image(0, 1, 1);
+        contents[6] |= 0x4;
+        contents.truncate(16 + 100);
+        assert!(matches!(INESFormat::from_bytes(&contents), Err(RomError::TruncatedTrainer)));
+
+        // 2 PRG banks claimed, only one present
+        let mut contents = banked_rom_image(0, 1, 0);
+        contents[4] = 2;
+        contents[5] = 1;
+        match INESFormat::from_bytes(&contents) {
+            Err(RomError::TruncatedPrgRom { expected, found }) => {
+                assert_eq!(expected, 2 * PRG_ROM_SIZE_FACTOR);
+                assert_eq!(found, PRG_ROM_SIZE_FACTOR);
+            }
+            _ => panic!("PRG-ROM should be truncated"),
+        }
+
+        let mut contents = banked_rom_image(0, 1, 1);
+        contents.truncate(contents.len() - 1);
+        match INESFormat::from_bytes(&contents) {
+            Err(RomError::TruncatedChrRom { expected, found }) => {
+                assert_eq!(expected, CHR_ROM_SIZE_FACTOR);
+                assert_eq!(found, CHR_ROM_SIZE_FACTOR - 1);
+            }
+            _ => panic!("CHR-ROM should be truncated"),
+        }
+
+        // NES 2.0 sizes close to usize::MAX are reported as truncated too
+        let mut contents = banked_rom_image(0, 1, 1);
+        contents[4] = 63 << 2;
+        contents[7] |= 0x08;
+        contents[9] = 0x0F;
+        assert!(matches!(INESFormat::from_bytes(&contents), Err(RomError::TruncatedPrgRom { .. })));
+    }
+
+    #[test]
+    fn test_trailing_data() {
+        let mut contents = banked_rom_image(0, 1, 1);
+        contents.extend_from_slice(&[0; 128]);
+        assert!(matches!(INESFormat::from_bytes(&contents), Err(RomError::TrailingData(128))));
 
-        assert_eq!(tmp_file.as_file().metadata().unwrap().len(), 16);
-        assert!(result.is_err());
+        // unless the header says what it is, PlayChoice boards here
+        contents[7] |= 0x2;
+        contents.resize(contents.len() - 128 + PLC_INST_ROM_SIZE + PLC_PROM_SIZE, 0x11);
+        let rom = INESFormat::from_bytes(&contents).unwrap();
+        assert_eq!(rom.plc_inst_rom.len(), PLC_INST_ROM_SIZE);
+        assert_eq!(rom.plc_prom.len(), PLC_PROM_SIZE);
     }
 }
@@ -1,3 +1,6 @@
+use crate::inesformat::format::{CHR_ROM_SIZE_FACTOR, PRG_RAM_SIZE_FACTOR, PRG_ROM_SIZE_FACTOR};
+use crate::inesformat::error::RomError;
+
 pub struct Header {
     // 0-3: Constant $4E $45 $53 $1A ("NES" followed by MS-DOS end-of-file)
     pub magic_const: [u8; 4],
@@ -25,27 +28,62 @@ pub struct Header {
     // ||||++--- If equal to 2, flags 8-15 are in NES 2.0 format
     // ++++----- Upper nybble of mapper number
     pub flags_7: u8,
-    // 8: Flags 8 - PRG-RAM size (rarely used extension)
+    // 8: Flags 8
+    //   iNES:    PRG-RAM size in 8KB units (rarely used extension)
+    //   NES 2.0: bits 0-3 mapper number bits 8-11, bits 4-7 submapper
     pub flags_8: u8,
-    // 9: Flags 9 - TV system (rarely used extension)
+    // 9: Flags 9
+    //   iNES:    bit 0 TV system (0: NTSC; 1: PAL) (rarely used extension)
+    //   NES 2.0: bits 0-3 PRG-ROM size MSB, bits 4-7 CHR-ROM size MSB
     pub flags_9: u8,
-    // 10: Flags 10 - TV system, PRG-RAM presence (unofficial, rarely used extension)
+    // 10: Flags 10
+    //   iNES: TV system, PRG-RAM presence (unofficial, rarely used extension)
     //
-    // 76543210
-    //   ||  ||
-    //   ||  ++- TV system (0: NTSC; 2: PAL; 1/3: dual compatible)
-    //   |+----- PRG RAM ($6000-$7FFF) (0: present; 1: not present)
-    //   +------ 0: Board has no bus conflicts; 1: Board has bus conflicts
+    //   76543210
+    //     ||  ||
+    //     ||  ++- TV system (0: NTSC; 2: PAL; 1/3: dual compatible)
+    //     |+----- PRG RAM ($6000-$7FFF) (0: present; 1: not present)
+    //     +------ 0: Board has no bus conflicts; 1: Board has bus conflicts
+    //
+    //   NES 2.0: bits 0-3 PRG-RAM shift count, bits 4-7 PRG-NVRAM shift count (64 << shift bytes)
     pub flags_10: u8,
-    // 11-15: Unused padding (should be filled with zero, but some rippers put their name across bytes 7-15)
-    pub unused: [u8; 5],
+    // 11-15: Unused padding in iNES (should be filled with zero, but some rippers put their name
+    // across bytes 7-15). NES 2.0 uses them as follows:
+    //   11: bits 0-3 CHR-RAM shift count, bits 4-7 CHR-NVRAM shift count
+    //   12: bits 0-1 CPU/PPU timing (0: NTSC; 1: PAL; 2: multiple regions; 3: Dendy)
+    //   13: Vs. System: bits 0-3 PPU type, bits 4-7 hardware type
+    //       Extended console type: bits 0-3 console type
+    //   14: bits 0-1 number of miscellaneous ROMs
+    //   15: bits 0-5 default expansion device
+    pub flags_11: u8,
+    pub flags_12: u8,
+    pub flags_13: u8,
+    pub flags_14: u8,
+    pub flags_15: u8,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum HeaderVersion {
     V1,
     V2,
 }
 
+/// CPU/PPU timing the game was made for
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Timing {
+    Ntsc,
+    Pal,
+    /// Runs on either, the game figures out which one it's on
+    MultiRegion,
+    Dendy,
+}
+
+impl Default for Header {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Header {
     pub fn new() -> Self {
         Header {
@@ -57,50 +95,244 @@ impl Header {
             flags_8: 0,
             flags_9: 0,
             flags_10: 0,
-            unused: [0; 5],
+            flags_11: 0,
+            flags_12: 0,
+            flags_13: 0,
+            flags_14: 0,
+            flags_15: 0,
         }
     }
 
-    pub fn from(content: &Vec<u8>) -> Result<Self, &str> {
+    pub fn from(content: &[u8]) -> Result<Self, RomError> {
         let mut ret = Header::new();
 
-        let magic_const = &content[0..4];
+        let magic_const = content.get(0..4).ok_or(RomError::TruncatedHeader)?;
         if magic_const != [0x4E, 0x45, 0x53, 0x1A] {
-            return Err("Header Magic Constant doesn't match INES header format");
+            return Err(RomError::BadMagic);
         }
-        ret.magic_const.copy_from_slice(magic_const);
-
-        let prg_rom_size = content[4];
-        if prg_rom_size == 0 {
-            return Err("prg_rom_size can't be 0");
+        if content.len() < 16 {
+            return Err(RomError::TruncatedHeader);
         }
-        ret.prg_rom_size = prg_rom_size;
+        ret.magic_const.copy_from_slice(magic_const);
 
-        let chr_rom_size = content[5];
-        if chr_rom_size == 0 {
-            return Err("chr_rom_size can't be 0");
-        }
-        ret.chr_rom_size = chr_rom_size;
+        ret.prg_rom_size = content[4];
+        ret.chr_rom_size = content[5];
         ret.flags_6 = content[6];
         ret.flags_7 = content[7];
         ret.flags_8 = content[8];
         ret.flags_9 = content[9];
         ret.flags_10 = content[10];
+        ret.flags_11 = content[11];
+        ret.flags_12 = content[12];
+        ret.flags_13 = content[13];
+        ret.flags_14 = content[14];
+        ret.flags_15 = content[15];
+
+        if ret.checked_prg_rom_bytes().is_none() {
+            return Err(RomError::InvalidRomSize("PRG-ROM"));
+        }
+        if ret.checked_chr_rom_bytes().is_none() {
+            return Err(RomError::InvalidRomSize("CHR-ROM"));
+        }
+        if ret.prg_rom_bytes() == 0 {
+            return Err(RomError::UnsupportedFormat("the header says there's no PRG-ROM"));
+        }
 
         Ok(ret)
     }
 
     pub fn format_version(&self) -> HeaderVersion {
         if self.flags_7 & 0x0C == 0x08 {
-            panic!("INES format V2 isn't support yet");
+            return HeaderVersion::V2;
         }
         HeaderVersion::V1
     }
 
-    pub fn mapper_id(&self) -> u8 {
+    fn is_v2(&self) -> bool {
+        self.format_version() == HeaderVersion::V2
+    }
+
+    // NES 2.0 sizes are either a 12-bit count of units or, when the MSB nibble is $F, an
+    // exponent-multiplier pair: 2^E * (MM * 2 + 1) bytes, which can overflow
+    fn rom_size(lsb: u8, msb: u8, unit: usize) -> Option<usize> {
+        match msb {
+            0xF => {
+                let exponent = (lsb >> 2) as u32;
+                let multiplier = (lsb & 0x3) as usize * 2 + 1;
+                2usize.checked_pow(exponent).and_then(|size| size.checked_mul(multiplier))
+            }
+            _ => ((msb as usize) << 8 | lsb as usize).checked_mul(unit),
+        }
+    }
+
+    // NES 2.0 RAM sizes are shift counts, 0 meaning there's none
+    fn shift_size(shift: u8) -> usize {
+        match shift {
+            0 => 0,
+            shift => 64 << shift,
+        }
+    }
+
+    fn checked_prg_rom_bytes(&self) -> Option<usize> {
+        match self.is_v2() {
+            true => Header::rom_size(self.prg_rom_size, self.flags_9 & 0x0F, PRG_ROM_SIZE_FACTOR),
+            false => Some(self.prg_rom_size as usize * PRG_ROM_SIZE_FACTOR),
+        }
+    }
+
+    fn checked_chr_rom_bytes(&self) -> Option<usize> {
+        match self.is_v2() {
+            true => Header::rom_size(self.chr_rom_size, self.flags_9 >> 4, CHR_ROM_SIZE_FACTOR),
+            false => Some(self.chr_rom_size as usize * CHR_ROM_SIZE_FACTOR),
+        }
+    }
+
+    /// PRG-ROM size in bytes, `Header::from` rejects sizes that don't fit in a usize
+    pub fn prg_rom_bytes(&self) -> usize {
+        self.checked_prg_rom_bytes().unwrap_or(0)
+    }
+
+    /// CHR-ROM size in bytes, `Header::from` rejects sizes that don't fit in a usize
+    pub fn chr_rom_bytes(&self) -> usize {
+        self.checked_chr_rom_bytes().unwrap_or(0)
+    }
+
+    /// PRG-ROM size in 16KB banks (at least one, even for odd NES 2.0 sizes)
+    pub fn prg_rom_banks(&self) -> usize {
+        (self.prg_rom_bytes() / PRG_ROM_SIZE_FACTOR).max(1)
+    }
+
+    /// CHR-ROM size in 8KB banks
+    pub fn chr_rom_banks(&self) -> usize {
+        self.chr_rom_bytes() / CHR_ROM_SIZE_FACTOR
+    }
+
+    /// Size of whatever the pattern tables are made of in 8KB banks, CHR-RAM when there's no
+    /// CHR-ROM (at least one)
+    pub fn chr_banks(&self) -> usize {
+        match self.chr_rom_bytes() {
+            0 => (self.chr_ram_size() / CHR_ROM_SIZE_FACTOR).max(1),
+            _ => self.chr_rom_banks().max(1),
+        }
+    }
+
+    /// Total PRG-RAM size in bytes, battery-backed or not. Hardly any iNES dump fills flags 8
+    /// in, so 0 means 8KB there for compatibility. The trainer lives at $7000, so there's at
+    /// least 8KB whenever one is present.
+    pub fn prg_ram_size(&self) -> usize {
+        let size = match self.is_v2() {
+            true => Header::shift_size(self.flags_10 & 0x0F) + self.prg_nvram_size(),
+            false => self.flags_8.max(1) as usize * PRG_RAM_SIZE_FACTOR,
+        };
+        match self.has_trainer() {
+            true => size.max(PRG_RAM_SIZE_FACTOR),
+            false => size,
+        }
+    }
+
+    /// Battery-backed part of the PRG-RAM. iNES only has the battery flag, in which case all of
+    /// it is.
+    pub fn prg_nvram_size(&self) -> usize {
+        match self.is_v2() {
+            true => Header::shift_size(self.flags_10 >> 4),
+            false if self.has_battery() => self.prg_ram_size(),
+            false => 0,
+        }
+    }
+
+    /// CHR-RAM size in bytes (including the battery-backed part). iNES boards without CHR-ROM
+    /// get 8KB.
+    pub fn chr_ram_size(&self) -> usize {
+        match self.is_v2() {
+            true => Header::shift_size(self.flags_11 & 0x0F) + self.chr_nvram_size(),
+            false if self.chr_rom_size == 0 => CHR_ROM_SIZE_FACTOR,
+            false => 0,
+        }
+    }
+
+    pub fn chr_nvram_size(&self) -> usize {
+        match self.is_v2() {
+            true => Header::shift_size(self.flags_11 >> 4),
+            false => 0,
+        }
+    }
+
+    pub fn has_battery(&self) -> bool {
+        self.flags_6 & 0x2 == 0x2
+    }
+
+    pub fn has_trainer(&self) -> bool {
+        self.flags_6 & 0x4 == 0x4
+    }
+
+    /// NES 2.0 tells boards with and without bus conflicts apart through submapper 1 and 2 of
+    /// the discrete logic mappers, iNES uses an unofficial bit in flags 10
+    pub fn has_bus_conflicts(&self) -> bool {
+        match self.is_v2() {
+            true => matches!(self.mapper_id(), 2 | 3 | 7) && self.submapper() == 2,
+            false => self.flags_10 & 0x20 == 0x20,
+        }
+    }
+
+    pub fn mapper_id(&self) -> u16 {
+        let mapper_id = (self.flags_6 >> 4) as u16;
         match self.format_version() {
-            HeaderVersion::V1 => (self.flags_6 >> 4) | (self.flags_7 & 0xF0),
-            _ => panic!("INES format V2 isn't support yet"),
+            HeaderVersion::V2 => mapper_id | (self.flags_7 & 0xF0) as u16 | ((self.flags_8 & 0x0F) as u16) << 8,
+            // old rippers left their name in bytes 7-15, the upper nibble can't be trusted then
+            HeaderVersion::V1 if self.flags_12 | self.flags_13 | self.flags_14 | self.flags_15 != 0 => mapper_id,
+            HeaderVersion::V1 => mapper_id | (self.flags_7 & 0xF0) as u16,
+        }
+    }
+
+    /// Board variant of the mapper, always 0 for iNES headers
+    pub fn submapper(&self) -> u8 {
+        match self.is_v2() {
+            true => self.flags_8 >> 4,
+            false => 0,
+        }
+    }
+
+    pub fn timing(&self) -> Timing {
+        let timing = match self.is_v2() {
+            true => self.flags_12 & 0x3,
+            false => self.flags_9 & 0x1,
+        };
+        match timing {
+            0 => Timing::Ntsc,
+            1 => Timing::Pal,
+            2 => Timing::MultiRegion,
+            _ => Timing::Dendy,
+        }
+    }
+
+    /// Vs. System PPU type (RP2C03B, RP2C04-0001...) as numbered by NES 2.0, None for anything
+    /// else than a Vs. System
+    pub fn vs_ppu_type(&self) -> Option<u8> {
+        match self.is_v2() && self.flags_7 & 0x3 == 0x1 {
+            true => Some(self.flags_13 & 0x0F),
+            false => None,
+        }
+    }
+
+    /// Vs. System hardware type (Vs. Unisystem, Vs. Dual System...) as numbered by NES 2.0
+    pub fn vs_hardware_type(&self) -> Option<u8> {
+        self.vs_ppu_type().map(|_| self.flags_13 >> 4)
+    }
+
+    /// Number of miscellaneous ROMs following CHR-ROM
+    pub fn misc_rom_count(&self) -> u8 {
+        match self.is_v2() {
+            true => self.flags_14 & 0x3,
+            false => 0,
+        }
+    }
+
+    /// Default expansion device (standard controllers, Zapper...) as numbered by NES 2.0, 0 means
+    /// unspecified
+    pub fn default_expansion_device(&self) -> u8 {
+        match self.is_v2() {
+            true => self.flags_15 & 0x3F,
+            false => 0,
         }
     }
 }
@@ -121,6 +353,14 @@ mod test {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_prg_ram_size() {
+        let mut header = Header::new();
+        assert_eq!(header.prg_ram_size(), 8192);
+        header.flags_8 = 4;
+        assert_eq!(header.prg_ram_size(), 32768);
+    }
+
     #[test]
     fn test_header_validate_min_sizes() {
         let x = vec![0x4E, 0x45, 0x53, 0x1A, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
@@ -131,12 +371,108 @@ mod test {
         let result = Header::from(&x);
         assert!(result.is_err());
 
+        // no CHR-ROM means CHR-RAM
         let x = vec![0x4E, 0x45, 0x53, 0x1A, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
         let result = Header::from(&x);
-        assert!(result.is_err());
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap().chr_ram_size(), CHR_ROM_SIZE_FACTOR);
 
         let x = vec![0x4E, 0x45, 0x53, 0x1A, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
         let result = Header::from(&x);
         assert!(result.is_err());
     }
+
+    fn nes2(bytes: [u8; 12]) -> Header {
+        let mut content = vec![0x4E, 0x45, 0x53, 0x1A];
+        content.extend_from_slice(&bytes);
+        content[7] |= 0x08;
+        Header::from(&content).unwrap()
+    }
+
+    #[test]
+    fn test_nes2_mapper_and_submapper() {
+        // mapper $1A5, submapper 3
+        let header = nes2([1, 1, 0x50, 0xA0, 0x31, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(header.format_version(), HeaderVersion::V2);
+        assert_eq!(header.mapper_id(), 0x1A5);
+        assert_eq!(header.submapper(), 3);
+    }
+
+    #[test]
+    fn test_nes2_rom_sizes() {
+        // MSB nibbles: $102 * 16KB of PRG and $010 * 8KB of CHR
+        let header = nes2([0x02, 0x10, 0, 0, 0, 0x01, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(header.prg_rom_bytes(), 0x102 * PRG_ROM_SIZE_FACTOR);
+        assert_eq!(header.chr_rom_bytes(), 0x010 * CHR_ROM_SIZE_FACTOR);
+
+        // exponent-multiplier: 2^10 * 3 bytes of PRG, 2^13 * 1 bytes of CHR
+        let header = nes2([(10 << 2) | 1, 13 << 2, 0, 0, 0, 0xFF, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(header.prg_rom_bytes(), 3072);
+        assert_eq!(header.chr_rom_bytes(), 8192);
+        assert_eq!(header.prg_rom_banks(), 1);
+    }
+
+    #[test]
+    fn test_nes2_oversized_rom() {
+        // 2^63 * 7 bytes of PRG
+        let mut x = vec![0x4E, 0x45, 0x53, 0x1A, 0xFF, 0, 0, 0x08, 0, 0x0F];
+        x.resize(16, 0);
+        assert!(matches!(Header::from(&x), Err(RomError::InvalidRomSize("PRG-ROM"))));
+
+        // same for CHR, PRG being fine
+        let mut x = vec![0x4E, 0x45, 0x53, 0x1A, 1, 0xFF, 0, 0x08, 0, 0xF0];
+        x.resize(16, 0);
+        assert!(matches!(Header::from(&x), Err(RomError::InvalidRomSize("CHR-ROM"))));
+    }
+
+    #[test]
+    fn test_nes2_ram_sizes() {
+        // 8KB of PRG-RAM, 32KB of PRG-NVRAM, 16KB of CHR-RAM and no CHR-NVRAM
+        let header = nes2([1, 1, 0x02, 0, 0, 0, 0x97, 0x08, 0, 0, 0, 0]);
+        assert_eq!(header.prg_nvram_size(), 32768);
+        assert_eq!(header.prg_ram_size(), 8192 + 32768);
+        assert_eq!(header.chr_ram_size(), 16384);
+        assert_eq!(header.chr_nvram_size(), 0);
+
+        // iNES: the battery backs all of the PRG-RAM and CHR-RAM only exists without CHR-ROM
+        let mut header = Header::new();
+        header.flags_6 = 0x2;
+        assert_eq!(header.prg_nvram_size(), 8192);
+        assert_eq!(header.chr_ram_size(), 8192);
+        header.chr_rom_size = 1;
+        assert_eq!(header.chr_ram_size(), 0);
+
+        // no PRG-RAM at all, but the trainer needs somewhere to go
+        let header = nes2([1, 1, 0x04, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(header.prg_ram_size(), 8192);
+        assert_eq!(header.prg_nvram_size(), 0);
+    }
+
+    #[test]
+    fn test_nes2_timing_and_misc_fields() {
+        // Vs. System with an RP2C04-0003, Dendy timing, 1 misc ROM and a Zapper
+        let header = nes2([1, 1, 0, 0x01, 0, 0, 0, 0, 0x03, 0x14, 0x01, 0x08]);
+        assert_eq!(header.timing(), Timing::Dendy);
+        assert_eq!(header.vs_ppu_type(), Some(4));
+        assert_eq!(header.vs_hardware_type(), Some(1));
+        assert_eq!(header.misc_rom_count(), 1);
+        assert_eq!(header.default_expansion_device(), 8);
+
+        let header = nes2([1, 1, 0, 0, 0, 0, 0, 0, 0x02, 0x14, 0, 0]);
+        assert_eq!(header.timing(), Timing::MultiRegion);
+        assert_eq!(header.vs_ppu_type(), None);
+
+        // iNES only knows about NTSC and PAL
+        let mut header = Header::new();
+        header.flags_9 = 0x1;
+        assert_eq!(header.timing(), Timing::Pal);
+    }
+
+    #[test]
+    fn test_ines_mapper_ignores_ripper_names() {
+        let content = b"NES\x1a\x01\x01\x10DiskDude!";
+        let header = Header::from(content).unwrap();
+        assert_eq!(header.format_version(), HeaderVersion::V1);
+        assert_eq!(header.mapper_id(), 1);
+    }
 }
@@ -0,0 +1,147 @@
+/// Components that are able to drive the CPU interrupt lines. Each of them owns a bit on the
+/// line so one source releasing it doesn't affect the others (both lines are open-collector)
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum InterruptSource {
+    Ppu = 0,
+    ApuFrameCounter = 1,
+    Dmc = 2,
+    Mapper = 3,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Interrupt {
+    Nmi,
+    Irq,
+}
+
+/// Models the /NMI and /IRQ lines of the 2A03.
+///
+/// NMI is edge-sensitive: the CPU only cares about the line going from released to asserted so
+/// that transition is latched until the CPU acknowledges it. IRQ is level-sensitive: it stays
+/// active for as long as any source keeps asserting it.
+#[derive(Debug, Default)]
+pub struct InterruptController {
+    nmi_line: u8,
+    irq_line: u8,
+    nmi_pending: bool,
+}
+
+impl InterruptController {
+    pub fn new() -> Self {
+        InterruptController {
+            nmi_line: 0,
+            irq_line: 0,
+            nmi_pending: false,
+        }
+    }
+
+    pub fn assert_nmi(&mut self, source: InterruptSource) {
+        if self.nmi_line == 0 {
+            self.nmi_pending = true;
+        }
+        self.nmi_line |= 1 << (source as u8);
+    }
+
+    pub fn release_nmi(&mut self, source: InterruptSource) {
+        self.nmi_line &= !(1 << (source as u8));
+    }
+
+    pub fn set_nmi(&mut self, source: InterruptSource, asserted: bool) {
+        if asserted {
+            self.assert_nmi(source);
+        } else {
+            self.release_nmi(source);
+        }
+    }
+
+    pub fn assert_irq(&mut self, source: InterruptSource) {
+        self.irq_line |= 1 << (source as u8);
+    }
+
+    pub fn release_irq(&mut self, source: InterruptSource) {
+        self.irq_line &= !(1 << (source as u8));
+    }
+
+    pub fn set_irq(&mut self, source: InterruptSource, asserted: bool) {
+        if asserted {
+            self.assert_irq(source);
+        } else {
+            self.release_irq(source);
+        }
+    }
+
+    /// Whether a NMI edge has been detected and not yet serviced by the CPU
+    pub fn is_nmi_pending(&self) -> bool {
+        self.nmi_pending
+    }
+
+    pub fn acknowledge_nmi(&mut self) {
+        self.nmi_pending = false;
+    }
+
+    pub fn is_irq_asserted(&self) -> bool {
+        self.irq_line != 0
+    }
+
+    pub fn is_irq_asserted_by(&self, source: InterruptSource) -> bool {
+        self.irq_line & (1 << (source as u8)) != 0
+    }
+
+    pub fn reset(&mut self) {
+        self.nmi_pending = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use InterruptSource::*;
+
+    #[test]
+    fn test_nmi_edge_detection() {
+        let mut ctrl = InterruptController::new();
+        assert!(!ctrl.is_nmi_pending());
+
+        ctrl.assert_nmi(Ppu);
+        assert!(ctrl.is_nmi_pending());
+        ctrl.acknowledge_nmi();
+
+        // holding the line doesn't trigger another NMI
+        ctrl.assert_nmi(Ppu);
+        assert!(!ctrl.is_nmi_pending());
+
+        // it has to be released and asserted again
+        ctrl.release_nmi(Ppu);
+        assert!(!ctrl.is_nmi_pending());
+        ctrl.set_nmi(Ppu, true);
+        assert!(ctrl.is_nmi_pending());
+    }
+
+    #[test]
+    fn test_nmi_stays_latched_after_release() {
+        let mut ctrl = InterruptController::new();
+        ctrl.assert_nmi(Ppu);
+        ctrl.release_nmi(Ppu);
+        assert!(ctrl.is_nmi_pending());
+    }
+
+    #[test]
+    fn test_irq_shared_line() {
+        let mut ctrl = InterruptController::new();
+        assert!(!ctrl.is_irq_asserted());
+
+        ctrl.assert_irq(ApuFrameCounter);
+        ctrl.assert_irq(Mapper);
+        assert!(ctrl.is_irq_asserted());
+        assert!(ctrl.is_irq_asserted_by(Mapper));
+        assert!(!ctrl.is_irq_asserted_by(Dmc));
+
+        // line stays low while any source is still asserting it
+        ctrl.release_irq(Mapper);
+        assert!(ctrl.is_irq_asserted());
+        assert!(!ctrl.is_irq_asserted_by(Mapper));
+
+        ctrl.set_irq(ApuFrameCounter, false);
+        assert!(!ctrl.is_irq_asserted());
+    }
+}
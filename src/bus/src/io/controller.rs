@@ -0,0 +1,111 @@
+/// Buttons of the standard NES controller, in the order they are shifted out
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(u8)]
+pub enum Button {
+    A = 1 << 0,
+    B = 1 << 1,
+    Select = 1 << 2,
+    Start = 1 << 3,
+    Up = 1 << 4,
+    Down = 1 << 5,
+    Left = 1 << 6,
+    Right = 1 << 7,
+}
+
+/// Standard controller which is basically a 4021 parallel-to-serial shift register. While the
+/// strobe is high the button states keep being latched, once it goes low every read shifts out
+/// one button.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Controller {
+    buttons: u8,
+    shift_register: u8,
+    strobe: bool,
+}
+
+impl Controller {
+    pub fn new() -> Self {
+        Controller {
+            buttons: 0,
+            shift_register: 0,
+            strobe: false,
+        }
+    }
+
+    pub fn set_button(&mut self, button: Button, pressed: bool) {
+        if pressed {
+            self.buttons |= button as u8;
+        } else {
+            self.buttons &= !(button as u8);
+        }
+    }
+
+    pub fn set_strobe(&mut self, strobe: bool) {
+        self.strobe = strobe;
+        if strobe {
+            self.shift_register = self.buttons;
+        }
+    }
+
+    pub fn read(&mut self) -> u8 {
+        if self.strobe {
+            self.shift_register = self.buttons;
+        }
+
+        let value = self.peek();
+        // official controllers report 1s once all 8 buttons have been read
+        self.shift_register = (self.shift_register >> 1) | 0x80;
+        value
+    }
+
+    pub fn peek(&self) -> u8 {
+        if self.strobe {
+            return self.buttons & 0x1;
+        }
+        self.shift_register & 0x1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buttons_are_shifted_out_in_order() {
+        let mut controller = Controller::new();
+        controller.set_button(Button::A, true);
+        controller.set_button(Button::Start, true);
+        controller.set_button(Button::Right, true);
+        controller.set_strobe(true);
+        controller.set_strobe(false);
+
+        let reads: Vec<u8> = (0..10).map(|_| controller.read()).collect();
+        assert_eq!(reads, vec![1, 0, 0, 1, 0, 0, 0, 1, 1, 1]);
+    }
+
+    #[test]
+    fn test_strobe_high_keeps_returning_a() {
+        let mut controller = Controller::new();
+        controller.set_button(Button::A, true);
+        controller.set_strobe(true);
+        assert_eq!(controller.read(), 1);
+        assert_eq!(controller.read(), 1);
+
+        controller.set_button(Button::A, false);
+        assert_eq!(controller.read(), 0);
+    }
+
+    #[test]
+    fn test_peek_does_not_shift() {
+        let mut controller = Controller::new();
+        controller.set_button(Button::B, true);
+        controller.set_strobe(true);
+        controller.set_strobe(false);
+
+        for _ in 0..4 {
+            assert_eq!(controller.peek(), 0);
+        }
+        assert_eq!(controller.read(), 0);
+        assert_eq!(controller.peek(), 1);
+        assert_eq!(controller.read(), 1);
+    }
+}
@@ -0,0 +1,102 @@
+use crate::memory::BusDevice;
+use self::controller::Controller;
+
+pub mod controller;
+
+/// APU and I/O registers living at $4000-$401F.
+///
+/// Audio isn't emulated yet so writes to the APU registers are simply dropped, and reads behave
+/// the way the real hardware does when nothing drives the data bus.
+pub struct IoRegisters {
+    controllers: [Controller; 2],
+}
+
+impl Default for IoRegisters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IoRegisters {
+    pub fn new() -> Self {
+        IoRegisters {
+            controllers: [Controller::new(); 2],
+        }
+    }
+
+    pub fn controller(&self, port: usize) -> &Controller {
+        &self.controllers[port]
+    }
+
+    pub fn controller_mut(&mut self, port: usize) -> &mut Controller {
+        &mut self.controllers[port]
+    }
+}
+
+impl BusDevice for IoRegisters {
+    fn read(&mut self, addr: u16, open_bus: u8) -> u8 {
+        match addr {
+            // Controller ports: only the lowest bits are driven
+            0x16 | 0x17 => (open_bus & 0xE0) | self.controllers[(addr - 0x16) as usize].read(),
+            _ => self.peek(addr, open_bus),
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        // both controllers share the strobe line
+        if addr == 0x16 {
+            for controller in self.controllers.iter_mut() {
+                controller.set_strobe(value & 0x1 != 0);
+            }
+        }
+    }
+
+    fn peek(&self, addr: u16, open_bus: u8) -> u8 {
+        match addr {
+            // APU Status: bit 5 isn't driven and every channel is silent
+            0x15 => open_bus & 0x20,
+            0x16 | 0x17 => (open_bus & 0xE0) | self.controllers[(addr - 0x16) as usize].peek(),
+            // write-only APU registers and the (disabled) CPU test registers
+            _ => open_bus,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::controller::Button;
+
+    #[test]
+    fn test_write_only_registers_are_open_bus() {
+        let mut io = IoRegisters::new();
+        for addr in 0x00..=0x14 {
+            io.write(addr, 0x12);
+            assert_eq!(io.read(addr, 0x40), 0x40);
+        }
+        for addr in 0x18..=0x1F {
+            assert_eq!(io.read(addr, 0x40), 0x40);
+        }
+    }
+
+    #[test]
+    fn test_partially_driven_registers() {
+        let mut io = IoRegisters::new();
+        assert_eq!(io.read(0x15, 0xFF), 0x20);
+        assert_eq!(io.read(0x16, 0xFF), 0xE0);
+        assert_eq!(io.read(0x17, 0x41), 0x40);
+    }
+
+    #[test]
+    fn test_controller_ports() {
+        let mut io = IoRegisters::new();
+        io.controller_mut(1).set_button(Button::A, true);
+        io.write(0x16, 0x1);
+        io.write(0x16, 0x0);
+
+        assert_eq!(io.peek(0x17, 0x40), 0x41);
+        assert_eq!(io.read(0x17, 0x40), 0x41);
+        assert_eq!(io.read(0x17, 0x40), 0x40);
+        assert_eq!(io.read(0x16, 0x40), 0x40);
+    }
+}
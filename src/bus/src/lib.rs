@@ -1,68 +1,207 @@
+use std::cell::{Ref, RefCell};
+use std::mem::take;
+use std::rc::Rc;
 use crate::cartridge::Cartridge;
+use crate::cartridge::mappers::mmc3::Mmc3Revision;
+use crate::dma::{DmaCycle, OamDma};
+use crate::inesformat::error::RomError;
+use crate::interrupt::{InterruptController, InterruptSource};
+use crate::io::IoRegisters;
+use crate::memory::{BusDevice, Device, MemoryMap, Ram};
+use crate::mos6502::Mos6502;
 use crate::rp2c02::PPU;
 
-// Notes to myself:
-//     - Implement some sort of subscribe mechanism that allow components to register their
-//         ranges so we can iterate through the list to find out which of them read/write op is
-//         meant to be carried out
-//
-//     - implement logic to write/read data to/from the right component in the bus
 pub mod mos6502;
 pub mod rp2c02;
 pub mod inesformat;
 pub mod cartridge;
+pub mod interrupt;
+pub mod dma;
+pub mod memory;
+pub mod io;
 
 const RAM_SIZE: u16 = 0x0800; // CPU has a whopping 2KB RAM
 // const MAX_ROM_SIZE: usize = (RAM_SIZE - ROM_START_ADDR) as usize;
 pub const ROM_START_ADDR: u16 = 0x8000;
+const OAM_DMA_ADDR: u16 = 0x4014;
+// APU status is read inside the 2A03, the value never makes it to the external data bus
+const APU_STATUS_ADDR: u16 = 0x4015;
 
 pub struct Bus {
-    cpu_ram: [u8; RAM_SIZE as usize + 1],
+    memory_map: MemoryMap,
+    cpu_ram: Ram,
+    // last value seen on the CPU data bus, returned for anything that isn't driven by a device
+    open_bus: u8,
     system_clock: u64,
-    cartridge: Cartridge,
+    // shared with the PPU which fetches pattern data from it
+    cartridge: Rc<RefCell<Cartridge>>,
     ppu: PPU,
+    io: IoRegisters,
+    cpu: Mos6502,
+    interrupts: InterruptController,
+    // started by a write to $4014, it takes over the CPU once the current instruction is done
+    oam_dma: Option<OamDma>,
+}
+
+impl Default for Bus {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Bus {
     pub fn new() -> Self {
+        let mut memory_map = MemoryMap::new();
+        // CPU address space as seen by the NES. Cartridge regions receive the full address as
+        // mappers are free to decode it however they want.
+        let regions = [
+            (0x0000, 0x1FFF, 0x07FF, Device::Ram),
+            (0x2000, 0x3FFF, 0x0007, Device::PpuRegisters),
+            (0x4000, 0x401F, 0x001F, Device::ApuIo),
+            (0x4020, 0x5FFF, 0xFFFF, Device::CartridgeExpansion),
+            (0x6000, 0x7FFF, 0xFFFF, Device::PrgRam),
+            (0x8000, 0xFFFF, 0xFFFF, Device::PrgRom),
+        ];
+        for (start, end, mirror_mask, device) in regions {
+            memory_map.register(start, end, mirror_mask, device).expect("invalid memory map");
+        }
+
+        let cartridge = Rc::new(RefCell::new(Cartridge::new()));
+        let mut ppu = PPU::new();
+        ppu.connect_cartridge(cartridge.clone());
+
         Bus {
-            cpu_ram: [0; RAM_SIZE as usize + 1],
+            memory_map,
+            cpu_ram: Ram::new(RAM_SIZE as usize),
+            open_bus: 0,
             system_clock: 0,
-            cartridge: Cartridge::new(),
-            ppu: PPU::new(),
+            cartridge,
+            ppu,
+            io: IoRegisters::new(),
+            cpu: Mos6502::new(),
+            interrupts: InterruptController::new(),
+            oam_dma: None,
+        }
+    }
+
+    pub fn cpu(&self) -> &Mos6502 {
+        &self.cpu
+    }
+
+    pub fn cpu_mut(&mut self) -> &mut Mos6502 {
+        &mut self.cpu
+    }
+
+    pub fn ppu(&self) -> &PPU {
+        &self.ppu
+    }
+
+    pub fn ppu_mut(&mut self) -> &mut PPU {
+        &mut self.ppu
+    }
+
+    pub fn interrupts(&self) -> &InterruptController {
+        &self.interrupts
+    }
+
+    pub fn interrupts_mut(&mut self) -> &mut InterruptController {
+        &mut self.interrupts
+    }
+
+    pub fn memory_map(&self) -> &MemoryMap {
+        &self.memory_map
+    }
+
+    fn device(&self, device: Device) -> &dyn BusDevice {
+        match device {
+            Device::Ram => &self.cpu_ram,
+            Device::PpuRegisters => &self.ppu,
+            Device::ApuIo => &self.io,
+            Device::CartridgeExpansion | Device::PrgRam | Device::PrgRom => &self.cartridge,
         }
     }
 
-    pub fn cpu_read_u8(&self, addr: u16, read_only: bool) -> u8 {
-        if addr <= 0x1FFF {
-            return self.cpu_ram[(addr & 0x07FF) as usize]
-        } else if addr >= 0x2000 && addr <= 0x3FFF {
-            return self.ppu.cpu_read_u8(addr & 0x7, read_only);
+    fn device_mut(&mut self, device: Device) -> &mut dyn BusDevice {
+        match device {
+            Device::Ram => &mut self.cpu_ram,
+            Device::PpuRegisters => &mut self.ppu,
+            Device::ApuIo => &mut self.io,
+            Device::CartridgeExpansion | Device::PrgRam | Device::PrgRom => &mut self.cartridge,
         }
-        panic!("invalid memory address requested... aborting")
+    }
+
+    pub fn io(&self) -> &IoRegisters {
+        &self.io
+    }
+
+    pub fn io_mut(&mut self) -> &mut IoRegisters {
+        &mut self.io
+    }
+
+    /// Reads a byte from the CPU address space. Addresses no device answers to return whatever
+    /// was last on the data bus. When `read_only` is set this behaves exactly like cpu_peek_u8.
+    pub fn cpu_read_u8(&mut self, addr: u16, read_only: bool) -> u8 {
+        if read_only {
+            return self.cpu_peek_u8(addr);
+        }
+
+        let open_bus = self.open_bus;
+        let Some((device, device_addr)) = self.memory_map.resolve(addr) else {
+            return open_bus;
+        };
+
+        let value = self.device_mut(device).read(device_addr, open_bus);
+        if addr != APU_STATUS_ADDR {
+            self.open_bus = value;
+        }
+        value
+    }
+
+    /// Returns what a read would return without any of its side effects: status flags aren't
+    /// acknowledged, shift registers don't move and no latch gets updated. That's what debugger
+    /// views and the disassembler must use so they can't change the outcome of the emulation.
+    pub fn cpu_peek_u8(&self, addr: u16) -> u8 {
+        match self.memory_map.resolve(addr) {
+            Some((device, device_addr)) => self.device(device).peek(device_addr, self.open_bus),
+            None => self.open_bus,
+        }
+    }
+
+    pub fn cpu_peek_u16(&self, addr: u16) -> u16 {
+        let low = self.cpu_peek_u8(addr);
+        let high = self.cpu_peek_u8(addr.wrapping_add(1));
+        ((high as u16) << 8) | low as u16
     }
 
     pub fn cpu_read_u8_slice(&self, from: u16, to: u16) -> &[u8] {
         if from <= 0x1FFF && to <= 0x1FFF && from < to {
-            return &self.cpu_ram[((from & 0x07FF) as usize)..((to & 0x07FF) as usize)]
+            return &self.cpu_ram.as_slice()[((from & 0x07FF) as usize)..((to & 0x07FF) as usize)]
         }
         //TODO implement ppu cpu read if necessary (doesn't seem like it but we never know)
         panic!("invalid memory range requested... aborting")
     }
 
-    pub fn cpu_read_u16(&self, addr: u16, read_only: bool) -> u16 {
+    pub fn cpu_read_u16(&mut self, addr: u16, read_only: bool) -> u16 {
         let low = self.cpu_read_u8(addr, read_only);
-        let high = self.cpu_read_u8(addr + 1, read_only);
+        let high = self.cpu_read_u8(addr.wrapping_add(1), read_only);
         ((high as u16) << 8) | low as u16
     }
 
     pub fn cpu_write_u8(&mut self, addr: u16, value: u8) {
-        if addr <= 0x1FFF {
-            self.cpu_ram[(addr & 0x07FF) as usize] = value;
-        } else if addr >= 0x2000 && addr <= 0x3FFF {
-            self.ppu.cpu_write_u8(addr & 0x7, value);
-        }else {
-            panic!("invalid memory address requested... aborting")
+        // the CPU drives the data bus during writes regardless of anyone listening
+        self.open_bus = value;
+        let resolved = self.memory_map.resolve(addr);
+        if let Some((device, device_addr)) = resolved {
+            self.device_mut(device).write(device_addr, value);
+        }
+        if addr == OAM_DMA_ADDR {
+            self.oam_dma = Some(OamDma::new(value));
+        }
+        // the cartridge connector carries the whole CPU address bus, some mappers (MMC5) keep
+        // track of the PPU configuration by watching those writes
+        match resolved {
+            Some((Device::CartridgeExpansion | Device::PrgRam | Device::PrgRom, _)) => {}
+            _ => self.cartridge.borrow_mut().snoop_cpu_write(addr, value),
         }
     }
 
@@ -73,23 +212,111 @@ impl Bus {
         self.cpu_write_u8(addr + 1, high);
     }
 
-    pub fn load_cartridge(&mut self, filename: &str) -> Result<(), &str> {
-        self.cartridge.load(filename).expect("failed to load cartridge");
+    pub fn cartridge(&self) -> Ref<'_, Cartridge> {
+        self.cartridge.borrow()
+    }
+
+    /// Inserting a cartridge is the same as powering the console on, so the system is reset
+    /// straight after
+    pub fn load_cartridge(&mut self, filename: &str) -> Result<(), RomError> {
+        self.cartridge.borrow_mut().load(filename)?;
+        self.reset();
+        Ok(())
+    }
+
+    pub fn load_cartridge_from_bytes(&mut self, bytes: &[u8]) -> Result<(), RomError> {
+        self.cartridge.borrow_mut().load_from_bytes(bytes)?;
+        self.reset();
         Ok(())
     }
 
+    pub fn unload_cartridge(&mut self) -> std::io::Result<()> {
+        self.cartridge.borrow_mut().unload()
+    }
+
+    /// Saves the battery-backed RAM next to the ROM, meant to be called every now and then and
+    /// before exiting so a crash doesn't lose much progress
+    pub fn flush_battery_ram(&mut self) -> std::io::Result<()> {
+        self.cartridge.borrow_mut().flush_battery_ram()
+    }
+
+    /// Save blob of the inserted cartridge (the contents of a .sav file), None without a battery
+    pub fn export_battery_ram(&self) -> Option<Vec<u8>> {
+        self.cartridge.borrow().battery_ram()
+    }
+
+    pub fn import_battery_ram(&mut self, data: &[u8]) {
+        self.cartridge.borrow_mut().restore_battery_ram(data);
+    }
+
+    /// See Cartridge::set_mmc3_revision
+    pub fn set_mmc3_revision(&mut self, revision: Option<Mmc3Revision>) {
+        self.cartridge.borrow_mut().set_mmc3_revision(revision);
+    }
+
     pub fn reset(&mut self) {
-        //cpu.reset()
+        self.interrupts.reset();
+        self.oam_dma = None;
+        self.ppu.reset();
+        self.cartridge.borrow_mut().reset();
+
+        // the CPU needs the bus to fetch the reset vector, so it's temporarily moved out of it
+        let mut cpu = take(&mut self.cpu);
+        cpu.reset(self);
+        self.cpu = cpu;
         self.system_clock = 0;
     }
 
+    /// Advances the whole system by one PPU clock cycle. The CPU runs 3 times slower than
+    /// the PPU so it only gets clocked on every third call, unless a DMA is holding it.
     pub fn clock(&mut self) {
-        //cpu.reset()
-        self.system_clock += 1;
+        self.ppu.clock();
         if self.system_clock % 3 == 0 {
-            //cpu.clock()
+            self.sync_interrupt_lines();
+            if self.oam_dma.is_some() && self.cpu.is_instruction_complete() {
+                self.oam_dma_cycle();
+            } else {
+                let mut cpu = take(&mut self.cpu);
+                cpu.clock(self);
+                self.cpu = cpu;
+            }
+            self.cartridge.borrow_mut().cpu_clock();
+        }
+        self.system_clock += 1;
+    }
+
+    /// One CPU cycle worth of OAM DMA. The bytes go through $2004 like any other write so they
+    /// land wherever OAMADDR points.
+    fn oam_dma_cycle(&mut self) {
+        let Some(mut dma) = self.oam_dma.take() else {
+            return;
+        };
+        let get = (self.system_clock / 3) % 2 == 0;
+        match dma.next_cycle(get) {
+            DmaCycle::Read(addr) => dma.latch(self.cpu_read_u8(addr, false)),
+            DmaCycle::Write(value) => self.cpu_write_u8(0x2004, value),
+            DmaCycle::Halt | DmaCycle::Align => {}
+        }
+        if !dma.is_done() {
+            self.oam_dma = Some(dma);
         }
-        //ppu.clock()
+    }
+
+    /// Clocks the system until the PPU is done with the current frame, i.e. until vblank starts.
+    /// The picture is then complete in the PPU framebuffer.
+    pub fn run_frame(&mut self) {
+        let frame = self.ppu.frame();
+        while self.ppu.frame() == frame {
+            self.clock();
+        }
+    }
+
+    /// Components drive their interrupt outputs on their own so the lines are refreshed right
+    /// before the CPU gets a chance to poll them.
+    fn sync_interrupt_lines(&mut self) {
+        self.interrupts.set_nmi(InterruptSource::Ppu, self.ppu.nmi_asserted());
+        let mapper_irq = self.cartridge.borrow().irq_asserted();
+        self.interrupts.set_irq(InterruptSource::Mapper, mapper_irq);
     }
 }
 
@@ -101,10 +328,9 @@ mod test{
     use tempfile::NamedTempFile;
     use filename::file_name;
     use crate::inesformat::format::{CHR_ROM_SIZE_FACTOR, PRG_ROM_SIZE_FACTOR};
+    use crate::io::controller::Button;
 
     pub fn generate_rom(add_trainer: bool, mapper_id: u8, ines_file_version: u8) -> (NamedTempFile, String) {
-        let mut tmp_file = NamedTempFile::new().unwrap();
-
         // header
         let mut contents:Vec<u8> = vec![
             0x4E,
@@ -122,29 +348,364 @@ mod test{
             contents.resize(contents.len() + 512, 0xFF);
         }
 
-        if ines_file_version == 1 {
-            //  prg_rom
-            contents.resize(contents.len() + contents[4] as usize * PRG_ROM_SIZE_FACTOR, 0xEE);
-
-            //  chr_rom
-            contents.resize(contents.len() + contents[5] as usize * CHR_ROM_SIZE_FACTOR, 0xDD);
-        } else if ines_file_version == 2 {
+        if ines_file_version == 2 {
             contents[7] |= 0x08;
         }
 
-        tmp_file.write_all(contents.as_slice()).expect("failed to write");
+        //  prg_rom
+        contents.resize(contents.len() + contents[4] as usize * PRG_ROM_SIZE_FACTOR, 0xEE);
+
+        //  chr_rom
+        contents.resize(contents.len() + contents[5] as usize * CHR_ROM_SIZE_FACTOR, 0xDD);
+
+        write_rom(&contents)
+    }
+
+    pub fn write_rom(contents: &[u8]) -> (NamedTempFile, String) {
+        let mut tmp_file = NamedTempFile::new().unwrap();
+        tmp_file.write_all(contents).expect("failed to write");
 
-        // filename
         let filename = file_name(&tmp_file.as_raw_fd()).unwrap();
         let os_str = filename.into_os_string();
 
         (tmp_file, String::from(os_str.to_str().unwrap()))
     }
 
+    /// NROM image (mapper 0) with the given PRG-ROM, which must be either 16KB or 32KB long
+    pub fn generate_nrom(prg_rom: &[u8], flags_6: u8) -> (NamedTempFile, String) {
+        let mut contents: Vec<u8> = vec![
+            0x4E, 0x45, 0x53, 0x1A,
+            (prg_rom.len() / PRG_ROM_SIZE_FACTOR) as u8,
+            1,
+            flags_6 & 0x0F,
+            0, 0, 0, 0, 0, 0, 0, 0, 0];
+        contents.extend_from_slice(prg_rom);
+        contents.resize(contents.len() + CHR_ROM_SIZE_FACTOR, 0xDD);
+        write_rom(&contents)
+    }
+
+    /// ROM image where every 8KB of PRG-ROM starts with its own index and every 1KB of CHR-ROM
+    /// is filled with its own index, which makes it easy to tell which banks got mapped where.
+    /// Every 16KB bank ends with a `JMP $FF00` loop the vectors point to, so the CPU can be
+    /// clocked safely whatever ends up being mapped.
+    pub fn banked_rom_image(mapper_id: u8, prg_rom_size: u8, chr_rom_size: u8) -> Vec<u8> {
+        let mut contents: Vec<u8> = vec![
+            0x4E, 0x45, 0x53, 0x1A,
+            prg_rom_size,
+            chr_rom_size,
+            (mapper_id & 0x0F) << 4,
+            mapper_id & 0xF0,
+            0, 0, 0, 0, 0, 0, 0, 0];
+
+        for bank in 0..prg_rom_size as usize {
+            let mut prg_bank = vec![0xEA; PRG_ROM_SIZE_FACTOR];
+            prg_bank[0x0000] = (bank * 2) as u8;
+            prg_bank[0x2000] = (bank * 2 + 1) as u8;
+            prg_bank[0x3F00..0x3F03].copy_from_slice(&[0x4C, 0x00, 0xFF]);
+            prg_bank[0x3FFA..].copy_from_slice(&[0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF]);
+            contents.extend_from_slice(&prg_bank);
+        }
+
+        for bank in 0..(chr_rom_size as usize * CHR_ROM_SIZE_FACTOR / 0x400) {
+            contents.resize(contents.len() + 0x400, bank as u8);
+        }
+        contents
+    }
+
+    pub fn bus_with_rom(contents: &[u8]) -> Bus {
+        let mut bus = Bus::new();
+        bus.load_cartridge_from_bytes(contents).expect("failed to load cartridge");
+        bus
+    }
+
+    /// Bus with a 16KB NROM cartridge inserted whose interrupt vectors point to the given addresses
+    pub fn bus_with_vectors(nmi: u16, reset: u16, irq: u16) -> Bus {
+        let mut prg_rom = vec![0xEE; PRG_ROM_SIZE_FACTOR];
+        prg_rom[0x3FFA..].copy_from_slice(&[
+            nmi as u8, (nmi >> 8) as u8,
+            reset as u8, (reset >> 8) as u8,
+            irq as u8, (irq >> 8) as u8,
+        ]);
+
+        let (_tmp_file, filename) = generate_nrom(&prg_rom, 0);
+        let mut bus = Bus::new();
+        bus.load_cartridge(filename.as_str()).expect("failed to load cartridge");
+        bus
+    }
+
+    #[test]
+    fn test_battery_ram_import_export() {
+        let mut contents = banked_rom_image(0, 1, 1);
+        contents[6] |= 0x2;
+        let mut bus = bus_with_rom(&contents);
+        bus.cpu_write_u8(0x6000, 0x42);
+        let mut save = bus.export_battery_ram().unwrap();
+        assert_eq!(save.len(), 8192);
+        assert_eq!(save[0], 0x42);
+
+        save[1] = 0x24;
+        bus.import_battery_ram(&save);
+        assert_eq!(bus.cpu_peek_u8(0x6001), 0x24);
+
+        // no battery, no save
+        let bus = bus_with_rom(&banked_rom_image(0, 1, 1));
+        assert_eq!(bus.export_battery_ram(), None);
+    }
+
     #[test]
     fn test_memory_is_zeroed() {
         let bus = Bus::new();
-        assert_eq!(&[0; RAM_SIZE as usize + 1], &bus.cpu_ram[..]);
+        assert_eq!(&[0; RAM_SIZE as usize], bus.cpu_ram.as_slice());
+    }
+
+    #[test]
+    fn test_ram_is_mirrored() {
+        let mut bus = Bus::new();
+        bus.cpu_write_u8(0x0042, 0x24);
+        assert_eq!(bus.cpu_read_u8(0x0842, false), 0x24);
+        assert_eq!(bus.cpu_read_u8(0x1042, false), 0x24);
+        assert_eq!(bus.cpu_read_u8(0x1842, false), 0x24);
+
+        bus.cpu_write_u8(0x1FFF, 0x11);
+        assert_eq!(bus.cpu_read_u8(0x07FF, false), 0x11);
+    }
+
+    #[test]
+    fn test_unmapped_reads_return_open_bus() {
+        let mut bus = Bus::new();
+        bus.cpu_write_u8(0x0010, 0x5A);
+        assert_eq!(bus.cpu_read_u8(0x0010, false), 0x5A);
+
+        // nothing answers these without a cartridge (or at all for $4018) and none of them panic
+        assert_eq!(bus.cpu_read_u8(0x4018, false), 0x5A);
+        assert_eq!(bus.cpu_read_u8(0x5000, false), 0x5A);
+        assert_eq!(bus.cpu_read_u8(0x6000, false), 0x5A);
+        assert_eq!(bus.cpu_read_u8(0xFFFF, false), 0x5A);
+
+        // writes drive the data bus too
+        bus.cpu_write_u8(0x8000, 0xA5);
+        assert_eq!(bus.cpu_read_u8(0x4000, false), 0xA5);
+    }
+
+    #[test]
+    fn test_apu_status_does_not_drive_open_bus() {
+        let mut bus = Bus::new();
+        bus.cpu_write_u8(0x0010, 0xDF);
+        assert_eq!(bus.cpu_read_u8(0x0010, false), 0xDF);
+        assert_eq!(bus.cpu_read_u8(0x4015, false), 0x00);
+        assert_eq!(bus.cpu_read_u8(0x5000, false), 0xDF);
+    }
+
+    #[test]
+    fn test_ppu_registers_drive_their_own_latch() {
+        let mut bus = Bus::new();
+        bus.cpu_write_u8(0x2000, 0xFF);
+        // the CPU data bus moved on but the PPU still remembers the last write
+        bus.cpu_write_u8(0x0000, 0x00);
+        assert_eq!(bus.cpu_read_u8(0x2002, false), 0x1F);
+        assert_eq!(bus.cpu_read_u8(0x3FF9, false), 0x1F);
+        // which then becomes the CPU's open bus value
+        assert_eq!(bus.cpu_read_u8(0x4000, false), 0x1F);
+    }
+
+    // a, x, pc, flags and open bus after every system clock
+    type Trace = Vec<(u8, u8, u16, u8, u8)>;
+
+    fn run_with_optional_debugger(debugger_attached: bool) -> (Trace, Vec<u8>) {
+        let mut bus = Bus::new();
+        bus.io_mut().controller_mut(0).set_button(Button::A, true);
+        bus.io_mut().controller_mut(0).set_button(Button::Up, true);
+        bus.io_mut().controller_mut(1).set_button(Button::Right, true);
+        bus.cpu_write_u8(0x4016, 0x1);
+        bus.cpu_write_u8(0x4016, 0x0);
+        bus.cpu_write_u8(0x2001, 0xFF);
+
+        // loop: LDA $4016 ; ORA $2002 ; ADC $4017 ; TAX ; LDA $4015 ; JMP loop
+        let program = [
+            0xAD, 0x16, 0x40,
+            0x0D, 0x02, 0x20,
+            0x6D, 0x17, 0x40,
+            0xAA,
+            0xAD, 0x15, 0x40,
+            0x4C, 0x00, 0x02,
+        ];
+        for (i, byte) in program.iter().enumerate() {
+            bus.cpu_write_u8(0x0200 + i as u16, *byte);
+        }
+        bus.cpu.pc = 0x0200;
+
+        let mut trace = vec![];
+        for _ in 0..150 {
+            if debugger_attached {
+                // whatever a debugger panel could possibly look at
+                for addr in 0..=0xFFFF {
+                    bus.cpu_peek_u8(addr);
+                    bus.cpu_read_u8(addr, true);
+                }
+            }
+            bus.clock();
+            trace.push((bus.cpu.a, bus.cpu.x, bus.cpu.pc, bus.cpu.flags, bus.open_bus));
+        }
+        (trace, bus.cpu_ram.as_slice().to_vec())
+    }
+
+    #[test]
+    fn test_debugger_does_not_change_emulation() {
+        assert_eq!(run_with_optional_debugger(false), run_with_optional_debugger(true));
+    }
+
+    #[test]
+    fn test_prg_rom_is_mapped() {
+        let mut bus = bus_with_vectors(0x1234, 0xC000, 0x5678);
+        assert_eq!(bus.cpu_read_u16(0xFFFA, false), 0x1234);
+        assert_eq!(bus.cpu_read_u16(0xFFFE, false), 0x5678);
+        // NROM-128 mirrors its only bank
+        assert_eq!(bus.cpu_read_u16(0xBFFC, false), 0xC000);
+        assert_eq!(bus.cpu_read_u8(0x8000, false), 0xEE);
+
+        // writes to ROM go nowhere
+        bus.cpu_write_u8(0x8000, 0x00);
+        assert_eq!(bus.cpu_read_u8(0x8000, false), 0xEE);
+
+        // loading a cartridge resets the system using its reset vector
+        assert_eq!(bus.cpu().pc, 0xC000);
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_nrom_256() {
+        let mut prg_rom = vec![0xEE; 2 * PRG_ROM_SIZE_FACTOR];
+        prg_rom[0] = 0x11;
+        prg_rom[PRG_ROM_SIZE_FACTOR] = 0x22;
+        let (_tmp_file, filename) = generate_nrom(&prg_rom, 0);
+
+        let mut bus = Bus::new();
+        bus.load_cartridge(filename.as_str()).expect("failed to load cartridge");
+        assert_eq!(bus.cpu_read_u8(0x8000, false), 0x11);
+        assert_eq!(bus.cpu_read_u8(0xC000, false), 0x22);
+    }
+
+    #[test]
+    fn test_prg_ram() {
+        let mut bus = bus_with_vectors(0, 0, 0);
+        bus.cpu_write_u8(0x6000, 0x42);
+        bus.cpu_write_u8(0x7FFF, 0x24);
+        assert_eq!(bus.cpu_read_u8(0x6000, false), 0x42);
+        assert_eq!(bus.cpu_read_u8(0x7FFF, false), 0x24);
+    }
+
+    #[test]
+    fn test_read_only_accesses_leave_open_bus_alone() {
+        let mut bus = Bus::new();
+        bus.cpu_write_u8(0x0001, 0x77);
+        bus.cpu_write_u8(0x0000, 0x33);
+        assert_eq!(bus.cpu_read_u8(0x0001, true), 0x77);
+        assert_eq!(bus.cpu_read_u8(0x5000, false), 0x33);
+    }
+
+    #[test]
+    fn test_cpu_runs_every_third_clock() {
+        let mut bus = Bus::new();
+
+        // LDA #$42 ; LDX #$24
+        bus.cpu_write_u8(0x0200, 0xA9);
+        bus.cpu_write_u8(0x0201, 0x42);
+        bus.cpu_write_u8(0x0202, 0xA2);
+        bus.cpu_write_u8(0x0203, 0x24);
+        bus.cpu.pc = 0x0200;
+
+        // first system tick clocks the CPU which fetches LDA
+        bus.clock();
+        assert_eq!(bus.cpu.a, 0x42);
+        assert_eq!(bus.cpu.cycles, 1);
+
+        // LDA takes 2 CPU cycles which is 6 system ticks
+        for _ in 0..5 {
+            bus.clock();
+        }
+        assert!(bus.cpu.is_instruction_complete());
+        assert_eq!(bus.cpu.x, 0x00);
+        assert_eq!(bus.cpu.pc, 0x0202);
+
+        bus.clock();
+        assert_eq!(bus.cpu.x, 0x24);
+        assert_eq!(bus.cpu.pc, 0x0204);
+        assert_eq!(bus.system_clock, 7);
+    }
+
+    #[test]
+    fn test_vblank_nmi() {
+        let mut bus = bus_with_rom(&banked_rom_image(0, 1, 1));
+        bus.reset();
+        bus.cpu_write_u8(0x2000, 0x80);
+        let sp = bus.cpu.sp;
+
+        bus.run_frame();
+        assert_eq!(bus.ppu().frame(), 1);
+        assert_eq!(bus.ppu().position(), (241, 2));
+        assert_eq!(bus.ppu().framebuffer().len(), rp2c02::FRAME_WIDTH * rp2c02::FRAME_HEIGHT);
+
+        // the CPU takes the NMI once its current instruction is done, pushing PC and P
+        for _ in 0..30 {
+            bus.clock();
+        }
+        assert_eq!(bus.cpu.sp, sp.wrapping_sub(3));
+        assert_eq!(bus.cpu.pc & 0xFF00, 0xFF00);
+    }
+
+    /// Runs `program` from $0200 followed by NOPs, writes to $4014 once it's done and returns
+    /// for how many CPU cycles the CPU is stalled before the next instruction
+    fn oam_dma_stall(program: &[u8]) -> u64 {
+        let mut bus = Bus::new();
+        for offset in 0..0x20 {
+            bus.cpu_write_u8(0x0200 + offset, 0xEA);
+        }
+        for (offset, &opcode) in program.iter().enumerate() {
+            bus.cpu_write_u8(0x0200 + offset as u16, opcode);
+        }
+        for offset in 0..=0xFF {
+            bus.cpu_write_u8(0x0400 + offset, offset as u8);
+        }
+        bus.cpu.pc = 0x0200;
+
+        // stop right before the CPU fetches the first NOP
+        while bus.cpu.pc != 0x0200 + program.len() as u16 || !bus.cpu.is_instruction_complete() {
+            bus.clock();
+        }
+        while bus.system_clock % 3 != 0 {
+            bus.clock();
+        }
+        // as if the program's last cycle was the write
+        bus.cpu_write_u8(0x4014, 0x04);
+        let start = bus.system_clock;
+        while bus.cpu.is_instruction_complete() {
+            bus.clock();
+        }
+        assert_eq!(bus.ppu().oam()[..], (0..=0xFF).collect::<Vec<u8>>()[..]);
+        (bus.system_clock - start) / 3
+    }
+
+    #[test]
+    fn test_oam_dma_stalls_the_cpu() {
+        // NOP ; NOP, the write lands on an odd cycle which delays the first read by one
+        assert_eq!(oam_dma_stall(&[0xEA, 0xEA]), 514);
+        // LDA $00, 3 cycles
+        assert_eq!(oam_dma_stall(&[0xA5, 0x00]), 513);
+    }
+
+    #[test]
+    fn test_oam_dma_starts_at_oam_addr() {
+        let mut bus = Bus::new();
+        for offset in 0..=0xFF {
+            bus.cpu_write_u8(0x0300 + offset, offset as u8);
+        }
+        bus.cpu_write_u8(0x2003, 0x10);
+        bus.cpu_write_u8(0x4014, 0x03);
+        while bus.oam_dma.is_some() {
+            bus.clock();
+        }
+        assert_eq!(bus.ppu().oam()[0x10], 0x00);
+        assert_eq!(bus.ppu().oam()[0x0F], 0xFF);
+        // one full round, OAMADDR ends up where it started
+        assert_eq!(bus.cpu_read_u8(0x2004, false), 0x00);
+    }
+}
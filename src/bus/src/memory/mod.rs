@@ -0,0 +1,168 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Anything that sits on the CPU bus and answers to a range of addresses.
+///
+/// Devices receive the value currently floating on the data bus so that they can leave it
+/// untouched for addresses (or bits) they don't drive, which is what happens on real hardware
+/// when reading write-only registers or empty cartridge space.
+pub trait BusDevice {
+    fn read(&mut self, addr: u16, open_bus: u8) -> u8;
+    fn write(&mut self, addr: u16, value: u8);
+    /// Same as read but without any side effects (think debuggers and disassemblers)
+    fn peek(&self, addr: u16, open_bus: u8) -> u8;
+}
+
+/// Devices that are also wired to other components (like the cartridge which sits on both the
+/// CPU and PPU buses) are shared behind a RefCell.
+impl<T: BusDevice> BusDevice for Rc<RefCell<T>> {
+    fn read(&mut self, addr: u16, open_bus: u8) -> u8 {
+        self.borrow_mut().read(addr, open_bus)
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        self.borrow_mut().write(addr, value);
+    }
+
+    fn peek(&self, addr: u16, open_bus: u8) -> u8 {
+        self.borrow().peek(addr, open_bus)
+    }
+}
+
+/// Components that can be mapped into the CPU address space
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Device {
+    Ram,
+    PpuRegisters,
+    ApuIo,
+    CartridgeExpansion,
+    PrgRam,
+    PrgRom,
+}
+
+/// A range of addresses owned by a device. Addresses are ANDed with the mirroring mask before
+/// being handed to the device, which is how the 2KB RAM shows up 4 times in $0000-$1FFF and the
+/// 8 PPU registers repeat all the way up to $3FFF.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MemoryRegion {
+    pub start: u16,
+    pub end: u16,
+    pub mirror_mask: u16,
+    pub device: Device,
+}
+
+pub struct MemoryMap {
+    regions: Vec<MemoryRegion>,
+}
+
+impl Default for MemoryMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryMap {
+    pub fn new() -> Self {
+        MemoryMap {
+            regions: vec![],
+        }
+    }
+
+    pub fn register(&mut self, start: u16, end: u16, mirror_mask: u16, device: Device) -> Result<(), &str> {
+        if start > end {
+            return Err("region start can't be greater than its end");
+        }
+
+        if self.regions.iter().any(|r| start <= r.end && r.start <= end) {
+            return Err("region overlaps with one that is already registered");
+        }
+
+        self.regions.push(MemoryRegion { start, end, mirror_mask, device });
+        Ok(())
+    }
+
+    /// Finds out which device is meant to handle the address and what the address looks like
+    /// from the device's point of view once mirroring is applied.
+    pub fn resolve(&self, addr: u16) -> Option<(Device, u16)> {
+        self.regions
+            .iter()
+            .find(|r| r.start <= addr && addr <= r.end)
+            .map(|r| (r.device, addr & r.mirror_mask))
+    }
+
+    pub fn regions(&self) -> &[MemoryRegion] {
+        &self.regions
+    }
+}
+
+pub struct Ram {
+    data: Vec<u8>,
+}
+
+impl Ram {
+    pub fn new(size: usize) -> Self {
+        Ram {
+            data: vec![0; size],
+        }
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl BusDevice for Ram {
+    fn read(&mut self, addr: u16, open_bus: u8) -> u8 {
+        self.peek(addr, open_bus)
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        self.data[addr as usize] = value;
+    }
+
+    fn peek(&self, addr: u16, _open_bus: u8) -> u8 {
+        self.data[addr as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_overlapping_regions() {
+        let mut map = MemoryMap::new();
+        assert!(map.register(0x0000, 0x1FFF, 0x07FF, Device::Ram).is_ok());
+        assert!(map.register(0x2000, 0x3FFF, 0x0007, Device::PpuRegisters).is_ok());
+        assert!(map.register(0x1FFF, 0x2000, 0xFFFF, Device::ApuIo).is_err());
+        assert!(map.register(0x3000, 0x3000, 0xFFFF, Device::ApuIo).is_err());
+        assert!(map.register(0x5000, 0x4000, 0xFFFF, Device::ApuIo).is_err());
+        assert_eq!(map.regions().len(), 2);
+    }
+
+    #[test]
+    fn test_resolve_applies_mirroring() {
+        let mut map = MemoryMap::new();
+        map.register(0x0000, 0x1FFF, 0x07FF, Device::Ram).unwrap();
+        map.register(0x2000, 0x3FFF, 0x0007, Device::PpuRegisters).unwrap();
+        map.register(0x8000, 0xFFFF, 0xFFFF, Device::PrgRom).unwrap();
+
+        assert_eq!(map.resolve(0x0000), Some((Device::Ram, 0x0000)));
+        assert_eq!(map.resolve(0x0801), Some((Device::Ram, 0x0001)));
+        assert_eq!(map.resolve(0x1FFF), Some((Device::Ram, 0x07FF)));
+        assert_eq!(map.resolve(0x2000), Some((Device::PpuRegisters, 0x0)));
+        assert_eq!(map.resolve(0x3FFE), Some((Device::PpuRegisters, 0x6)));
+        assert_eq!(map.resolve(0xFFFC), Some((Device::PrgRom, 0xFFFC)));
+        assert_eq!(map.resolve(0x4000), None);
+        assert_eq!(map.resolve(0x6000), None);
+    }
+
+    #[test]
+    fn test_ram_device() {
+        let mut ram = Ram::new(0x800);
+        ram.write(0x10, 0x42);
+        assert_eq!(ram.read(0x10, 0xFF), 0x42);
+        assert_eq!(ram.peek(0x10, 0xFF), 0x42);
+        assert_eq!(ram.peek(0x11, 0xFF), 0x00);
+    }
+}
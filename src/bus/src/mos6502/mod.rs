@@ -1,10 +1,22 @@
 mod opcodes;
 
 pub use crate::Bus;
+use crate::interrupt::Interrupt;
 use opcodes::{parse_instruction, Flags};
 pub use crate::mos6502::opcodes::{AddressingMode, Instruction};
 pub use crate::mos6502::opcodes::OPTABLE;
 const STACK_PAGE:u16 = 0x0100;
+const NMI_VECTOR:u16 = 0xFFFA;
+const RESET_VECTOR:u16 = 0xFFFC;
+const IRQ_VECTOR:u16 = 0xFFFE;
+
+/// What to do when the CPU runs into one of the JAM (also known as KIL) opcodes. On real
+/// hardware the CPU locks up and only a reset brings it back to life.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum JamPolicy {
+    Halt,
+    Panic,
+}
 
 #[derive(Debug)]
 pub struct Mos6502 {
@@ -15,6 +27,16 @@ pub struct Mos6502 {
     pub sp: u8,
     pub flags: u8,
     pub cycles: u8,
+    /* whether IRQs are ignored when polled at the end of the current instruction */
+    irq_inhibit: bool,
+    pub jam_policy: JamPolicy,
+    jammed: bool,
+}
+
+impl Default for Mos6502 {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Mos6502 {
@@ -30,12 +52,15 @@ impl Mos6502 {
             flags: 0x34,
             /*  counts how many cycles the instruction has remaining */
             cycles: 0,
+            irq_inhibit: true,
+            jam_policy: JamPolicy::Halt,
+            jammed: false,
         }
     }
 
-    pub fn reset(&mut self, bus: & Bus) {
+    pub fn reset(&mut self, bus: &mut Bus) {
         // Get address to set program counter to
-        self.pc = bus.cpu_read_u16(0xFFFC, false);
+        self.pc = bus.cpu_read_u16(RESET_VECTOR, false);
 
         // reset regs
         self.a = 0;
@@ -48,13 +73,119 @@ impl Mos6502 {
 
         // Reset takes time
         self.cycles = 8;
+        self.irq_inhibit = true;
+        self.jammed = false;
+    }
+
+    /// Whether the CPU has been halted by a JAM opcode
+    pub fn is_jammed(&self) -> bool {
+        self.jammed
+    }
+
+    /// NMI - Non-Maskable Interrupt
+    ///
+    /// Pushes the program counter and processor status on to the stack and jumps to the address
+    /// stored in the NMI vector at $FFFA/B regardless of the DisableInterrupt flag.
+    pub fn nmi(&mut self, bus: &mut Bus) {
+        self.interrupt(NMI_VECTOR, bus);
+    }
+
+    /// IRQ - Interrupt Request
+    ///
+    /// Same as NMI but the address comes from $FFFE/F and the request is ignored when the
+    /// DisableInterrupt flag is set.
+    pub fn irq(&mut self, bus: &mut Bus) {
+        if !self.is_flag_set(Flags::DisableInterrupt) {
+            let vector = Self::irq_vector(bus);
+            self.interrupt(vector, bus);
+        }
+    }
+
+    /// BRK and IRQ share the same vector but a NMI detected while they push their state on to
+    /// the stack hijacks the vector fetch so the CPU ends up in the NMI handler instead.
+    pub(crate) fn irq_vector(bus: &mut Bus) -> u16 {
+        if bus.interrupts.is_nmi_pending() {
+            bus.interrupts.acknowledge_nmi();
+            NMI_VECTOR
+        } else {
+            IRQ_VECTOR
+        }
+    }
+
+    fn interrupt(&mut self, vector: u16, bus: &mut Bus) {
+        self.stack_push((self.pc >> 8) as u8, bus);
+        self.stack_push((self.pc & 0x00FF) as u8, bus);
+
+        // Break flag is only ever pushed as set by BRK/PHP
+        self.clear_flag(Flags::Break);
+        self.set_flag(Flags::Unused);
+        self.stack_push(self.flags, bus);
+        self.set_flag(Flags::DisableInterrupt);
+
+        self.pc = bus.cpu_read_u16(vector, false);
+        self.cycles = 7;
+        self.irq_inhibit = true;
+    }
+
+    /// Interrupt lines are polled at the end of every instruction. IRQs are compared against the
+    /// DisableInterrupt flag as it was when polled which means that CLI, SEI and PLP only affect
+    /// IRQs after the next instruction (RTI, on the other hand, takes effect immediately).
+    fn poll_interrupts(&self, bus: &Bus) -> Option<Interrupt> {
+        if bus.interrupts.is_nmi_pending() {
+            Some(Interrupt::Nmi)
+        } else if bus.interrupts.is_irq_asserted() && !self.irq_inhibit {
+            Some(Interrupt::Irq)
+        } else {
+            None
+        }
+    }
+
+    /// Advances the CPU by a single clock cycle. Instructions are executed all at once when fetched
+    /// and the remaining clock cycles are then burnt one at a time so the CPU stays in sync with
+    /// the other components connected to the bus.
+    pub fn clock(&mut self, bus: &mut Bus) {
+        if self.cycles == 0 {
+            // nothing but a reset gets the CPU out of a JAM
+            if self.jammed {
+                return;
+            }
+
+            match self.poll_interrupts(bus) {
+                Some(Interrupt::Nmi) => {
+                    bus.interrupts.acknowledge_nmi();
+                    self.nmi(bus);
+                },
+                Some(Interrupt::Irq) => {
+                    let vector = Self::irq_vector(bus);
+                    self.interrupt(vector, bus);
+                },
+                None => {
+                    let opcode = bus.cpu_read_u8(self.pc, false);
+                    self.set_flag(Flags::Unused);
+
+                    let prev_irq_disabled = self.is_flag_set(Flags::DisableInterrupt);
+                    self.cycles = self.execute_instruction(opcode, bus);
+                    self.irq_inhibit = match opcode {
+                        // CLI, SEI and PLP change the flag after interrupts have been polled
+                        0x58 | 0x78 | 0x28 => prev_irq_disabled,
+                        _ => self.is_flag_set(Flags::DisableInterrupt),
+                    };
+                },
+            }
+        }
+        self.cycles -= 1;
+    }
+
+    /// Whether the current instruction has burnt all of its cycles
+    pub fn is_instruction_complete(&self) -> bool {
+        self.cycles == 0
     }
 
     pub fn execute_instruction(&mut self, opcode: u8, bus: &mut Bus) -> u8 {
         let inst = parse_instruction(opcode);
         let r = (inst.function)(self, inst, bus);
 
-        inst.cycles as u8 + r
+        inst.cycles + r
     }
 
     pub fn set_flag(&mut self, flag: Flags) {
@@ -90,73 +221,73 @@ impl Mos6502 {
         self.sp -= 1;
     }
 
-    pub fn stack_pull(&mut self, bus: &Bus) -> u8 {
+    pub fn stack_pull(&mut self, bus: &mut Bus) -> u8 {
         if self.sp == 0xFF {
             panic!("Can't pull more data from the stack");
         }
 
         self.sp += 1;
         let addr: u16 = STACK_PAGE | self.sp  as u16;
-        let value = bus.cpu_read_u8(addr, false);
-        value
+        bus.cpu_read_u8(addr, false)
+    }
+
+    pub fn address_mode_fetch(&self, bus: &mut Bus, inst: &Instruction) -> (u8, u8) {
+        match inst.mode {
+            AddressingMode::Immediate | AddressingMode::Relative => (bus.cpu_read_u8(self.pc + 1, false), 0),
+            AddressingMode::Accumulator => (self.a, 0),
+            _ => {
+                let (addr, additional_cycle) = self.address_mode_addr(bus, inst);
+                (bus.cpu_read_u8(addr, false), additional_cycle)
+            },
+        }
     }
 
     // Notes to myself
     // -> TODO: I'm not yet 100% confident that I got the inner workings of Indirect X && Y
-    pub fn address_mode_fetch(&self, bus: &Bus, inst: &Instruction) -> (u8, u8) {
+    /// Resolves the effective address of the operand for addressing modes that reference memory.
+    /// Also returns whether an additional cycle is needed due to a page crossing.
+    pub fn address_mode_addr(&self, bus: &mut Bus, inst: &Instruction) -> (u16, u8) {
         let mut additional_cycle= 0;
 
-        let fetched= match inst.mode {
-            AddressingMode::Immediate | AddressingMode::Relative => bus.cpu_read_u8(self.pc + 1, false),
-            AddressingMode::Accumulator => self.a,
-            AddressingMode::ZeroPage => {
-                let addr = bus.cpu_read_u8(self.pc + 1, false);
-                bus.cpu_read_u8(addr as u16, false)
-            },
+        let addr = match inst.mode {
+            AddressingMode::ZeroPage => bus.cpu_read_u8(self.pc + 1, false) as u16,
             AddressingMode::ZeroPageX => {
                 // val = PEEK((arg + X) % 256) to simulate hardware bug in 6502
-                let mut addr = bus.cpu_read_u8(self.pc + 1, false) as u16;
-                addr = (addr + self.x as u16) % 256;
-                bus.cpu_read_u8(addr, false)
+                let addr = bus.cpu_read_u8(self.pc + 1, false) as u16;
+                (addr + self.x as u16) % 256
             },
             AddressingMode::ZeroPageY => {
                 // val = PEEK((arg + Y) % 256) to simulate hardware bug in 6502
-                let mut addr = bus.cpu_read_u8(self.pc + 1, false) as u16;
-                addr = (addr + self.y as u16) % 256;
-                bus.cpu_read_u8(addr, false)
-            },
-            AddressingMode::Absolute => {
-                let addr = bus.cpu_read_u16(self.pc + 1, false);
-                bus.cpu_read_u8(addr, false)
+                let addr = bus.cpu_read_u8(self.pc + 1, false) as u16;
+                (addr + self.y as u16) % 256
             },
+            AddressingMode::Absolute => bus.cpu_read_u16(self.pc + 1, false),
             AddressingMode::AbsoluteX => {
                 let orig_addr = bus.cpu_read_u16(self.pc + 1, false);
-                let addr = orig_addr + self.x as u16;
+                let addr = orig_addr.wrapping_add(self.x as u16);
 
                 // page crossing costs 1 additional cycle.. Joao would be proud of me now <3
                 if (orig_addr >> 8) != (addr >> 8) {
                     additional_cycle = 1;
                 }
-
-                bus.cpu_read_u8(addr, false)
+                addr
             },
             AddressingMode::AbsoluteY => {
                 let orig_addr = bus.cpu_read_u16(self.pc + 1, false);
-                let addr = orig_addr + self.y as u16;
+                let addr = orig_addr.wrapping_add(self.y as u16);
 
                 // page crossing costs 1 additional cycle
                 if (orig_addr >> 8) != (addr >> 8) {
                     additional_cycle = 1;
                 }
-
-                bus.cpu_read_u8(addr, false)
+                addr
             },
             AddressingMode::IndirectX => {
                 // val = PEEK(PEEK((arg + X) % 256) + PEEK((arg + X + 1) % 256) * 256)
                 let arg = bus.cpu_read_u8(self.pc + 1, false) as u16;
                 let low = bus.cpu_read_u8((arg + self.x as u16) & 0xff, false) as u16;
                 let high = bus.cpu_read_u8((arg + self.x as u16 + 1) & 0xff, false) as u16;
-                bus.cpu_read_u8((high << 8) | low, false)
+                (high << 8) | low
             },
             AddressingMode::IndirectY => {
                 // val = PEEK(PEEK(arg) + PEEK((arg + 1) % 256) * 256 + Y)
@@ -165,18 +296,17 @@ impl Mos6502 {
                 let high = bus.cpu_read_u8((arg + 1) & 0xff, false) as u16;
 
                 let orig_addr = (high << 8) | low;
-                let addr = orig_addr + self.y as u16;
+                let addr = orig_addr.wrapping_add(self.y as u16);
 
                 // page crossing costs 1 additional cycle
                 if (orig_addr >> 8) != (addr >> 8) {
                     additional_cycle = 1;
                 }
-
-                bus.cpu_read_u8(addr, false)
+                addr
             },
             _ => panic!("invalid addressing mode... aborting"),
         };
-        (fetched, additional_cycle)
+        (addr, additional_cycle)
     }
 
 }
@@ -184,6 +314,8 @@ impl Mos6502 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::interrupt::InterruptSource;
+    use crate::test::bus_with_vectors;
 
     #[test]
     fn test_set_flag() {
@@ -259,9 +391,9 @@ mod tests {
         cpu.sp = 0xff;
         cpu.stack_push(0x10, &mut bus);
         cpu.stack_push(0x11, &mut bus);
-        assert_eq!(cpu.stack_pull(&bus), 0x11);
+        assert_eq!(cpu.stack_pull(&mut bus), 0x11);
         assert_eq!(cpu.sp, 0xfe);
-        assert_eq!(cpu.stack_pull(&bus), 0x10);
+        assert_eq!(cpu.stack_pull(&mut bus), 0x10);
         assert_eq!(cpu.sp, 0xff);
     }
 
@@ -269,32 +401,167 @@ mod tests {
     #[should_panic]
     fn test_stack_underflow() {
         let mut cpu = Mos6502::new();
-        let bus = Bus::new();
+        let mut bus = Bus::new();
 
         cpu.sp = 0xff;
-        cpu.stack_pull(&bus);
+        cpu.stack_pull(&mut bus);
     }
 
-    // TODO implement vectors otherise this test will fail
     #[test]
-    #[ignore]
-    fn test_cpu_reset() {
+    fn test_cpu_clock() {
+        let mut cpu = Mos6502::new();
+        let mut bus = Bus::new();
+
+        // LDA #$42 ; LDX #$24
+        cpu.pc = 0x0200;
+        bus.cpu_write_u8(0x0200, 0xA9);
+        bus.cpu_write_u8(0x0201, 0x42);
+        bus.cpu_write_u8(0x0202, 0xA2);
+        bus.cpu_write_u8(0x0203, 0x24);
+
+        // instruction is executed on its first cycle
+        cpu.clock(&mut bus);
+        assert_eq!(cpu.a, 0x42);
+        assert_eq!(cpu.pc, 0x0202);
+        assert_eq!(cpu.cycles, 1);
+        assert!(!cpu.is_instruction_complete());
+
+        // remaining cycles are burnt without fetching anything else
+        cpu.clock(&mut bus);
+        assert_eq!(cpu.x, 0x00);
+        assert_eq!(cpu.pc, 0x0202);
+        assert!(cpu.is_instruction_complete());
+
+        cpu.clock(&mut bus);
+        assert_eq!(cpu.x, 0x24);
+        assert_eq!(cpu.pc, 0x0204);
+        assert_eq!(cpu.cycles, 1);
+    }
+
+    #[test]
+    fn test_nmi_ignores_disable_interrupt() {
         let mut cpu = Mos6502::new();
         let mut bus = Bus::new();
 
-        bus.cpu_write_u16(0xFFFC, 0x1234);
+        // SEI
+        cpu.pc = 0x0200;
+        cpu.flags = 0b0000_0000;
+        bus.cpu_write_u8(0x0200, 0x78);
+        cpu.clock(&mut bus);
+        cpu.clock(&mut bus);
+        assert!(cpu.is_flag_set(Flags::DisableInterrupt));
+
+        bus.interrupts.assert_nmi(InterruptSource::Ppu);
+        assert_eq!(cpu.poll_interrupts(&bus), Some(Interrupt::Nmi));
+    }
+
+    #[test]
+    fn test_irq_masking() {
+        let mut cpu = Mos6502::new();
+        let mut bus = Bus::new();
+
+        bus.interrupts.assert_irq(InterruptSource::Mapper);
+        cpu.irq_inhibit = true;
+        assert_eq!(cpu.poll_interrupts(&bus), None);
+
+        cpu.irq_inhibit = false;
+        assert_eq!(cpu.poll_interrupts(&bus), Some(Interrupt::Irq));
+
+        // NMI takes precedence over IRQ
+        bus.interrupts.assert_nmi(InterruptSource::Ppu);
+        assert_eq!(cpu.poll_interrupts(&bus), Some(Interrupt::Nmi));
+    }
+
+    #[test]
+    fn test_cli_delays_irq_by_one_instruction() {
+        let mut cpu = Mos6502::new();
+        let mut bus = Bus::new();
+
+        // CLI ; NOP
+        cpu.pc = 0x0200;
+        cpu.flags = 0b0000_0100;
+        bus.cpu_write_u8(0x0200, 0x58);
+        bus.cpu_write_u8(0x0201, 0xEA);
+        bus.interrupts.assert_irq(InterruptSource::ApuFrameCounter);
+
+        cpu.clock(&mut bus);
+        cpu.clock(&mut bus);
+        assert!(!cpu.is_flag_set(Flags::DisableInterrupt));
+        assert_eq!(cpu.poll_interrupts(&bus), None);
+
+        cpu.clock(&mut bus);
+        cpu.clock(&mut bus);
+        assert_eq!(cpu.pc, 0x0202);
+        assert_eq!(cpu.poll_interrupts(&bus), Some(Interrupt::Irq));
+    }
+
+    #[test]
+    fn test_sei_still_lets_pending_irq_through() {
+        let mut cpu = Mos6502::new();
+        let mut bus = Bus::new();
+
+        // SEI
+        cpu.pc = 0x0200;
+        cpu.flags = 0b0000_0000;
+        cpu.irq_inhibit = false;
+        bus.cpu_write_u8(0x0200, 0x78);
+
+        // IRQ shows up while SEI is being executed
+        cpu.clock(&mut bus);
+        bus.interrupts.assert_irq(InterruptSource::Mapper);
+        cpu.clock(&mut bus);
+        assert!(cpu.is_flag_set(Flags::DisableInterrupt));
+        assert_eq!(cpu.poll_interrupts(&bus), Some(Interrupt::Irq));
+    }
+
+    #[test]
+    fn test_brk_hijacked_by_nmi() {
+        let mut bus = Bus::new();
+        assert_eq!(Mos6502::irq_vector(&mut bus), IRQ_VECTOR);
+
+        bus.interrupts.assert_nmi(InterruptSource::Ppu);
+        assert_eq!(Mos6502::irq_vector(&mut bus), NMI_VECTOR);
+        assert!(!bus.interrupts.is_nmi_pending());
+    }
+
+    #[test]
+    fn test_nmi_sequence() {
+        let mut cpu = Mos6502::new();
+        let mut bus = bus_with_vectors(0x1234, 0x8000, 0x8000);
+
+        cpu.sp = 0xff;
+        cpu.pc = 0x0200;
+        cpu.flags = 0b0001_0100;
+        bus.cpu_write_u8(0x0200, 0xEA);
+        bus.interrupts.assert_nmi(InterruptSource::Ppu);
+
+        cpu.clock(&mut bus);
+        assert_eq!(cpu.pc, 0x1234);
+        assert_eq!(cpu.sp, 0xfc);
+        assert_eq!(cpu.cycles, 6);
+        assert!(!bus.interrupts.is_nmi_pending());
+        assert_eq!(bus.cpu_read_u16(0x01FE, false), 0x0200);
+        assert_eq!(bus.cpu_read_u8(0x01FD, false), 0b0010_0100);
+    }
+
+    #[test]
+    fn test_cpu_reset() {
+        let mut cpu = Mos6502::new();
+        let mut bus = bus_with_vectors(0x8000, 0x1234, 0x8000);
+
         cpu.a = 0x1;
         cpu.x = 0x1;
         cpu.y = 0x1;
         cpu.sp = 0xC0;
         cpu.flags = 0xFF;
 
-        cpu.reset(&bus);
+        cpu.reset(&mut bus);
         assert_eq!(cpu.a, 0);
         assert_eq!(cpu.x, 0);
         assert_eq!(cpu.y, 0);
         assert_eq!(cpu.sp, 0xFD);
         assert_eq!(cpu.flags, 0b0010_0000);
         assert_eq!(cpu.cycles, 8);
+        assert_eq!(cpu.pc, 0x1234);
     }
 }
\ No newline at end of file
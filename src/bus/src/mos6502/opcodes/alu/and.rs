@@ -8,7 +8,7 @@ use super::*;
 pub fn and(cpu: &mut Mos6502, inst: Instruction, bus: &mut Bus) -> u8 {
     println!("{} -> {:?} was called with cpu: {:?}", inst.name, inst.mode, cpu);
     let (fetched, additional_cycle) = cpu.address_mode_fetch(bus, &inst);
-    cpu.a = cpu.a & fetched;
+    cpu.a &= fetched;
     cpu.write_flag_cond(Zero, cpu.a == 0);
     cpu.write_flag_cond(Negative, cpu.a & 0x80 == 0x80 );
     cpu.pc += inst.bytes as u16;
@@ -318,4 +318,24 @@ mod tests {
         assert_eq!(cpu.pc, 0x0802);
         assert_eq!(cpu.sp, 0xff);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn unofficial_immediate() {
+        let opcode = OPTABLE[0xEB];
+        assert_eq!(opcode.mode, Immediate);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b0000_0001;
+        cpu.a = 10;
+        cpu.pc = 0x10;
+        bus.cpu_write_u8(cpu.pc + 1, 3);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(cpu.a, 7);
+        assert_eq!(cpu.flags, 0b0000_0001);
+        assert_eq!(cpu.pc, 0x12);
+        assert_eq!(cpu.sp, 0xff);
+    }
+}
@@ -17,23 +17,24 @@ pub fn brk(cpu: &mut Mos6502, inst: Instruction, bus: &mut Bus) -> u8 {
     cpu.stack_push(cpu.flags, bus);
     cpu.clear_flag(Break);
 
-    cpu.pc = bus.cpu_read_u16(0xFFFE, false);
+    let vector = Mos6502::irq_vector(bus);
+    cpu.pc = bus.cpu_read_u16(vector, false);
     0
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::interrupt::InterruptSource;
+    use crate::test::bus_with_vectors;
 
     const OPCODE_NAME: &str = "BRK";
 
     fn init() -> (Mos6502, Bus) {
-        (Mos6502::new(), Bus::new())
+        (Mos6502::new(), bus_with_vectors(0x4321, 0x8000, 0x1234))
     }
 
-    // TODO implement vectors otherise this test will fail
     #[test]
-    #[ignore]
     fn implicit() {
         let opcode = OPTABLE[0x00];
         assert_eq!(opcode.mode, Implicit);
@@ -46,7 +47,6 @@ mod tests {
         cpu.a = 0;
         cpu.x = 0;
         cpu.y = 0;
-        bus.cpu_write_u16(0xFFFE, 0x1234);
         let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
         assert_eq!(cycles, opcode.cycles);
         assert_eq!(cpu.flags, 0b0000_0111);
@@ -59,4 +59,20 @@ mod tests {
         assert_eq!(bus.cpu_read_u16(0x01FE, false), 0x0801); // check right pc is pushed to stack
         assert_eq!(bus.cpu_read_u8(0x01FD, false), 0b0001_0111); // check right pc is pushed to stack
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn nmi_hijack() {
+        let opcode = OPTABLE[0x00];
+
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.pc = 0x0800;
+        cpu.flags = 0b0000_0011;
+        bus.interrupts_mut().assert_nmi(InterruptSource::Ppu);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(cpu.pc, 0x4321);
+        assert!(!bus.interrupts().is_nmi_pending());
+        assert_eq!(bus.cpu_read_u8(0x01FD, false), 0b0001_0111); // B flag still pushed as set
+    }
+}
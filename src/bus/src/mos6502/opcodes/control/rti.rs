@@ -9,7 +9,6 @@ pub fn rti(cpu: &mut Mos6502, inst: Instruction, bus: &mut Bus) -> u8 {
     cpu.flags = cpu.stack_pull(bus);
     cpu.pc = cpu.stack_pull(bus) as u16 | (cpu.stack_pull(bus) as u16) << 8;
     cpu.clear_flag(Break);
-    cpu.clear_flag(DisableInterrupt);
     0
 }
 
@@ -32,7 +32,7 @@ pub fn asl(cpu: &mut Mos6502, inst: Instruction, bus: &mut Bus) -> u8 {
             },
             _ => unreachable!("invalid addressing mode... aborting"),
         };
-        bus.cpu_write_u8(addr, (result & 0x00ff) as u8);
+        rmw_write(bus, addr, fetched, (result & 0x00ff) as u8);
     }
 
     cpu.write_flag_cond(Carry, fetched & 0x80 == 0x80);
@@ -87,7 +87,7 @@ mod tests {
         // Zero flag set
         let (mut cpu, mut bus) = init();
         cpu.sp = 0xff;
-        cpu.flags = 0b0000_000;
+        cpu.flags = 0b0000_0000;
         cpu.a = 0b0000_0000;
         cpu.pc = 0x0800;
         let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
@@ -100,7 +100,7 @@ mod tests {
         // Carry flag set
         let (mut cpu, mut bus) = init();
         cpu.sp = 0xff;
-        cpu.flags = 0b0000_000;
+        cpu.flags = 0b0000_0000;
         cpu.a = 0b1000_0001;
         cpu.pc = 0x0800;
         let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
@@ -113,7 +113,7 @@ mod tests {
         // Negative flag set
         let (mut cpu, mut bus) = init();
         cpu.sp = 0xff;
-        cpu.flags = 0b0000_000;
+        cpu.flags = 0b0000_0000;
         cpu.a = 0b0100_0001;
         cpu.pc = 0x0800;
         let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
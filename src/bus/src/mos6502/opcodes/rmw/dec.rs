@@ -27,7 +27,7 @@ pub fn dec(cpu: &mut Mos6502, inst: Instruction, bus: &mut Bus) -> u8 {
         },
         _ => unreachable!("invalid addressing mode... aborting"),
     };
-    bus.cpu_write_u8(addr, result);
+    rmw_write(bus, addr, fetched, result);
 
     cpu.write_flag_cond(Zero, result == 0);
     cpu.write_flag_cond(Negative, result & 0x80 == 0x80);
@@ -29,6 +29,7 @@ pub use ror::*;
 
 use crate::Bus;
 use crate::mos6502::{Mos6502, Instruction, Flags::*};
+use crate::mos6502::opcodes::rmw_write;
 #[allow(unused_imports)]
 use crate::mos6502::{AddressingMode::*};
 #[cfg(test)]
@@ -3,10 +3,17 @@ use super::*;
 /// NOP - No Operation
 /// The NOP instruction causes no changes to the processor other than the normal incrementing
 /// of the program counter to the next instruction.
-pub fn nop(cpu: &mut Mos6502, inst: Instruction, _bus: &mut Bus) -> u8 {
+///
+/// Unofficial NOPs also come with an operand which is read and thrown away, so the ones using
+/// absolute indexed addressing still pay for page crossings.
+pub fn nop(cpu: &mut Mos6502, inst: Instruction, bus: &mut Bus) -> u8 {
     println!("{} -> {:?} was called with cpu: {:?}", inst.name, inst.mode, cpu);
-    cpu.pc += 1;
-    0
+    let additional_cycle = match inst.mode {
+        Implicit => 0,
+        _ => cpu.address_mode_fetch(bus, &inst).1,
+    };
+    cpu.pc += inst.bytes as u16;
+    additional_cycle
 }
 
 #[cfg(test)]
@@ -41,4 +48,99 @@ mod tests {
         assert_eq!(cpu.pc, 0x0801);
         assert_eq!(cpu.sp, 0xff);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn unofficial_implicit() {
+        for op in [0x1A, 0x3A, 0x5A, 0x7A, 0xDA, 0xFA] {
+            let opcode = OPTABLE[op];
+            assert_eq!(opcode.mode, Implicit);
+            assert_eq!(opcode.name, OPCODE_NAME);
+
+            let (mut cpu, mut bus) = init();
+            cpu.pc = 0x0800;
+            cpu.flags = 0b0000_0000;
+            let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+            assert_eq!(cycles, opcode.cycles);
+            assert_eq!(cpu.flags, 0b0000_0000);
+            assert_eq!(cpu.pc, 0x0801);
+        }
+    }
+
+    #[test]
+    fn unofficial_immediate() {
+        for op in [0x80, 0x82, 0x89, 0xC2, 0xE2] {
+            let opcode = OPTABLE[op];
+            assert_eq!(opcode.mode, Immediate);
+            assert_eq!(opcode.name, OPCODE_NAME);
+
+            let (mut cpu, mut bus) = init();
+            cpu.pc = 0x0800;
+            cpu.flags = 0b0000_0000;
+            bus.cpu_write_u8(cpu.pc + 1, 0x00);
+            let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+            assert_eq!(cycles, opcode.cycles);
+            assert_eq!(cpu.flags, 0b0000_0000);
+            assert_eq!(cpu.pc, 0x0802);
+        }
+    }
+
+    #[test]
+    fn unofficial_zero_page() {
+        for (op, mode) in [(0x04, ZeroPage), (0x44, ZeroPage), (0x64, ZeroPage),
+                           (0x14, ZeroPageX), (0x34, ZeroPageX), (0x54, ZeroPageX),
+                           (0x74, ZeroPageX), (0xD4, ZeroPageX), (0xF4, ZeroPageX)] {
+            let opcode = OPTABLE[op];
+            assert_eq!(opcode.mode, mode);
+            assert_eq!(opcode.name, OPCODE_NAME);
+
+            let (mut cpu, mut bus) = init();
+            cpu.pc = 0x0800;
+            cpu.a = 0x12;
+            let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+            assert_eq!(cycles, opcode.cycles);
+            assert_eq!(cpu.a, 0x12);
+            assert_eq!(cpu.pc, 0x0802);
+        }
+    }
+
+    #[test]
+    fn unofficial_absolute() {
+        let opcode = OPTABLE[0x0C];
+        assert_eq!(opcode.mode, Absolute);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        let (mut cpu, mut bus) = init();
+        cpu.pc = 0x0800;
+        bus.cpu_write_u16(cpu.pc + 1, 0x1234);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(cpu.pc, 0x0803);
+    }
+
+    #[test]
+    fn unofficial_absolute_x() {
+        for op in [0x1C, 0x3C, 0x5C, 0x7C, 0xDC, 0xFC] {
+            let opcode = OPTABLE[op];
+            assert_eq!(opcode.mode, AbsoluteX);
+            assert_eq!(opcode.name, OPCODE_NAME);
+
+            // no page crossing
+            let (mut cpu, mut bus) = init();
+            cpu.pc = 0x0800;
+            cpu.x = 0x01;
+            bus.cpu_write_u16(cpu.pc + 1, 0x1234);
+            let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+            assert_eq!(cycles, opcode.cycles);
+            assert_eq!(cpu.pc, 0x0803);
+
+            // with page crossing
+            let (mut cpu, mut bus) = init();
+            cpu.pc = 0x0800;
+            cpu.x = 0xff;
+            bus.cpu_write_u16(cpu.pc + 1, 0x1234);
+            let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+            assert_eq!(cycles, opcode.cycles + 1);
+            assert_eq!(cpu.pc, 0x0803);
+        }
+    }
+}
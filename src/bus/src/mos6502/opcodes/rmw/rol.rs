@@ -29,7 +29,7 @@ pub fn rol(cpu: &mut Mos6502, inst: Instruction, bus: &mut Bus) -> u8 {
             },
             _ => unreachable!("invalid addressing mode... aborting"),
         };
-        bus.cpu_write_u8(addr, result);
+        rmw_write(bus, addr, fetched, result);
     }
 
     cpu.write_flag_cond(Carry, fetched & 0x80 == 0x80);
@@ -84,7 +84,7 @@ mod tests {
         // Zero flag set
         let (mut cpu, mut bus) = init();
         cpu.sp = 0xff;
-        cpu.flags = 0b0000_000;
+        cpu.flags = 0b0000_0000;
         cpu.a = 0b0000_0000;
         cpu.pc = 0x0800;
         let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
@@ -97,7 +97,7 @@ mod tests {
         // Carry flag set
         let (mut cpu, mut bus) = init();
         cpu.sp = 0xff;
-        cpu.flags = 0b0000_000;
+        cpu.flags = 0b0000_0000;
         cpu.a = 0b1000_0001;
         cpu.pc = 0x0800;
         let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
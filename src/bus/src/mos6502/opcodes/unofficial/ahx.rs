@@ -0,0 +1,90 @@
+use super::*;
+
+/// AHX - Store Accumulator AND X AND High Address (also known as SHA)
+/// M = A & X & (H+1)
+///
+/// Stores A & X & (high byte of the base address + 1). When indexing crosses a page the high
+/// byte of the target address gets replaced by the stored value, just like the real chip does.
+pub fn ahx(cpu: &mut Mos6502, inst: Instruction, bus: &mut Bus) -> u8 {
+    println!("{} -> {:?} was called with cpu: {:?}", inst.name, inst.mode, cpu);
+    let (addr, page_crossed) = cpu.address_mode_addr(bus, &inst);
+    let base = addr.wrapping_sub(cpu.y as u16);
+    let value = cpu.a & cpu.x & ((base >> 8) as u8).wrapping_add(1);
+    bus.cpu_write_u8(unstable_store_addr(addr, value, page_crossed), value);
+    cpu.pc += inst.bytes as u16;
+    0
+}
+
+/// SHA/SHX/SHY/TAS glitch: on page crossing the value being stored also ends up on the high
+/// byte of the address bus
+pub(super) fn unstable_store_addr(addr: u16, value: u8, page_crossed: u8) -> u16 {
+    if page_crossed == 1 {
+        ((value as u16) << 8) | (addr & 0x00FF)
+    } else {
+        addr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPCODE_NAME: &str = "AHX";
+
+    fn init() -> (Mos6502, Bus) {
+        (Mos6502::new(), Bus::new())
+    }
+
+    #[test]
+    fn absolute_y() {
+        let opcode = OPTABLE[0x9F];
+        assert_eq!(opcode.mode, AbsoluteY);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b0000_0000;
+        cpu.a = 0xff;
+        cpu.x = 0x0f;
+        cpu.y = 0x01;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u16(cpu.pc + 1, 0x1234);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(bus.cpu_read_u8(0x1235, false), 0x0f & 0x13);
+        assert_eq!(cpu.flags, 0b0000_0000);
+        assert_eq!(cpu.pc, 0x0803);
+        assert_eq!(cpu.sp, 0xff);
+
+        // page crossing corrupts the high byte of the address
+        let (mut cpu, mut bus) = init();
+        cpu.a = 0xff;
+        cpu.x = 0x01;
+        cpu.y = 0xff;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u16(cpu.pc + 1, 0x1234);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(bus.cpu_read_u8(0x0133, false), 0x01);
+        assert_eq!(bus.cpu_read_u8(0x1333, false), 0x00);
+    }
+
+    #[test]
+    fn indirect_y() {
+        let opcode = OPTABLE[0x93];
+        assert_eq!(opcode.mode, IndirectY);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        let (mut cpu, mut bus) = init();
+        cpu.a = 0xff;
+        cpu.x = 0xff;
+        cpu.y = 0x01;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u8(cpu.pc + 1, 0x34);
+        bus.cpu_write_u16(0x34, 0x0634);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(bus.cpu_read_u8(0x0635, false), 0x07);
+        assert_eq!(cpu.pc, 0x0802);
+    }
+}
@@ -0,0 +1,58 @@
+use super::*;
+
+/// ALR - AND then Logical Shift Right
+/// A = (A&M)/2
+///
+/// ANDs the accumulator with an immediate value and then shifts the result one bit right. Same as
+/// AND followed by LSR A.
+pub fn alr(cpu: &mut Mos6502, inst: Instruction, bus: &mut Bus) -> u8 {
+    println!("{} -> {:?} was called with cpu: {:?}", inst.name, inst.mode, cpu);
+    let (fetched, _) = cpu.address_mode_fetch(bus, &inst);
+    let value = cpu.a & fetched;
+    cpu.a = value >> 1;
+    cpu.write_flag_cond(Carry, value & 0x1 == 0x1);
+    cpu.write_flag_cond(Zero, cpu.a == 0);
+    cpu.write_flag_cond(Negative, false);
+    cpu.pc += inst.bytes as u16;
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPCODE_NAME: &str = "ALR";
+
+    fn init() -> (Mos6502, Bus) {
+        (Mos6502::new(), Bus::new())
+    }
+
+    #[test]
+    fn immediate() {
+        let opcode = OPTABLE[0x4B];
+        assert_eq!(opcode.mode, Immediate);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b1000_0000;
+        cpu.a = 0b1000_0011;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u8(cpu.pc + 1, 0b1000_0001);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(cpu.a, 0b0100_0000);
+        assert_eq!(cpu.flags, 0b0000_0001);
+        assert_eq!(cpu.pc, 0x0802);
+        assert_eq!(cpu.sp, 0xff);
+
+        let (mut cpu, mut bus) = init();
+        cpu.flags = 0b0000_0000;
+        cpu.a = 0b0000_0001;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u8(cpu.pc + 1, 0b0000_0011);
+        cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cpu.a, 0);
+        assert_eq!(cpu.flags, 0b0000_0011);
+    }
+}
@@ -0,0 +1,59 @@
+use super::*;
+
+/// ANC - AND with Carry
+/// A,Z,N = A&M, C = N
+///
+/// ANDs the accumulator with an immediate value and then copies the negative flag into the carry
+/// flag as if the result had been shifted out by ASL/ROL.
+pub fn anc(cpu: &mut Mos6502, inst: Instruction, bus: &mut Bus) -> u8 {
+    println!("{} -> {:?} was called with cpu: {:?}", inst.name, inst.mode, cpu);
+    let (fetched, _) = cpu.address_mode_fetch(bus, &inst);
+    cpu.a &= fetched;
+    cpu.write_flag_cond(Zero, cpu.a == 0);
+    cpu.write_flag_cond(Negative, cpu.a & 0x80 == 0x80);
+    cpu.write_flag_cond(Carry, cpu.a & 0x80 == 0x80);
+    cpu.pc += inst.bytes as u16;
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPCODE_NAME: &str = "ANC";
+
+    fn init() -> (Mos6502, Bus) {
+        (Mos6502::new(), Bus::new())
+    }
+
+    #[test]
+    fn immediate() {
+        for op in [0x0B, 0x2B] {
+            let opcode = OPTABLE[op];
+            assert_eq!(opcode.mode, Immediate);
+            assert_eq!(opcode.name, OPCODE_NAME);
+
+            let (mut cpu, mut bus) = init();
+            cpu.sp = 0xff;
+            cpu.flags = 0b0000_0000;
+            cpu.a = 0b1100_0000;
+            cpu.pc = 0x0800;
+            bus.cpu_write_u8(cpu.pc + 1, 0b1000_0001);
+            let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+            assert_eq!(cycles, opcode.cycles);
+            assert_eq!(cpu.a, 0b1000_0000);
+            assert_eq!(cpu.flags, 0b1000_0001);
+            assert_eq!(cpu.pc, 0x0802);
+            assert_eq!(cpu.sp, 0xff);
+
+            let (mut cpu, mut bus) = init();
+            cpu.flags = 0b0000_0001;
+            cpu.a = 0b0100_0000;
+            cpu.pc = 0x0800;
+            bus.cpu_write_u8(cpu.pc + 1, 0b1000_0001);
+            cpu.execute_instruction(opcode.opcode, &mut bus);
+            assert_eq!(cpu.a, 0);
+            assert_eq!(cpu.flags, 0b0000_0010);
+        }
+    }
+}
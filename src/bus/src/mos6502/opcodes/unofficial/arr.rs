@@ -0,0 +1,60 @@
+use super::*;
+
+/// ARR - AND then Rotate Right
+/// A = (A&M)/2+C*128
+///
+/// ANDs the accumulator with an immediate value and rotates the result one bit right. Flags are
+/// the odd part: carry gets bit 6 of the result and overflow gets bit 6 XOR bit 5.
+pub fn arr(cpu: &mut Mos6502, inst: Instruction, bus: &mut Bus) -> u8 {
+    println!("{} -> {:?} was called with cpu: {:?}", inst.name, inst.mode, cpu);
+    let (fetched, _) = cpu.address_mode_fetch(bus, &inst);
+    let result = ((cpu.flags & 0x1) << 7) | (cpu.a & fetched) >> 1;
+    cpu.a = result;
+    cpu.write_flag_cond(Carry, result & 0x40 == 0x40);
+    cpu.write_flag_cond(Overflow, ((result >> 6) ^ (result >> 5)) & 0x1 == 0x1);
+    cpu.write_flag_cond(Zero, result == 0);
+    cpu.write_flag_cond(Negative, result & 0x80 == 0x80);
+    cpu.pc += inst.bytes as u16;
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPCODE_NAME: &str = "ARR";
+
+    fn init() -> (Mos6502, Bus) {
+        (Mos6502::new(), Bus::new())
+    }
+
+    #[test]
+    fn immediate() {
+        let opcode = OPTABLE[0x6B];
+        assert_eq!(opcode.mode, Immediate);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        // carry goes into bit 7, bit 6 into carry and bit 6 ^ bit 5 into overflow
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b0000_0001;
+        cpu.a = 0b1000_0000;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u8(cpu.pc + 1, 0xff);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(cpu.a, 0b1100_0000);
+        assert_eq!(cpu.flags, 0b1100_0001);
+        assert_eq!(cpu.pc, 0x0802);
+        assert_eq!(cpu.sp, 0xff);
+
+        let (mut cpu, mut bus) = init();
+        cpu.flags = 0b0100_0001;
+        cpu.a = 0b0000_0001;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u8(cpu.pc + 1, 0xff);
+        cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cpu.a, 0b1000_0000);
+        assert_eq!(cpu.flags, 0b1000_0000);
+    }
+}
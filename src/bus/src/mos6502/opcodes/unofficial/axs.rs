@@ -0,0 +1,62 @@
+use super::*;
+
+/// AXS - AND X Register with Accumulator then Subtract (also known as SBX)
+/// X = (A&X)-M
+///
+/// Subtracts an immediate value from the bitwise AND of the accumulator and the X register and
+/// stores the result in X. Flags are set like CMP does (the carry flag isn't used as input).
+pub fn axs(cpu: &mut Mos6502, inst: Instruction, bus: &mut Bus) -> u8 {
+    println!("{} -> {:?} was called with cpu: {:?}", inst.name, inst.mode, cpu);
+    let (fetched, _) = cpu.address_mode_fetch(bus, &inst);
+    let value = cpu.a & cpu.x;
+    cpu.x = value.wrapping_sub(fetched);
+    cpu.write_flag_cond(Carry, value >= fetched);
+    cpu.write_flag_cond(Zero, cpu.x == 0);
+    cpu.write_flag_cond(Negative, cpu.x & 0x80 == 0x80);
+    cpu.pc += inst.bytes as u16;
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPCODE_NAME: &str = "AXS";
+
+    fn init() -> (Mos6502, Bus) {
+        (Mos6502::new(), Bus::new())
+    }
+
+    #[test]
+    fn immediate() {
+        let opcode = OPTABLE[0xCB];
+        assert_eq!(opcode.mode, Immediate);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b0000_0000;
+        cpu.a = 0b0000_1111;
+        cpu.x = 0b0000_0110;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u8(cpu.pc + 1, 0x02);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(cpu.a, 0b0000_1111);
+        assert_eq!(cpu.x, 0x04);
+        assert_eq!(cpu.flags, 0b0000_0001);
+        assert_eq!(cpu.pc, 0x0802);
+        assert_eq!(cpu.sp, 0xff);
+
+        // borrow ignores the carry flag
+        let (mut cpu, mut bus) = init();
+        cpu.flags = 0b0000_0001;
+        cpu.a = 0x00;
+        cpu.x = 0xff;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u8(cpu.pc + 1, 0x01);
+        cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cpu.x, 0xff);
+        assert_eq!(cpu.flags, 0b1000_0000);
+    }
+}
@@ -0,0 +1,90 @@
+use super::*;
+
+/// DCP - Decrement Memory then Compare
+/// M = M-1, Z,C,N = A-M
+///
+/// Subtracts one from the memory contents and then compares the result with the accumulator.
+/// Same as DEC followed by CMP.
+pub fn dcp(cpu: &mut Mos6502, inst: Instruction, bus: &mut Bus) -> u8 {
+    println!("{} -> {:?} was called with cpu: {:?}", inst.name, inst.mode, cpu);
+    let (addr, _) = cpu.address_mode_addr(bus, &inst);
+    let fetched = bus.cpu_read_u8(addr, false);
+    let result = fetched.wrapping_sub(1);
+    rmw_write(bus, addr, fetched, result);
+
+    cpu.write_flag_cond(Zero, cpu.a == result);
+    cpu.write_flag_cond(Carry, cpu.a >= result);
+    cpu.write_flag_cond(Negative, cpu.a.wrapping_sub(result) & 0x80 == 0x80);
+    cpu.pc += inst.bytes as u16;
+    0 // read-modify-write instructions always take the extra cycle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPCODE_NAME: &str = "DCP";
+
+    fn init() -> (Mos6502, Bus) {
+        (Mos6502::new(), Bus::new())
+    }
+
+    #[test]
+    fn zero_page() {
+        let opcode = OPTABLE[0xC7];
+        assert_eq!(opcode.mode, ZeroPage);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        // equal after decrement
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b0000_0000;
+        cpu.a = 0x41;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u8(cpu.pc + 1, 0x10);
+        bus.cpu_write_u8(0x10, 0x42);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(bus.cpu_read_u8(0x10, false), 0x41);
+        assert_eq!(cpu.a, 0x41);
+        assert_eq!(cpu.flags, 0b0000_0011);
+        assert_eq!(cpu.pc, 0x0802);
+        assert_eq!(cpu.sp, 0xff);
+
+        // wraps around and accumulator ends up being smaller
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b0000_0000;
+        cpu.a = 0x01;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u8(cpu.pc + 1, 0x10);
+        bus.cpu_write_u8(0x10, 0x00);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(bus.cpu_read_u8(0x10, false), 0xff);
+        assert_eq!(cpu.flags, 0b0000_0000);
+    }
+
+    #[test]
+    fn indirect_y() {
+        let opcode = OPTABLE[0xD3];
+        assert_eq!(opcode.mode, IndirectY);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        // page crossing doesn't cost anything extra
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b0000_0000;
+        cpu.a = 0x10;
+        cpu.y = 0xff;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u8(cpu.pc + 1, 0x34);
+        bus.cpu_write_u16(0x34, 0x1234);
+        bus.cpu_write_u8(0x1333, 0x20);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(bus.cpu_read_u8(0x1333, false), 0x1f);
+        assert_eq!(cpu.flags, 0b1000_0000);
+        assert_eq!(cpu.pc, 0x0802);
+    }
+}
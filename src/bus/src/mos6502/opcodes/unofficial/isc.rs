@@ -0,0 +1,95 @@
+use super::*;
+
+/// ISC - Increment Memory then Subtract with Carry
+/// M = M+1, A = A-M-(1-C)
+///
+/// Adds one to the memory contents and then subtracts the result from the accumulator together
+/// with the not of the carry bit. Same as INC followed by SBC.
+pub fn isc(cpu: &mut Mos6502, inst: Instruction, bus: &mut Bus) -> u8 {
+    println!("{} -> {:?} was called with cpu: {:?}", inst.name, inst.mode, cpu);
+    let (addr, _) = cpu.address_mode_addr(bus, &inst);
+    let original = bus.cpu_read_u8(addr, false);
+    let fetched = original.wrapping_add(1);
+    rmw_write(bus, addr, original, fetched);
+
+    let (data1, is_carry1) = cpu.a.overflowing_sub(fetched);
+    let (result, is_carry2) = data1.overflowing_sub(if cpu.is_flag_set(Carry) { 0 } else { 1 } );
+
+    cpu.write_flag_cond(Carry, !(is_carry1 || is_carry2));
+    cpu.write_flag_cond(Zero, result == 0);
+    cpu.write_flag_cond(Negative, (result & 0x80) == 0x80);
+    cpu.write_flag_cond(Overflow, (((cpu.a ^ fetched) & 0x80) == 0x80) && (((cpu.a ^ result) & 0x80) == 0x80));
+
+    cpu.a = result;
+    cpu.pc += inst.bytes as u16;
+    0 // read-modify-write instructions always take the extra cycle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPCODE_NAME: &str = "ISC";
+
+    fn init() -> (Mos6502, Bus) {
+        (Mos6502::new(), Bus::new())
+    }
+
+    #[test]
+    fn zero_page() {
+        let opcode = OPTABLE[0xE7];
+        assert_eq!(opcode.mode, ZeroPage);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b0000_0001;
+        cpu.a = 10;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u8(cpu.pc + 1, 0x10);
+        bus.cpu_write_u8(0x10, 3);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(bus.cpu_read_u8(0x10, false), 4);
+        assert_eq!(cpu.a, 6);
+        assert_eq!(cpu.flags, 0b0000_0001);
+        assert_eq!(cpu.pc, 0x0802);
+        assert_eq!(cpu.sp, 0xff);
+
+        // borrow
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b0000_0001;
+        cpu.a = 0;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u8(cpu.pc + 1, 0x10);
+        bus.cpu_write_u8(0x10, 0);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(bus.cpu_read_u8(0x10, false), 1);
+        assert_eq!(cpu.a, 0xff);
+        assert_eq!(cpu.flags, 0b1000_0000);
+    }
+
+    #[test]
+    fn absolute_y() {
+        let opcode = OPTABLE[0xFB];
+        assert_eq!(opcode.mode, AbsoluteY);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b0000_0001;
+        cpu.a = 0x05;
+        cpu.y = 0x01;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u16(cpu.pc + 1, 0x1234);
+        bus.cpu_write_u8(0x1235, 0x04);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(bus.cpu_read_u8(0x1235, false), 0x05);
+        assert_eq!(cpu.a, 0);
+        assert_eq!(cpu.flags, 0b0000_0011);
+        assert_eq!(cpu.pc, 0x0803);
+    }
+}
@@ -0,0 +1,62 @@
+use super::*;
+use crate::mos6502::JamPolicy;
+
+/// JAM - Halt the CPU (also known as KIL or STP)
+///
+/// These opcodes lock the CPU up in an endless loop and only a reset gets it out of there. Some
+/// games hit them by accident so halting and reporting is more useful than bringing the whole
+/// emulator down, which is why the behaviour can be picked through `Mos6502::jam_policy`.
+pub fn jam(cpu: &mut Mos6502, inst: Instruction, _bus: &mut Bus) -> u8 {
+    println!("{} -> {:?} was called with cpu: {:?}", inst.name, inst.mode, cpu);
+    match cpu.jam_policy {
+        JamPolicy::Halt => {
+            println!("CPU jammed by opcode {:02X} at {:04X}... halting", inst.opcode, cpu.pc);
+            cpu.jammed = true;
+        },
+        JamPolicy::Panic => panic!("CPU jammed by opcode {:02X} at {:04X}... aborting", inst.opcode, cpu.pc),
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPCODE_NAME: &str = "JAM";
+
+    fn init() -> (Mos6502, Bus) {
+        (Mos6502::new(), Bus::new())
+    }
+
+    #[test]
+    fn implicit() {
+        for op in [0x02, 0x12, 0x22, 0x32, 0x42, 0x52, 0x62, 0x72, 0x92, 0xB2, 0xD2, 0xF2] {
+            let opcode = OPTABLE[op];
+            assert_eq!(opcode.mode, Implicit);
+            assert_eq!(opcode.name, OPCODE_NAME);
+        }
+
+        let (mut cpu, mut bus) = init();
+        cpu.pc = 0x0800;
+        cpu.jam_policy = JamPolicy::Halt;
+        bus.cpu_write_u8(0x0800, 0x02);
+        bus.cpu_write_u8(0x0801, 0xE8); // INX
+        while !cpu.is_jammed() {
+            cpu.clock(&mut bus);
+        }
+        for _ in 0..10 {
+            cpu.clock(&mut bus);
+        }
+        assert_eq!(cpu.pc, 0x0800);
+        assert_eq!(cpu.x, 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn panic_policy() {
+        let (mut cpu, mut bus) = init();
+        cpu.pc = 0x0800;
+        cpu.jam_policy = JamPolicy::Panic;
+        cpu.execute_instruction(0x02, &mut bus);
+    }
+}
@@ -0,0 +1,63 @@
+use super::*;
+
+/// LAS - Load Accumulator, X and Stack Pointer (also known as LAR)
+/// A,X,SP,Z,N = M & SP
+///
+/// ANDs the memory contents with the stack pointer and loads the result into the accumulator, the
+/// X register and the stack pointer.
+pub fn las(cpu: &mut Mos6502, inst: Instruction, bus: &mut Bus) -> u8 {
+    println!("{} -> {:?} was called with cpu: {:?}", inst.name, inst.mode, cpu);
+    let (fetched, additional_cycle) = cpu.address_mode_fetch(bus, &inst);
+    let value = fetched & cpu.sp;
+    cpu.a = value;
+    cpu.x = value;
+    cpu.sp = value;
+    cpu.write_flag_cond(Zero, value == 0);
+    cpu.write_flag_cond(Negative, value & 0x80 == 0x80);
+    cpu.pc += inst.bytes as u16;
+    additional_cycle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPCODE_NAME: &str = "LAS";
+
+    fn init() -> (Mos6502, Bus) {
+        (Mos6502::new(), Bus::new())
+    }
+
+    #[test]
+    fn absolute_y() {
+        let opcode = OPTABLE[0xBB];
+        assert_eq!(opcode.mode, AbsoluteY);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0b1111_0000;
+        cpu.flags = 0b0000_0000;
+        cpu.y = 0x01;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u16(cpu.pc + 1, 0x1234);
+        bus.cpu_write_u8(0x1235, 0b1001_1001);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(cpu.a, 0b1001_0000);
+        assert_eq!(cpu.x, 0b1001_0000);
+        assert_eq!(cpu.sp, 0b1001_0000);
+        assert_eq!(cpu.flags, 0b1000_0000);
+        assert_eq!(cpu.pc, 0x0803);
+
+        // with page crossing
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.y = 0xff;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u16(cpu.pc + 1, 0x1234);
+        bus.cpu_write_u8(0x1333, 0x42);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles + 1);
+        assert_eq!(cpu.a, 0x42);
+    }
+}
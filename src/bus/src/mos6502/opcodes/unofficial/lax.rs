@@ -0,0 +1,125 @@
+use super::*;
+
+/// LAX - Load Accumulator and X Register
+/// A,X,Z,N = M
+///
+/// Loads a byte of memory into both the accumulator and the X register setting the zero and
+/// negative flags as appropriate. The immediate variant is unstable on most 6502s; the 2A03
+/// behaves as if the magic constant is $FF so it ends up being a plain load.
+pub fn lax(cpu: &mut Mos6502, inst: Instruction, bus: &mut Bus) -> u8 {
+    println!("{} -> {:?} was called with cpu: {:?}", inst.name, inst.mode, cpu);
+    let (fetched, additional_cycle) = cpu.address_mode_fetch(bus, &inst);
+    cpu.a = fetched;
+    cpu.x = fetched;
+    cpu.write_flag_cond(Zero, fetched == 0);
+    cpu.write_flag_cond(Negative, fetched & 0x80 == 0x80);
+    cpu.pc += inst.bytes as u16;
+    additional_cycle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPCODE_NAME: &str = "LAX";
+
+    fn init() -> (Mos6502, Bus) {
+        (Mos6502::new(), Bus::new())
+    }
+
+    #[test]
+    fn immediate() {
+        let opcode = OPTABLE[0xAB];
+        assert_eq!(opcode.mode, Immediate);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b0000_0000;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u8(cpu.pc + 1, 0x80);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(cpu.a, 0x80);
+        assert_eq!(cpu.x, 0x80);
+        assert_eq!(cpu.flags, 0b1000_0000);
+        assert_eq!(cpu.pc, 0x0802);
+        assert_eq!(cpu.sp, 0xff);
+    }
+
+    #[test]
+    fn zero_page() {
+        let opcode = OPTABLE[0xA7];
+        assert_eq!(opcode.mode, ZeroPage);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b0000_0000;
+        cpu.a = 0x12;
+        cpu.x = 0x34;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u8(cpu.pc + 1, 0x10);
+        bus.cpu_write_u8(0x10, 0x00);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(cpu.a, 0);
+        assert_eq!(cpu.x, 0);
+        assert_eq!(cpu.flags, 0b0000_0010);
+        assert_eq!(cpu.pc, 0x0802);
+        assert_eq!(cpu.sp, 0xff);
+    }
+
+    #[test]
+    fn absolute_y() {
+        let opcode = OPTABLE[0xBF];
+        assert_eq!(opcode.mode, AbsoluteY);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        // no page crossing
+        let (mut cpu, mut bus) = init();
+        cpu.flags = 0b0000_0000;
+        cpu.y = 0x01;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u16(cpu.pc + 1, 0x1234);
+        bus.cpu_write_u8(0x1235, 0x42);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(cpu.a, 0x42);
+        assert_eq!(cpu.x, 0x42);
+        assert_eq!(cpu.pc, 0x0803);
+
+        // with page crossing
+        let (mut cpu, mut bus) = init();
+        cpu.flags = 0b0000_0000;
+        cpu.y = 0xff;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u16(cpu.pc + 1, 0x1234);
+        bus.cpu_write_u8(0x1333, 0x42);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles + 1);
+        assert_eq!(cpu.a, 0x42);
+        assert_eq!(cpu.x, 0x42);
+        assert_eq!(cpu.pc, 0x0803);
+    }
+
+    #[test]
+    fn indirect_y() {
+        let opcode = OPTABLE[0xB3];
+        assert_eq!(opcode.mode, IndirectY);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        let (mut cpu, mut bus) = init();
+        cpu.flags = 0b0000_0000;
+        cpu.y = 0xff;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u8(cpu.pc + 1, 0x34);
+        bus.cpu_write_u16(0x34, 0x1234);
+        bus.cpu_write_u8(0x1333, 0x42);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles + 1);
+        assert_eq!(cpu.a, 0x42);
+        assert_eq!(cpu.x, 0x42);
+        assert_eq!(cpu.pc, 0x0802);
+    }
+}
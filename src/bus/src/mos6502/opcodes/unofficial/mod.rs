@@ -0,0 +1,46 @@
+mod jam;
+mod slo;
+mod rla;
+mod sre;
+mod rra;
+mod sax;
+mod lax;
+mod dcp;
+mod isc;
+mod anc;
+mod alr;
+mod arr;
+mod xaa;
+mod axs;
+mod ahx;
+mod shy;
+mod shx;
+mod tas;
+mod las;
+pub use jam::*;
+pub use slo::*;
+pub use rla::*;
+pub use sre::*;
+pub use rra::*;
+pub use sax::*;
+pub use lax::*;
+pub use dcp::*;
+pub use isc::*;
+pub use anc::*;
+pub use alr::*;
+pub use arr::*;
+pub use xaa::*;
+pub use axs::*;
+pub use ahx::*;
+pub use shy::*;
+pub use shx::*;
+pub use tas::*;
+pub use las::*;
+
+use crate::Bus;
+use crate::mos6502::{Mos6502, Instruction, Flags::*};
+use crate::mos6502::opcodes::rmw_write;
+#[allow(unused_imports)]
+use crate::mos6502::{AddressingMode::*};
+#[cfg(test)]
+use crate::mos6502::opcodes::{OPTABLE};
@@ -0,0 +1,77 @@
+use super::*;
+
+/// RLA - Rotate Left then AND with Accumulator
+/// M = M*2+C, A = A & M
+///
+/// Rotates the memory contents one bit left through the carry flag and then ANDs the result with
+/// the accumulator. Same as ROL followed by AND.
+pub fn rla(cpu: &mut Mos6502, inst: Instruction, bus: &mut Bus) -> u8 {
+    println!("{} -> {:?} was called with cpu: {:?}", inst.name, inst.mode, cpu);
+    let (addr, _) = cpu.address_mode_addr(bus, &inst);
+    let fetched = bus.cpu_read_u8(addr, false);
+    let result = (fetched << 1) | (cpu.flags & 0x1);
+    rmw_write(bus, addr, fetched, result);
+
+    cpu.a &= result;
+    cpu.write_flag_cond(Carry, fetched & 0x80 == 0x80);
+    cpu.write_flag_cond(Zero, cpu.a == 0);
+    cpu.write_flag_cond(Negative, cpu.a & 0x80 == 0x80);
+    cpu.pc += inst.bytes as u16;
+    0 // read-modify-write instructions always take the extra cycle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPCODE_NAME: &str = "RLA";
+
+    fn init() -> (Mos6502, Bus) {
+        (Mos6502::new(), Bus::new())
+    }
+
+    #[test]
+    fn zero_page_x() {
+        let opcode = OPTABLE[0x37];
+        assert_eq!(opcode.mode, ZeroPageX);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b0000_0001;
+        cpu.a = 0b1111_0001;
+        cpu.x = 0x01;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u8(cpu.pc + 1, 0x10);
+        bus.cpu_write_u8(0x11, 0b1100_0000);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(bus.cpu_read_u8(0x11, false), 0b1000_0001);
+        assert_eq!(cpu.a, 0b1000_0001);
+        assert_eq!(cpu.flags, 0b1000_0001);
+        assert_eq!(cpu.pc, 0x0802);
+        assert_eq!(cpu.sp, 0xff);
+    }
+
+    #[test]
+    fn absolute() {
+        let opcode = OPTABLE[0x2F];
+        assert_eq!(opcode.mode, Absolute);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b0000_0000;
+        cpu.a = 0b0000_0001;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u16(cpu.pc + 1, 0x1234);
+        bus.cpu_write_u8(0x1234, 0b0000_0001);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(bus.cpu_read_u8(0x1234, false), 0b0000_0010);
+        assert_eq!(cpu.a, 0);
+        assert_eq!(cpu.flags, 0b0000_0010);
+        assert_eq!(cpu.pc, 0x0803);
+        assert_eq!(cpu.sp, 0xff);
+    }
+}
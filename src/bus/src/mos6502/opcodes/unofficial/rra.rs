@@ -0,0 +1,97 @@
+use super::*;
+
+/// RRA - Rotate Right then Add with Carry
+/// M = M/2+C*128, A = A+M+C
+///
+/// Rotates the memory contents one bit right through the carry flag and then adds the result to
+/// the accumulator using the carry that came out of the rotation. Same as ROR followed by ADC.
+pub fn rra(cpu: &mut Mos6502, inst: Instruction, bus: &mut Bus) -> u8 {
+    println!("{} -> {:?} was called with cpu: {:?}", inst.name, inst.mode, cpu);
+    let (addr, _) = cpu.address_mode_addr(bus, &inst);
+    let fetched = bus.cpu_read_u8(addr, false);
+    let rotated = ((cpu.flags & 0x1) << 7) | fetched >> 1;
+    rmw_write(bus, addr, fetched, rotated);
+
+    let tmp = cpu.a as u16 + rotated as u16 + (fetched & 0x1) as u16;
+    let result = (tmp & 0xff) as u8;
+
+    cpu.write_flag_cond(Carry, tmp > 0x00ff);
+    cpu.write_flag_cond(Zero, result == 0);
+    cpu.write_flag_cond(Negative, (result & 0x80) == 0x80);
+    cpu.write_flag_cond(Overflow, ((cpu.a ^ result) & (rotated ^ result) & 0x80) == 0x80);
+
+    cpu.a = result;
+    cpu.pc += inst.bytes as u16;
+    0 // read-modify-write instructions always take the extra cycle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPCODE_NAME: &str = "RRA";
+
+    fn init() -> (Mos6502, Bus) {
+        (Mos6502::new(), Bus::new())
+    }
+
+    #[test]
+    fn zero_page() {
+        let opcode = OPTABLE[0x67];
+        assert_eq!(opcode.mode, ZeroPage);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        // carry from the rotation is used by the addition
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b0000_0000;
+        cpu.a = 10;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u8(cpu.pc + 1, 0x10);
+        bus.cpu_write_u8(0x10, 0b0000_0101);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(bus.cpu_read_u8(0x10, false), 0b0000_0010);
+        assert_eq!(cpu.a, 13);
+        assert_eq!(cpu.flags, 0b0000_0000);
+        assert_eq!(cpu.pc, 0x0802);
+        assert_eq!(cpu.sp, 0xff);
+
+        // carry before the op goes into bit 7 which then overflows
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b0000_0001;
+        cpu.a = 0b1000_0000;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u8(cpu.pc + 1, 0x10);
+        bus.cpu_write_u8(0x10, 0b0000_0000);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(bus.cpu_read_u8(0x10, false), 0b1000_0000);
+        assert_eq!(cpu.a, 0);
+        assert_eq!(cpu.flags, 0b0100_0011);
+        assert_eq!(cpu.pc, 0x0802);
+        assert_eq!(cpu.sp, 0xff);
+    }
+
+    #[test]
+    fn absolute_x() {
+        let opcode = OPTABLE[0x7F];
+        assert_eq!(opcode.mode, AbsoluteX);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b0000_0000;
+        cpu.a = 1;
+        cpu.x = 0xff;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u16(cpu.pc + 1, 0x1234);
+        bus.cpu_write_u8(0x1333, 4);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(bus.cpu_read_u8(0x1333, false), 2);
+        assert_eq!(cpu.a, 3);
+        assert_eq!(cpu.pc, 0x0803);
+    }
+}
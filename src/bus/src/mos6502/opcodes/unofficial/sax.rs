@@ -0,0 +1,68 @@
+use super::*;
+
+/// SAX - Store Accumulator AND X Register
+/// M = A & X
+///
+/// Stores the bitwise AND of the accumulator and the X register into memory. No flags are
+/// affected.
+pub fn sax(cpu: &mut Mos6502, inst: Instruction, bus: &mut Bus) -> u8 {
+    println!("{} -> {:?} was called with cpu: {:?}", inst.name, inst.mode, cpu);
+    let (addr, _) = cpu.address_mode_addr(bus, &inst);
+    bus.cpu_write_u8(addr, cpu.a & cpu.x);
+    cpu.pc += inst.bytes as u16;
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPCODE_NAME: &str = "SAX";
+
+    fn init() -> (Mos6502, Bus) {
+        (Mos6502::new(), Bus::new())
+    }
+
+    #[test]
+    fn zero_page_y() {
+        let opcode = OPTABLE[0x97];
+        assert_eq!(opcode.mode, ZeroPageY);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b0000_0000;
+        cpu.a = 0b1111_0000;
+        cpu.x = 0b0011_1100;
+        cpu.y = 0x02;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u8(cpu.pc + 1, 0xff);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(bus.cpu_read_u8(0x01, false), 0b0011_0000);
+        assert_eq!(cpu.a, 0b1111_0000);
+        assert_eq!(cpu.x, 0b0011_1100);
+        assert_eq!(cpu.flags, 0b0000_0000);
+        assert_eq!(cpu.pc, 0x0802);
+        assert_eq!(cpu.sp, 0xff);
+    }
+
+    #[test]
+    fn absolute() {
+        let opcode = OPTABLE[0x8F];
+        assert_eq!(opcode.mode, Absolute);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        let (mut cpu, mut bus) = init();
+        cpu.flags = 0b0000_0000;
+        cpu.a = 0b1000_0001;
+        cpu.x = 0b0000_0001;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u16(cpu.pc + 1, 0x1234);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(bus.cpu_read_u8(0x1234, false), 0b0000_0001);
+        assert_eq!(cpu.flags, 0b0000_0000);
+        assert_eq!(cpu.pc, 0x0803);
+    }
+}
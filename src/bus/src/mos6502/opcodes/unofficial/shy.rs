@@ -0,0 +1,49 @@
+use super::*;
+use super::ahx::unstable_store_addr;
+
+/// SHY - Store Y Register AND High Address (also known as SYA)
+/// M = Y & (H+1)
+///
+/// Stores Y & (high byte of the base address + 1) and suffers from the same address corruption
+/// as AHX when indexing crosses a page.
+pub fn shy(cpu: &mut Mos6502, inst: Instruction, bus: &mut Bus) -> u8 {
+    println!("{} -> {:?} was called with cpu: {:?}", inst.name, inst.mode, cpu);
+    let (addr, page_crossed) = cpu.address_mode_addr(bus, &inst);
+    let base = addr.wrapping_sub(cpu.x as u16);
+    let value = cpu.y & ((base >> 8) as u8).wrapping_add(1);
+    bus.cpu_write_u8(unstable_store_addr(addr, value, page_crossed), value);
+    cpu.pc += inst.bytes as u16;
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPCODE_NAME: &str = "SHY";
+
+    fn init() -> (Mos6502, Bus) {
+        (Mos6502::new(), Bus::new())
+    }
+
+    #[test]
+    fn absolute_x() {
+        let opcode = OPTABLE[0x9C];
+        assert_eq!(opcode.mode, AbsoluteX);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b0000_0000;
+        cpu.y = 0xff;
+        cpu.x = 0x01;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u16(cpu.pc + 1, 0x0634);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(bus.cpu_read_u8(0x0635, false), 0x07);
+        assert_eq!(cpu.flags, 0b0000_0000);
+        assert_eq!(cpu.pc, 0x0803);
+        assert_eq!(cpu.sp, 0xff);
+    }
+}
@@ -0,0 +1,102 @@
+use super::*;
+
+/// SLO - Arithmetic Shift Left then OR with Accumulator
+/// M = M*2, A = A | M
+///
+/// Shifts the memory contents one bit left (bit 7 goes into the carry flag) and then ORs the
+/// result into the accumulator. Same as ASL followed by ORA.
+pub fn slo(cpu: &mut Mos6502, inst: Instruction, bus: &mut Bus) -> u8 {
+    println!("{} -> {:?} was called with cpu: {:?}", inst.name, inst.mode, cpu);
+    let (addr, _) = cpu.address_mode_addr(bus, &inst);
+    let fetched = bus.cpu_read_u8(addr, false);
+    let result = fetched << 1;
+    rmw_write(bus, addr, fetched, result);
+
+    cpu.a |= result;
+    cpu.write_flag_cond(Carry, fetched & 0x80 == 0x80);
+    cpu.write_flag_cond(Zero, cpu.a == 0);
+    cpu.write_flag_cond(Negative, cpu.a & 0x80 == 0x80);
+    cpu.pc += inst.bytes as u16;
+    0 // read-modify-write instructions always take the extra cycle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPCODE_NAME: &str = "SLO";
+
+    fn init() -> (Mos6502, Bus) {
+        (Mos6502::new(), Bus::new())
+    }
+
+    #[test]
+    fn zero_page() {
+        let opcode = OPTABLE[0x07];
+        assert_eq!(opcode.mode, ZeroPage);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b0000_0000;
+        cpu.a = 0b0000_0001;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u8(cpu.pc + 1, 0x10);
+        bus.cpu_write_u8(0x10, 0b1100_0000);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(bus.cpu_read_u8(0x10, false), 0b1000_0000);
+        assert_eq!(cpu.a, 0b1000_0001);
+        assert_eq!(cpu.flags, 0b1000_0001);
+        assert_eq!(cpu.pc, 0x0802);
+        assert_eq!(cpu.sp, 0xff);
+    }
+
+    #[test]
+    fn absolute_y() {
+        let opcode = OPTABLE[0x1B];
+        assert_eq!(opcode.mode, AbsoluteY);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        // page crossing doesn't cost anything extra
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b0000_0000;
+        cpu.a = 0b0000_0000;
+        cpu.y = 0xff;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u16(cpu.pc + 1, 0x1234);
+        bus.cpu_write_u8(0x1333, 0b1000_0000);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(bus.cpu_read_u8(0x1333, false), 0);
+        assert_eq!(cpu.a, 0);
+        assert_eq!(cpu.flags, 0b0000_0011);
+        assert_eq!(cpu.pc, 0x0803);
+        assert_eq!(cpu.sp, 0xff);
+    }
+
+    #[test]
+    fn indirect_y() {
+        let opcode = OPTABLE[0x13];
+        assert_eq!(opcode.mode, IndirectY);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b0000_0000;
+        cpu.a = 0b0000_0001;
+        cpu.y = 0x01;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u8(cpu.pc + 1, 0x34);
+        bus.cpu_write_u16(0x34, 0x1234);
+        bus.cpu_write_u8(0x1235, 0b0000_0010);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(bus.cpu_read_u8(0x1235, false), 0b0000_0100);
+        assert_eq!(cpu.a, 0b0000_0101);
+        assert_eq!(cpu.flags, 0b0000_0000);
+        assert_eq!(cpu.pc, 0x0802);
+        assert_eq!(cpu.sp, 0xff);
+    }
+}
@@ -0,0 +1,78 @@
+use super::*;
+
+/// SRE - Logical Shift Right then Exclusive OR with Accumulator
+/// M = M/2, A = A ^ M
+///
+/// Shifts the memory contents one bit right (bit 0 goes into the carry flag) and then XORs the
+/// result into the accumulator. Same as LSR followed by EOR.
+pub fn sre(cpu: &mut Mos6502, inst: Instruction, bus: &mut Bus) -> u8 {
+    println!("{} -> {:?} was called with cpu: {:?}", inst.name, inst.mode, cpu);
+    let (addr, _) = cpu.address_mode_addr(bus, &inst);
+    let fetched = bus.cpu_read_u8(addr, false);
+    let result = fetched >> 1;
+    rmw_write(bus, addr, fetched, result);
+
+    cpu.a ^= result;
+    cpu.write_flag_cond(Carry, fetched & 0x1 == 0x1);
+    cpu.write_flag_cond(Zero, cpu.a == 0);
+    cpu.write_flag_cond(Negative, cpu.a & 0x80 == 0x80);
+    cpu.pc += inst.bytes as u16;
+    0 // read-modify-write instructions always take the extra cycle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPCODE_NAME: &str = "SRE";
+
+    fn init() -> (Mos6502, Bus) {
+        (Mos6502::new(), Bus::new())
+    }
+
+    #[test]
+    fn zero_page() {
+        let opcode = OPTABLE[0x47];
+        assert_eq!(opcode.mode, ZeroPage);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b0000_0000;
+        cpu.a = 0b1000_0001;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u8(cpu.pc + 1, 0x10);
+        bus.cpu_write_u8(0x10, 0b0000_0011);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(bus.cpu_read_u8(0x10, false), 0b0000_0001);
+        assert_eq!(cpu.a, 0b1000_0000);
+        assert_eq!(cpu.flags, 0b1000_0001);
+        assert_eq!(cpu.pc, 0x0802);
+        assert_eq!(cpu.sp, 0xff);
+    }
+
+    #[test]
+    fn indirect_x() {
+        let opcode = OPTABLE[0x43];
+        assert_eq!(opcode.mode, IndirectX);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b0000_0000;
+        cpu.a = 0b0000_0001;
+        cpu.x = 0x02;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u8(cpu.pc + 1, 0x32);
+        bus.cpu_write_u16(0x34, 0x1234);
+        bus.cpu_write_u8(0x1234, 0b0000_0010);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(bus.cpu_read_u8(0x1234, false), 0b0000_0001);
+        assert_eq!(cpu.a, 0);
+        assert_eq!(cpu.flags, 0b0000_0010);
+        assert_eq!(cpu.pc, 0x0802);
+        assert_eq!(cpu.sp, 0xff);
+    }
+}
@@ -0,0 +1,49 @@
+use super::*;
+
+/// XAA - Transfer X to Accumulator then AND (also known as ANE)
+/// A = (A | $EE) & X & M
+///
+/// Highly unstable opcode whose result depends on an analog "magic constant". $EE is the value
+/// most commonly observed on the 2A03 so that's what we go with.
+pub fn xaa(cpu: &mut Mos6502, inst: Instruction, bus: &mut Bus) -> u8 {
+    println!("{} -> {:?} was called with cpu: {:?}", inst.name, inst.mode, cpu);
+    let (fetched, _) = cpu.address_mode_fetch(bus, &inst);
+    cpu.a = (cpu.a | 0xEE) & cpu.x & fetched;
+    cpu.write_flag_cond(Zero, cpu.a == 0);
+    cpu.write_flag_cond(Negative, cpu.a & 0x80 == 0x80);
+    cpu.pc += inst.bytes as u16;
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPCODE_NAME: &str = "XAA";
+
+    fn init() -> (Mos6502, Bus) {
+        (Mos6502::new(), Bus::new())
+    }
+
+    #[test]
+    fn immediate() {
+        let opcode = OPTABLE[0x8B];
+        assert_eq!(opcode.mode, Immediate);
+        assert_eq!(opcode.name, OPCODE_NAME);
+
+        let (mut cpu, mut bus) = init();
+        cpu.sp = 0xff;
+        cpu.flags = 0b0000_0000;
+        cpu.a = 0b0000_0001;
+        cpu.x = 0b1111_0011;
+        cpu.pc = 0x0800;
+        bus.cpu_write_u8(cpu.pc + 1, 0b1000_0011);
+        let cycles = cpu.execute_instruction(opcode.opcode, &mut bus);
+        assert_eq!(cycles, opcode.cycles);
+        assert_eq!(cpu.a, 0b1000_0011);
+        assert_eq!(cpu.x, 0b1111_0011);
+        assert_eq!(cpu.flags, 0b1000_0000);
+        assert_eq!(cpu.pc, 0x0802);
+        assert_eq!(cpu.sp, 0xff);
+    }
+}
@@ -0,0 +1,45 @@
+/// Background half of the rendering pipeline: what the PPU fetched for the next tile and the
+/// 16-bit shift registers feeding pixels out of the current one.
+///
+/// Tiles are fetched 8 dots ahead, so while the pixels of one tile are shifted out the bytes for
+/// the following one get latched and are loaded into the low half of the shifters every 8 dots.
+/// Attributes only have one bit per plane for the whole tile, they're spread over 8 bits on load
+/// so they can be shifted alongside the pattern.
+#[derive(Default)]
+pub(super) struct Background {
+    pub(super) next_tile: u8,
+    // palette number (0-3) of the next tile, already picked out of the attribute byte
+    pub(super) next_attribute: u8,
+    pub(super) next_pattern_lo: u8,
+    pub(super) next_pattern_hi: u8,
+    pattern_lo: u16,
+    pattern_hi: u16,
+    attribute_lo: u16,
+    attribute_hi: u16,
+}
+
+impl Background {
+    pub(super) fn load(&mut self) {
+        let spread = |bit: bool| if bit { 0xFF } else { 0x00 };
+        self.pattern_lo = (self.pattern_lo & 0xFF00) | self.next_pattern_lo as u16;
+        self.pattern_hi = (self.pattern_hi & 0xFF00) | self.next_pattern_hi as u16;
+        self.attribute_lo = (self.attribute_lo & 0xFF00) | spread(self.next_attribute & 0x1 != 0);
+        self.attribute_hi = (self.attribute_hi & 0xFF00) | spread(self.next_attribute & 0x2 != 0);
+    }
+
+    pub(super) fn shift(&mut self) {
+        self.pattern_lo <<= 1;
+        self.pattern_hi <<= 1;
+        self.attribute_lo <<= 1;
+        self.attribute_hi <<= 1;
+    }
+
+    /// Pixel (0-3) and palette (0-3) currently at the top of the shifters, offset by fine X
+    pub(super) fn pixel(&self, fine_x: u8) -> (u8, u8) {
+        let mux = 0x8000 >> fine_x;
+        let bit = |register: u16| (register & mux != 0) as u8;
+        let pixel = bit(self.pattern_hi) << 1 | bit(self.pattern_lo);
+        let palette = bit(self.attribute_hi) << 1 | bit(self.attribute_lo);
+        (pixel, palette)
+    }
+}
@@ -1,7 +1,88 @@
+use crate::cartridge::mappers::Mapped;
+use crate::cartridge::Cartridge;
+use crate::memory::BusDevice;
+use self::background::Background;
+use self::sprites::{Sprites, Unit, ATTRIBUTE_FLIP_HORIZONTAL, ATTRIBUTE_FLIP_VERTICAL};
+use std::cell::RefCell;
+use std::mem::take;
+use std::rc::Rc;
+
+mod background;
+mod sprites;
+
+pub const FRAME_WIDTH: usize = 256;
+pub const FRAME_HEIGHT: usize = 240;
+const DOTS_PER_SCANLINE: u16 = 341;
+const VBLANK_SCANLINE: u16 = 241;
+const PRE_RENDER_SCANLINE: u16 = 261;
+
+const CTRL_VRAM_INCREMENT_32: u8 = 0x04;
+const CTRL_SPRITE_PATTERN_TABLE: u8 = 0x08;
+const CTRL_BG_PATTERN_TABLE: u8 = 0x10;
+const CTRL_SPRITE_SIZE_16: u8 = 0x20;
+const CTRL_NMI_ENABLE: u8 = 0x80;
+const MASK_GRAYSCALE: u8 = 0x01;
+const MASK_SHOW_BG_LEFT: u8 = 0x02;
+const MASK_SHOW_SPRITES_LEFT: u8 = 0x04;
+const MASK_SHOW_BG: u8 = 0x08;
+const MASK_SHOW_SPRITES: u8 = 0x10;
+const MASK_EMPHASIS: u8 = 0xE0;
+pub const STATUS_SPRITE_OVERFLOW: u8 = 0x20;
+pub const STATUS_SPRITE_0_HIT: u8 = 0x40;
+pub const STATUS_VBLANK: u8 = 0x80;
+
 pub struct PPU {
     // C: tbl_name[2][1024]
     tbl_name: [[u8; 1024]; 2],
     tbl_palette: [u8; 32],
+    oam: [u8; 256],
+    // $2000, $2001 and $2002
+    ctrl: u8,
+    mask: u8,
+    status: u8,
+    oam_addr: u8,
+    // Internal registers as named on nesdev ("loopy" registers): v is the current VRAM address,
+    // t the temporary one (the top-left of the screen while rendering), x the fine X scroll and
+    // w the first/second write toggle shared by $2005 and $2006. Both addresses are laid out as
+    // yyy NN YYYYY XXXXX (fine Y, nametable, coarse Y, coarse X).
+    v: u16,
+    t: u16,
+    x: u8,
+    w: bool,
+    // $2007 reads return the previous value, except for the palette
+    read_buffer: u8,
+    // next dot to be processed, scanline 261 being the pre-render one
+    scanline: u16,
+    dot: u16,
+    odd_frame: bool,
+    frame: u64,
+    // reading $2002 right as vblank starts keeps the flag (and the NMI) from showing up at all
+    vblank_suppressed: bool,
+    background: Background,
+    sprites: Sprites,
+    // the hardware only draws 8 sprites per scanline, games flicker to work around it
+    sprite_limit: bool,
+    // one entry per pixel: the palette color in the low 6 bits and the emphasis bits above
+    framebuffer: Vec<u16>,
+    // dots elapsed since power up, used to tell how stale the I/O latch bits are
+    dots: u64,
+    // Registers are exposed to the CPU through an 8-bit latch which holds the last value driven
+    // onto the PPU's data bus. Each bit slowly leaks to 0 unless it gets refreshed, so we keep
+    // track of when each of them was last written.
+    io_latch: u8,
+    io_latch_refreshed_at: [u64; 8],
+    // pattern tables live on the cartridge, which also gets to see every address the PPU puts
+    // on its bus (mappers like the MMC3 count scanlines that way)
+    cartridge: Rc<RefCell<Cartridge>>,
+}
+
+// roughly 600ms worth of NTSC PPU dots, which is about how long the latch holds a 1
+pub const IO_LATCH_DECAY_DOTS: u64 = 3_221_591;
+
+impl Default for PPU {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PPU {
@@ -9,55 +90,287 @@ impl PPU {
         PPU {
             tbl_name: [[0; 1024]; 2],
             tbl_palette: [0; 32],
+            oam: [0; 256],
+            ctrl: 0,
+            mask: 0,
+            status: 0,
+            oam_addr: 0,
+            v: 0,
+            t: 0,
+            x: 0,
+            w: false,
+            read_buffer: 0,
+            scanline: 0,
+            dot: 0,
+            odd_frame: false,
+            frame: 0,
+            vblank_suppressed: false,
+            background: Background::default(),
+            sprites: Sprites::default(),
+            sprite_limit: true,
+            framebuffer: vec![0; FRAME_WIDTH * FRAME_HEIGHT],
+            dots: 0,
+            io_latch: 0,
+            io_latch_refreshed_at: [0; 8],
+            cartridge: Rc::new(RefCell::new(Cartridge::new())),
         }
     }
 
-    pub fn cpu_read_u8(&self, addr: u16, _read_only: bool) -> u8 {
+    pub fn connect_cartridge(&mut self, cartridge: Rc<RefCell<Cartridge>>) {
+        self.cartridge = cartridge;
+    }
+
+    /// The reset line clears the write registers but leaves VRAM, OAM, the status flags and v
+    /// alone
+    pub fn reset(&mut self) {
+        self.ctrl = 0;
+        self.mask = 0;
+        self.t = 0;
+        self.x = 0;
+        self.w = false;
+        self.read_buffer = 0;
+    }
+
+    pub fn ctrl(&self) -> u8 {
+        self.ctrl
+    }
+
+    pub fn mask(&self) -> u8 {
+        self.mask
+    }
+
+    pub fn status(&self) -> u8 {
+        self.status
+    }
+
+    pub fn oam(&self) -> &[u8; 256] {
+        &self.oam
+    }
+
+    /// The PPU holds /NMI low for as long as it's in vblank with NMIs enabled
+    pub fn nmi_asserted(&self) -> bool {
+        self.status & STATUS_VBLANK != 0 && self.ctrl & CTRL_NMI_ENABLE != 0
+    }
+
+    /// Palette colors (6 bits) and emphasis bits (bits 6-8) of the last frame, 256x240 row by
+    /// row. Lines are overwritten as the PPU goes, so this is only a complete picture once
+    /// frame() moved on.
+    pub fn framebuffer(&self) -> &[u16] {
+        &self.framebuffer
+    }
+
+    /// Frames completed since power up, it goes up as vblank starts
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Scanline and dot the PPU is about to process
+    pub fn position(&self) -> (u16, u16) {
+        (self.scanline, self.dot)
+    }
+
+    pub fn sprite_limit(&self) -> bool {
+        self.sprite_limit
+    }
+
+    /// Turning the 8 sprites per scanline limit off draws every sprite, which gets rid of the
+    /// flickering games use to cope with it. The overflow flag still behaves like the hardware.
+    pub fn set_sprite_limit(&mut self, enabled: bool) {
+        self.sprite_limit = enabled;
+    }
+
+    fn rendering_enabled(&self) -> bool {
+        self.mask & (MASK_SHOW_BG | MASK_SHOW_SPRITES) != 0
+    }
+
+    fn rendering_scanline(&self) -> bool {
+        self.scanline < FRAME_HEIGHT as u16 || self.scanline == PRE_RENDER_SCANLINE
+    }
+
+    // $2007 accesses move v along, unless the PPU is busy rendering in which case both the
+    // coarse X and Y increments kick in (some games rely on this glitch)
+    fn increment_vram_address(&mut self) {
+        if self.rendering_enabled() && self.rendering_scanline() {
+            self.increment_coarse_x();
+            self.increment_y();
+            return;
+        }
+        let increment = match self.ctrl & CTRL_VRAM_INCREMENT_32 {
+            0 => 1,
+            _ => 32,
+        };
+        self.v = (self.v + increment) & 0x7FFF;
+    }
+
+    // coarse X wraps into the horizontally adjacent nametable
+    fn increment_coarse_x(&mut self) {
+        if self.v & 0x001F == 31 {
+            self.v &= !0x001F;
+            self.v ^= 0x0400;
+        } else {
+            self.v += 1;
+        }
+    }
+
+    // fine Y first, then coarse Y which wraps into the vertically adjacent nametable after row
+    // 29 (rows 30 and 31 are the attributes, scrolling into them wraps without switching)
+    fn increment_y(&mut self) {
+        if self.v & 0x7000 != 0x7000 {
+            self.v += 0x1000;
+            return;
+        }
+        self.v &= !0x7000;
+        let coarse_y = match (self.v & 0x03E0) >> 5 {
+            29 => {
+                self.v ^= 0x0800;
+                0
+            }
+            31 => 0,
+            coarse_y => coarse_y + 1,
+        };
+        self.v = (self.v & !0x03E0) | coarse_y << 5;
+    }
+
+    fn transfer_x(&mut self) {
+        self.v = (self.v & !0x041F) | (self.t & 0x041F);
+    }
+
+    fn transfer_y(&mut self) {
+        self.v = (self.v & !0x7BE0) | (self.t & 0x7BE0);
+    }
+
+    /// Value currently held by the I/O latch once decay is taken into account
+    pub fn io_latch(&self) -> u8 {
+        (0..8)
+            .filter(|bit| self.dots - self.io_latch_refreshed_at[*bit] < IO_LATCH_DECAY_DOTS)
+            .fold(0, |acc, bit| acc | (self.io_latch & (1 << bit)))
+    }
+
+    fn refresh_io_latch(&mut self, value: u8, driven: u8) {
+        self.io_latch = (self.io_latch() & !driven) | (value & driven);
+        for bit in 0..8 {
+            if driven & (1 << bit) != 0 {
+                self.io_latch_refreshed_at[bit] = self.dots;
+            }
+        }
+    }
+
+    /// Returns the register value along with a mask of the bits it actually drives, anything
+    /// else comes from the I/O latch.
+    fn register_read(&self, addr: u16) -> (u8, u8) {
         match addr {
             // Control
-            0x0 => 0,
+            0x0 => (0, 0x00),
             // Mask
-            0x1 => 0,
-            // Status
-            0x2 => 0,
+            0x1 => (0, 0x00),
+            // Status: only the top 3 bits are driven
+            0x2 => (self.status, 0xE0),
             // OAM Address
-            0x3 => 0,
-            // OAM Data
-            0x4 => 0,
+            0x3 => (0, 0x00),
+            // OAM Data: bits 2-4 of the sprite attributes don't exist
+            0x4 => match self.oam_addr & 0x3 {
+                2 => (self.oam[self.oam_addr as usize] & 0xE3, 0xFF),
+                _ => (self.oam[self.oam_addr as usize], 0xFF),
+            },
             // Scroll
-            0x5 => 0,
+            0x5 => (0, 0x00),
             // PPU Address
-            0x6 => 0,
-            // PPU Data
-            0x7 => 0,
+            0x6 => (0, 0x00),
+            // PPU Data: palette entries are 6 bits wide and skip the read buffer
+            0x7 => match self.v & 0x3FFF {
+                0x3F00..=0x3FFF => (self.palette_read(self.v), 0x3F),
+                _ => (self.read_buffer, 0xFF),
+            },
             _ => panic!("invalid address requested"),
         }
     }
 
-    pub fn cpu_read_u16(&self, addr: u16, read_only: bool) -> u16 {
+    pub fn cpu_read_u8(&mut self, addr: u16, read_only: bool) -> u8 {
+        if read_only {
+            return self.cpu_peek_u8(addr);
+        }
+
+        let (value, driven) = self.register_read(addr);
+        match addr {
+            0x2 => {
+                if (self.scanline, self.dot) == (VBLANK_SCANLINE, 1) {
+                    self.vblank_suppressed = true;
+                }
+                self.status &= !STATUS_VBLANK;
+                self.w = false;
+            }
+            0x7 => {
+                // reading the palette still fills the buffer, with the nametable byte "under" it
+                let addr = match self.v & 0x3FFF {
+                    addr @ 0x3F00..=0x3FFF => addr - 0x1000,
+                    addr => addr,
+                };
+                self.read_buffer = self.ppu_read_u8(addr, false);
+                self.increment_vram_address();
+            }
+            _ => {}
+        }
+        self.refresh_io_latch(value, driven);
+        self.io_latch()
+    }
+
+    /// Same as cpu_read_u8 but leaves the I/O latch untouched
+    pub fn cpu_peek_u8(&self, addr: u16) -> u8 {
+        let (value, driven) = self.register_read(addr);
+        (value & driven) | (self.io_latch() & !driven)
+    }
+
+    pub fn cpu_read_u16(&mut self, addr: u16, read_only: bool) -> u16 {
         let low = self.cpu_read_u8(addr, read_only);
         let high = self.cpu_read_u8(addr + 1, read_only);
         ((high as u16) << 8) | low as u16
     }
 
-    pub fn cpu_write_u8(&mut self, addr: u16, _value: u8) {
+    pub fn cpu_write_u8(&mut self, addr: u16, value: u8) {
+        // every write, even to read-only registers, fills the latch
+        self.refresh_io_latch(value, 0xFF);
         match addr {
-            // Control
-            0x0 => {}
+            // Control: the nametable bits go to t
+            0x0 => {
+                self.ctrl = value;
+                self.t = (self.t & !0x0C00) | ((value & 0x3) as u16) << 10;
+            }
             // Mask
-            0x1 => {}
+            0x1 => self.mask = value,
             // Status
             0x2 => {}
             // OAM Address
-            0x3 => {}
+            0x3 => self.oam_addr = value,
             // OAM Data
-            0x4 => {}
-            // Scroll
-            0x5 => {}
-            // PPU Address
-            0x6 => {}
+            0x4 => {
+                self.oam[self.oam_addr as usize] = value;
+                self.oam_addr = self.oam_addr.wrapping_add(1);
+            }
+            // Scroll: X first (coarse into t, fine into x), then Y
+            0x5 => {
+                if !self.w {
+                    self.t = (self.t & !0x001F) | (value >> 3) as u16;
+                    self.x = value & 0x7;
+                } else {
+                    self.t = (self.t & !0x73E0) | ((value & 0x7) as u16) << 12 | ((value & 0xF8) as u16) << 2;
+                }
+                self.w = !self.w;
+            }
+            // PPU Address: high byte first (bit 14 gets cleared), v is only updated by the second
+            0x6 => {
+                if !self.w {
+                    self.t = (self.t & 0x00FF) | ((value & 0x3F) as u16) << 8;
+                } else {
+                    self.t = (self.t & 0xFF00) | value as u16;
+                    self.v = self.t;
+                }
+                self.w = !self.w;
+            }
             // PPU Data
-            0x7 => {}
+            0x7 => {
+                self.ppu_write_u8(self.v, value);
+                self.increment_vram_address();
+            }
             _ => panic!("invalid address on PPU"),
         };
     }
@@ -70,11 +383,1063 @@ impl PPU {
     }
 
 
-    pub fn ppu_write_u8(&mut self, _addr: u16, _value: u8) {
-        panic!("Not implemented yet");
+    /// Processes one dot: 341 of them per scanline, 240 visible scanlines, vblank starting at
+    /// 241 and the pre-render scanline (261) getting everything ready for the next frame.
+    pub fn clock(&mut self) {
+        match self.scanline {
+            0..=239 => {
+                self.background_fetches();
+                self.sprite_fetches();
+                if (1..=256).contains(&self.dot) {
+                    self.render_pixel();
+                }
+            }
+            VBLANK_SCANLINE if self.dot == 1 => {
+                if !self.vblank_suppressed {
+                    self.status |= STATUS_VBLANK;
+                }
+                self.vblank_suppressed = false;
+                self.frame += 1;
+            }
+            PRE_RENDER_SCANLINE => {
+                if self.dot == 1 {
+                    self.status &= !(STATUS_VBLANK | STATUS_SPRITE_0_HIT | STATUS_SPRITE_OVERFLOW);
+                }
+                self.background_fetches();
+                self.sprite_fetches();
+                if self.rendering_enabled() && (280..=304).contains(&self.dot) {
+                    self.transfer_y();
+                }
+            }
+            _ => {}
+        }
+
+        self.dots += 1;
+        self.dot += 1;
+        // odd frames are one dot shorter when rendering, the last dot of the pre-render
+        // scanline is skipped
+        let skip = self.odd_frame && self.rendering_enabled();
+        if self.scanline == PRE_RENDER_SCANLINE && self.dot == DOTS_PER_SCANLINE - 1 && skip {
+            self.dot = DOTS_PER_SCANLINE;
+        }
+        if self.dot == DOTS_PER_SCANLINE {
+            self.dot = 0;
+            self.scanline += 1;
+            if self.scanline > PRE_RENDER_SCANLINE {
+                self.scanline = 0;
+                self.odd_frame = !self.odd_frame;
+            }
+        }
+    }
+
+    /// Background memory accesses of visible and pre-render scanlines. Every tile takes 8 dots:
+    /// nametable byte, attribute byte, then both pattern planes, 2 dots each. Dots 321-336
+    /// prefetch the first two tiles of the next scanline.
+    fn background_fetches(&mut self) {
+        if !self.rendering_enabled() {
+            return;
+        }
+        let dot = self.dot;
+        if (2..=257).contains(&dot) || (322..=337).contains(&dot) {
+            self.background.shift();
+        }
+        if (1..=256).contains(&dot) || (321..=336).contains(&dot) {
+            match dot % 8 {
+                1 => {
+                    if dot != 1 && dot != 321 {
+                        self.background.load();
+                    }
+                    self.background.next_tile = self.ppu_read_u8(0x2000 | (self.v & 0x0FFF), false);
+                }
+                3 => {
+                    let v = self.v;
+                    let addr = 0x23C0 | (v & 0x0C00) | ((v >> 4) & 0x38) | ((v >> 2) & 0x07);
+                    let attribute = self.ppu_read_u8(addr, false);
+                    // each byte covers 4x4 tiles, 2 bits per 2x2 quadrant
+                    let shift = ((v >> 4) & 0x4) | (v & 0x2);
+                    self.background.next_attribute = (attribute >> shift) & 0x3;
+                }
+                5 => {
+                    let addr = self.background_pattern_addr();
+                    self.background.next_pattern_lo = self.ppu_read_u8(addr, false);
+                }
+                7 => {
+                    let addr = self.background_pattern_addr() + 8;
+                    self.background.next_pattern_hi = self.ppu_read_u8(addr, false);
+                }
+                0 => self.increment_coarse_x(),
+                _ => {}
+            }
+        }
+        match dot {
+            256 => self.increment_y(),
+            257 => {
+                self.background.load();
+                self.transfer_x();
+            }
+            337 => self.background.load(),
+            // two more nametable fetches nobody uses, MMC5 counts scanlines with them
+            338 | 340 => {
+                self.ppu_read_u8(0x2000 | (self.v & 0x0FFF), false);
+            }
+            _ => {}
+        }
+    }
+
+    fn background_pattern_addr(&self) -> u16 {
+        let table = match self.ctrl & CTRL_BG_PATTERN_TABLE {
+            0 => 0x0000,
+            _ => 0x1000,
+        };
+        let fine_y = (self.v >> 12) & 0x7;
+        table | (self.background.next_tile as u16) << 4 | fine_y
+    }
+
+    /// Sprite side of visible and pre-render scanlines: secondary OAM is cleared during dots
+    /// 1-64 and filled during 65-256 (both done in one go here), then each of the 8 slots gets
+    /// 8 dots to fetch its pattern, 2 nametable fetches going nowhere followed by both planes.
+    /// Empty slots still fetch tile $FF, which mappers watching A12 rely on.
+    fn sprite_fetches(&mut self) {
+        if !self.rendering_enabled() {
+            return;
+        }
+        let dot = self.dot;
+        match dot {
+            64 if self.scanline != PRE_RENDER_SCANLINE => self.sprites.clear_secondary_oam(),
+            256 if self.scanline == PRE_RENDER_SCANLINE => self.sprites.clear_found(),
+            256 => {
+                let overflow = self.sprites.evaluate(&self.oam, self.scanline, self.sprite_height(), self.sprite_limit);
+                if overflow {
+                    self.status |= STATUS_SPRITE_OVERFLOW;
+                }
+            }
+            257 => self.sprites.start_loading(),
+            _ => {}
+        }
+        if !(257..=320).contains(&dot) {
+            return;
+        }
+
+        self.oam_addr = 0;
+        let slot = ((dot - 257) / 8) as usize;
+        let sprite: [u8; 4] = self.sprites.secondary_oam[slot * 4..slot * 4 + 4].try_into().unwrap();
+        match dot % 8 {
+            1 | 3 => {
+                self.ppu_read_u8(0x2000 | (self.v & 0x0FFF), false);
+            }
+            5 => {
+                let addr = self.sprite_pattern_addr(&sprite);
+                self.sprites.next_pattern_lo = self.ppu_read_u8(addr, false);
+            }
+            0 => {
+                let addr = self.sprite_pattern_addr(&sprite) + 8;
+                let pattern_hi = self.ppu_read_u8(addr, false);
+                if slot < self.sprites.found {
+                    let unit = self.sprite_unit(&sprite, self.sprites.next_pattern_lo, pattern_hi);
+                    self.sprites.load(unit);
+                }
+            }
+            _ => {}
+        }
+
+        // sprites past the limit don't exist on the hardware, fetching them mustn't be seen
+        // by the cartridge
+        if dot == 320 {
+            for sprite in take(&mut self.sprites.extra) {
+                let addr = self.sprite_pattern_addr(&sprite);
+                let pattern_lo = self.ppu_peek_u8(addr);
+                let pattern_hi = self.ppu_peek_u8(addr + 8);
+                let unit = self.sprite_unit(&sprite, pattern_lo, pattern_hi);
+                self.sprites.load(unit);
+            }
+        }
+    }
+
+    fn sprite_height(&self) -> u16 {
+        match self.ctrl & CTRL_SPRITE_SIZE_16 {
+            0 => 8,
+            _ => 16,
+        }
+    }
+
+    // 8x16 sprites take their pattern table from bit 0 of the tile number, the top half being
+    // the even tile and the bottom half the odd one
+    fn sprite_pattern_addr(&self, sprite: &[u8; 4]) -> u16 {
+        let height = self.sprite_height();
+        let [y, tile, attribute, _] = *sprite;
+        let mut row = self.scanline.wrapping_sub(y as u16) & (height - 1);
+        if attribute & ATTRIBUTE_FLIP_VERTICAL != 0 {
+            row = height - 1 - row;
+        }
+        let (table, tile) = match (height, self.ctrl & CTRL_SPRITE_PATTERN_TABLE) {
+            (16, _) => ((tile as u16 & 0x1) << 12, (tile & 0xFE) as u16 + row / 8),
+            (_, 0) => (0x0000, tile as u16),
+            _ => (0x1000, tile as u16),
+        };
+        table | tile << 4 | (row & 0x7)
+    }
+
+    fn sprite_unit(&self, sprite: &[u8; 4], pattern_lo: u8, pattern_hi: u8) -> Unit {
+        let flip = |pattern: u8| match sprite[2] & ATTRIBUTE_FLIP_HORIZONTAL {
+            0 => pattern,
+            _ => pattern.reverse_bits(),
+        };
+        Unit {
+            pattern_lo: flip(pattern_lo),
+            pattern_hi: flip(pattern_hi),
+            attribute: sprite[2],
+            x: sprite[3],
+        }
+    }
+
+    fn render_pixel(&mut self) {
+        let x = (self.dot - 1) as usize;
+        let (pixel, palette) = match self.mask & MASK_SHOW_BG {
+            0 => (0, 0),
+            _ if x < 8 && self.mask & MASK_SHOW_BG_LEFT == 0 => (0, 0),
+            _ => self.background.pixel(self.x),
+        };
+        let sprite = match self.mask & MASK_SHOW_SPRITES {
+            0 => None,
+            _ if x < 8 && self.mask & MASK_SHOW_SPRITES_LEFT == 0 => None,
+            _ => self.sprites.pixel(x as u8),
+        };
+
+        // sprite 0 hits whenever both are opaque, whatever the priority, except on the last
+        // column
+        if let Some(sprite) = &sprite {
+            if sprite.sprite_zero && pixel != 0 && x != 255 {
+                self.status |= STATUS_SPRITE_0_HIT;
+            }
+        }
+
+        let addr = match sprite {
+            // with rendering off the backdrop is whichever palette entry v points to
+            _ if !self.rendering_enabled() && self.v & 0x3F00 == 0x3F00 => self.v,
+            Some(sprite) if pixel == 0 || !sprite.behind_background => {
+                0x3F10 | (sprite.palette as u16) << 2 | sprite.pixel as u16
+            }
+            _ if pixel == 0 => 0x3F00,
+            _ => 0x3F00 | (palette as u16) << 2 | pixel as u16,
+        };
+        let emphasis = ((self.mask & MASK_EMPHASIS) as u16) << 1;
+        self.framebuffer[self.scanline as usize * FRAME_WIDTH + x] = self.palette_read(addr) as u16 | emphasis;
+    }
+
+    // The cartridge decides where PPU accesses go, including the PPU's own nametable RAM
+    fn load_ciram(&self, mapped: Mapped) -> u8 {
+        match mapped {
+            Mapped::Ciram(offset) => self.tbl_name[(offset >> 10) & 0x1][offset & 0x3FF],
+            Mapped::Data(value) => value,
+            _ => 0,
+        }
+    }
+
+    fn store_ciram(&mut self, mapped: Mapped, value: u8) {
+        if let Mapped::Ciram(offset) = mapped {
+            self.tbl_name[(offset >> 10) & 0x1][offset & 0x3FF] = value;
+        }
+    }
+
+    // $3F10/$3F14/$3F18/$3F1C are the same cells as $3F00/$3F04/$3F08/$3F0C
+    fn palette_index(addr: u16) -> usize {
+        let index = (addr & 0x1F) as usize;
+        match index & 0x13 {
+            0x10 => index & !0x10,
+            _ => index,
+        }
+    }
+
+    fn palette_read(&self, addr: u16) -> u8 {
+        let value = self.tbl_palette[PPU::palette_index(addr)];
+        match self.mask & MASK_GRAYSCALE {
+            0 => value,
+            _ => value & 0x30,
+        }
+    }
+
+    /// Writes to the PPU's 14-bit address space: pattern tables ($0000-$1FFF) and nametables
+    /// ($2000-$2FFF, mirrored up to $3EFF) are whatever the cartridge says they are, palette RAM
+    /// ($3F00-$3F1F, mirrored up to $3FFF) lives in the PPU.
+    pub fn ppu_write_u8(&mut self, addr: u16, value: u8) {
+        let addr = addr & 0x3FFF;
+        match addr {
+            0x0000..=0x1FFF => {
+                let mapped = self.cartridge.borrow_mut().pattern_write(addr, value);
+                self.store_ciram(mapped, value);
+            }
+            // $3000-$3EFF mirrors the nametables
+            0x2000..=0x3EFF => {
+                let mapped = self.cartridge.borrow_mut().nametable_write(0x2000 | (addr & 0x0FFF), value);
+                self.store_ciram(mapped, value);
+            }
+            // palette entries are only 6 bits wide
+            _ => self.tbl_palette[PPU::palette_index(addr)] = value & 0x3F,
+        }
+    }
+
+    pub fn ppu_read_u8(&mut self, addr: u16, read_only: bool) -> u8 {
+        if read_only {
+            return self.ppu_peek_u8(addr);
+        }
+
+        let addr = addr & 0x3FFF;
+        match addr {
+            0x0000..=0x1FFF => {
+                let mapped = self.cartridge.borrow_mut().pattern_read(addr);
+                self.load_ciram(mapped)
+            }
+            0x2000..=0x3EFF => {
+                let mapped = self.cartridge.borrow_mut().nametable_read(0x2000 | (addr & 0x0FFF));
+                self.load_ciram(mapped)
+            }
+            _ => self.palette_read(addr),
+        }
+    }
+
+    /// Same as ppu_read_u8 but the cartridge doesn't get to see the access, so mappers snooping
+    /// the PPU bus (MMC2, MMC3, MMC5) aren't disturbed
+    pub fn ppu_peek_u8(&self, addr: u16) -> u8 {
+        let addr = addr & 0x3FFF;
+        match addr {
+            0x0000..=0x1FFF => self.load_ciram(self.cartridge.borrow().pattern_peek(addr)),
+            0x2000..=0x3EFF => self.load_ciram(self.cartridge.borrow().nametable_peek(0x2000 | (addr & 0x0FFF))),
+            _ => self.palette_read(addr),
+        }
+    }
+}
+
+impl BusDevice for PPU {
+    fn read(&mut self, addr: u16, _open_bus: u8) -> u8 {
+        self.cpu_read_u8(addr, false)
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        self.cpu_write_u8(addr, value);
+    }
+
+    fn peek(&self, addr: u16, _open_bus: u8) -> u8 {
+        self.cpu_peek_u8(addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_only_registers_return_io_latch() {
+        let mut ppu = PPU::new();
+        ppu.cpu_write_u8(0x0, 0x5A);
+        for addr in [0x0, 0x1, 0x3, 0x5, 0x6] {
+            assert_eq!(ppu.cpu_read_u8(addr, false), 0x5A);
+        }
+    }
+
+    #[test]
+    fn test_status_low_bits_come_from_io_latch() {
+        let mut ppu = PPU::new();
+        ppu.cpu_write_u8(0x5, 0xFF);
+        assert_eq!(ppu.cpu_read_u8(0x2, false), 0x1F);
+        // reading the status register drove the top 3 bits
+        assert_eq!(ppu.cpu_read_u8(0x0, false), 0x1F);
+    }
+
+    #[test]
+    fn test_peek_does_not_refresh_io_latch() {
+        let mut ppu = PPU::new();
+        ppu.cpu_write_u8(0x6, 0xFF);
+        assert_eq!(ppu.cpu_peek_u8(0x7), 0x00);
+        assert_eq!(ppu.cpu_read_u8(0x7, true), 0x00);
+        assert_eq!(ppu.io_latch(), 0xFF);
+
+        assert_eq!(ppu.cpu_read_u8(0x7, false), 0x00);
+        assert_eq!(ppu.io_latch(), 0x00);
+    }
+
+    #[test]
+    fn test_io_latch_decays() {
+        let mut ppu = PPU::new();
+        ppu.cpu_write_u8(0x0, 0xF0);
+        for _ in 0..IO_LATCH_DECAY_DOTS / 2 {
+            ppu.clock();
+        }
+        // refreshing some bits keeps those alive for longer
+        ppu.cpu_write_u8(0x0, 0x30);
+        for _ in 0..IO_LATCH_DECAY_DOTS - IO_LATCH_DECAY_DOTS / 2 {
+            ppu.clock();
+        }
+        assert_eq!(ppu.io_latch(), 0x30);
+
+        for _ in 0..IO_LATCH_DECAY_DOTS / 2 {
+            ppu.clock();
+        }
+        assert_eq!(ppu.io_latch(), 0x00);
+    }
+
+    #[test]
+    fn test_pattern_writes_land_in_chr_ram() {
+        let cartridge = Rc::new(RefCell::new(Cartridge::new()));
+        let mut ppu = PPU::new();
+        ppu.connect_cartridge(cartridge.clone());
+
+        cartridge.borrow_mut().load_from_bytes(&crate::test::banked_rom_image(2, 2, 0)).unwrap();
+        ppu.ppu_write_u8(0x1234, 0x42);
+        assert_eq!(ppu.ppu_read_u8(0x1234, false), 0x42);
+
+        // CHR-ROM can't be written to
+        cartridge.borrow_mut().load_from_bytes(&crate::test::banked_rom_image(2, 2, 1)).unwrap();
+        ppu.ppu_write_u8(0x0400, 0x42);
+        assert_eq!(ppu.ppu_read_u8(0x0400, false), 1);
+    }
+
+    fn set_vram_address(ppu: &mut PPU, addr: u16) {
+        ppu.cpu_write_u8(0x6, (addr >> 8) as u8);
+        ppu.cpu_write_u8(0x6, addr as u8);
+    }
+
+    #[test]
+    fn test_status_read_clears_vblank_and_write_toggle() {
+        let mut ppu = PPU::new();
+        ppu.status = STATUS_VBLANK | STATUS_SPRITE_0_HIT | STATUS_SPRITE_OVERFLOW;
+        ppu.cpu_write_u8(0x5, 0x00);
+        assert!(ppu.w);
+
+        assert_eq!(ppu.cpu_peek_u8(0x2) & 0xE0, 0xE0);
+        assert_eq!(ppu.status, 0xE0);
+        assert_eq!(ppu.cpu_read_u8(0x2, false) & 0xE0, 0xE0);
+        // only vblank is cleared, sprite flags last until the pre-render line
+        assert_eq!(ppu.status, STATUS_SPRITE_0_HIT | STATUS_SPRITE_OVERFLOW);
+        assert!(!ppu.w);
+        assert_eq!(ppu.cpu_read_u8(0x2, false) & 0xE0, 0x60);
+    }
+
+    #[test]
+    fn test_scroll_and_address_writes() {
+        // the register walkthrough from nesdev's "PPU scrolling" page
+        let mut ppu = PPU::new();
+        ppu.cpu_write_u8(0x0, 0x03);
+        assert_eq!(ppu.t, 0x0C00);
+        ppu.cpu_read_u8(0x2, false);
+        assert!(!ppu.w);
+
+        ppu.cpu_write_u8(0x5, 0x7D);
+        assert_eq!((ppu.t, ppu.x, ppu.w), (0x0C0F, 0x5, true));
+        ppu.cpu_write_u8(0x5, 0x5E);
+        assert_eq!((ppu.t, ppu.x, ppu.w), (0x6D6F, 0x5, false));
+
+        ppu.cpu_write_u8(0x6, 0x3D);
+        assert_eq!((ppu.t, ppu.w), (0x3D6F, true));
+        assert_eq!(ppu.v, 0x0000);
+        ppu.cpu_write_u8(0x6, 0xF0);
+        assert_eq!((ppu.t, ppu.v, ppu.x, ppu.w), (0x3DF0, 0x3DF0, 0x5, false));
+
+        // bit 14 of t is cleared by the first $2006 write
+        ppu.cpu_write_u8(0x6, 0xFF);
+        assert_eq!(ppu.t & 0x4000, 0);
     }
 
-    pub fn ppu_read_u8(&mut self, _addr: u16, _read_only: bool) -> u8 {
-        panic!("Not implemented yet");
+    #[test]
+    fn test_ppudata_read_buffer_and_increments() {
+        let mut ppu = PPU::new();
+        set_vram_address(&mut ppu, 0x2000);
+        for value in [0x11, 0x22, 0x33] {
+            ppu.cpu_write_u8(0x7, value);
+        }
+        assert_eq!(ppu.v, 0x2003);
+
+        // reads lag one behind because of the buffer
+        set_vram_address(&mut ppu, 0x2000);
+        assert_eq!(ppu.cpu_peek_u8(0x7), 0x00);
+        assert_eq!(ppu.cpu_read_u8(0x7, false), 0x00);
+        assert_eq!(ppu.cpu_read_u8(0x7, false), 0x11);
+        assert_eq!(ppu.cpu_read_u8(0x7, false), 0x22);
+
+        // going down a column
+        ppu.cpu_write_u8(0x0, CTRL_VRAM_INCREMENT_32);
+        set_vram_address(&mut ppu, 0x2000);
+        ppu.cpu_write_u8(0x7, 0x44);
+        assert_eq!(ppu.v, 0x2020);
+        ppu.cpu_write_u8(0x7, 0x55);
+        assert_eq!(ppu.ppu_read_u8(0x2020, true), 0x55);
+    }
+
+    #[test]
+    fn test_palette_reads_bypass_the_buffer() {
+        let mut ppu = PPU::new();
+        set_vram_address(&mut ppu, 0x2F05);
+        ppu.cpu_write_u8(0x7, 0x77);
+        set_vram_address(&mut ppu, 0x3F05);
+        ppu.cpu_write_u8(0x7, 0xE9);
+
+        // the palette value comes back straight away, 6 bits of it with the rest from the latch
+        set_vram_address(&mut ppu, 0x3F05);
+        ppu.cpu_write_u8(0x2, 0xC0);
+        assert_eq!(ppu.cpu_read_u8(0x7, false), 0xC0 | 0x29);
+        // while the buffer got the nametable byte underneath
+        assert_eq!(ppu.read_buffer, 0x77);
+
+        // grayscale masks the color off the palette reads
+        ppu.cpu_write_u8(0x1, MASK_GRAYSCALE);
+        set_vram_address(&mut ppu, 0x3F05);
+        assert_eq!(ppu.cpu_read_u8(0x7, false) & 0x3F, 0x20);
+    }
+
+    #[test]
+    fn test_oam_data() {
+        let mut ppu = PPU::new();
+        ppu.cpu_write_u8(0x3, 0xFE);
+        ppu.cpu_write_u8(0x4, 0x12);
+        ppu.cpu_write_u8(0x4, 0xFF);
+        ppu.cpu_write_u8(0x4, 0x34);
+        // writes increment OAMADDR and wrap around
+        assert_eq!(ppu.oam_addr, 0x01);
+        assert_eq!(ppu.oam[0xFE], 0x12);
+        assert_eq!(ppu.oam[0x00], 0x34);
+
+        // reads don't increment it, and attribute bytes lose bits 2-4
+        ppu.cpu_write_u8(0x3, 0x00);
+        assert_eq!(ppu.cpu_read_u8(0x4, false), 0x34);
+        assert_eq!(ppu.cpu_read_u8(0x4, false), 0x34);
+        ppu.cpu_write_u8(0x3, 0x02);
+        ppu.cpu_write_u8(0x4, 0xFF);
+        ppu.cpu_write_u8(0x3, 0x02);
+        assert_eq!(ppu.cpu_read_u8(0x4, false), 0xE3);
+    }
+
+    #[test]
+    fn test_nmi_output() {
+        let mut ppu = PPU::new();
+        ppu.status = STATUS_VBLANK;
+        assert!(!ppu.nmi_asserted());
+        ppu.cpu_write_u8(0x0, CTRL_NMI_ENABLE);
+        assert!(ppu.nmi_asserted());
+        // acknowledged by reading the status
+        ppu.cpu_read_u8(0x2, false);
+        assert!(!ppu.nmi_asserted());
+    }
+
+    #[test]
+    fn test_reset_clears_write_registers() {
+        let mut ppu = PPU::new();
+        ppu.cpu_write_u8(0x0, 0xFF);
+        ppu.cpu_write_u8(0x1, 0xFF);
+        ppu.cpu_write_u8(0x5, 0xFF);
+        ppu.cpu_write_u8(0x3, 0x10);
+        ppu.reset();
+        assert_eq!((ppu.ctrl, ppu.mask, ppu.t, ppu.x, ppu.w), (0, 0, 0, 0, false));
+        assert_eq!(ppu.oam_addr, 0x10);
+    }
+
+    fn ppu_with_rom(contents: &[u8]) -> (PPU, Rc<RefCell<Cartridge>>) {
+        let cartridge = Rc::new(RefCell::new(Cartridge::new()));
+        cartridge.borrow_mut().load_from_bytes(contents).unwrap();
+        let mut ppu = PPU::new();
+        ppu.connect_cartridge(cartridge.clone());
+        (ppu, cartridge)
+    }
+
+    /// Writes a different value to each of the four nametables through $2006/$2007 and returns
+    /// what each of them reads back afterwards
+    fn nametable_layout(ppu: &mut PPU) -> [u8; 4] {
+        for table in 0..4u16 {
+            set_vram_address(ppu, 0x2000 + table * 0x400 + 0x3C);
+            ppu.cpu_write_u8(0x7, table as u8 + 1);
+        }
+        let mut layout = [0; 4];
+        for (table, value) in layout.iter_mut().enumerate() {
+            set_vram_address(ppu, 0x2000 + table as u16 * 0x400 + 0x3C);
+            ppu.cpu_read_u8(0x7, false);
+            *value = ppu.cpu_read_u8(0x7, false);
+        }
+        layout
+    }
+
+    #[test]
+    fn test_mirroring_from_header() {
+        use crate::test::banked_rom_image;
+
+        // horizontal: $2000 = $2400 and $2800 = $2C00
+        let (mut ppu, _) = ppu_with_rom(&banked_rom_image(0, 1, 1));
+        assert_eq!(nametable_layout(&mut ppu), [2, 2, 4, 4]);
+
+        // vertical: $2000 = $2800 and $2400 = $2C00
+        let mut contents = banked_rom_image(0, 1, 1);
+        contents[6] |= 0x1;
+        let (mut ppu, _) = ppu_with_rom(&contents);
+        assert_eq!(nametable_layout(&mut ppu), [3, 4, 3, 4]);
+
+        // four-screen: every table is its own
+        contents[6] |= 0x8;
+        let (mut ppu, _) = ppu_with_rom(&contents);
+        assert_eq!(nametable_layout(&mut ppu), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_mirroring_changed_by_mappers() {
+        use crate::test::banked_rom_image;
+
+        // AxROM picks one of the single screens through bit 4
+        let (mut ppu, cartridge) = ppu_with_rom(&banked_rom_image(7, 2, 0));
+        assert_eq!(nametable_layout(&mut ppu), [4, 4, 4, 4]);
+        // the other page wasn't touched
+        cartridge.borrow_mut().cpu_write(0x8000, 0x10);
+        set_vram_address(&mut ppu, 0x2C3C);
+        ppu.cpu_read_u8(0x7, false);
+        assert_eq!(ppu.cpu_read_u8(0x7, false), 0);
+        assert_eq!(nametable_layout(&mut ppu), [4, 4, 4, 4]);
+
+        // MMC1 goes through all of them with the control register
+        let (mut ppu, cartridge) = ppu_with_rom(&banked_rom_image(1, 2, 1));
+        let layouts = [[4, 4, 4, 4], [4, 4, 4, 4], [3, 4, 3, 4], [2, 2, 4, 4]];
+        for (mirroring, layout) in layouts.iter().enumerate() {
+            let control = mirroring as u8 | 0x0C;
+            for bit in 0..5 {
+                // the MMC1 ignores writes on back to back CPU cycles
+                cartridge.borrow_mut().cpu_clock();
+                cartridge.borrow_mut().cpu_clock();
+                cartridge.borrow_mut().cpu_write(0x8000, (control >> bit) & 0x1);
+            }
+            assert_eq!(&nametable_layout(&mut ppu), layout, "mirroring {}", mirroring);
+        }
+    }
+
+    #[test]
+    fn test_address_space_mirrors() {
+        let mut ppu = PPU::new();
+        // $3000-$3EFF is the nametables again
+        set_vram_address(&mut ppu, 0x3123);
+        ppu.cpu_write_u8(0x7, 0x42);
+        assert_eq!(ppu.ppu_peek_u8(0x2123), 0x42);
+        // so is anything above $3FFF, the address is only 14 bits
+        assert_eq!(ppu.ppu_peek_u8(0x6123), 0x42);
+
+        // the backdrop color of the sprite palettes is shared with the background ones
+        for (mirror, entry) in [(0x3F10, 0x3F00), (0x3F14, 0x3F04), (0x3F18, 0x3F08), (0x3F1C, 0x3F0C)] {
+            set_vram_address(&mut ppu, mirror);
+            ppu.cpu_write_u8(0x7, (entry & 0xFF) as u8 + 1);
+            set_vram_address(&mut ppu, entry);
+            assert_eq!(ppu.cpu_read_u8(0x7, false), (entry & 0xFF) as u8 + 1);
+        }
+        // other sprite entries are their own
+        set_vram_address(&mut ppu, 0x3F11);
+        ppu.cpu_write_u8(0x7, 0x2A);
+        assert_eq!(ppu.ppu_peek_u8(0x3F01), 0);
+        // and the 32 bytes repeat up to $3FFF
+        assert_eq!(ppu.ppu_peek_u8(0x3FF1), 0x2A);
+    }
+
+    fn run_until(ppu: &mut PPU, scanline: u16, dot: u16) {
+        while ppu.position() != (scanline, dot) {
+            ppu.clock();
+        }
+    }
+
+    fn run_frame(ppu: &mut PPU) {
+        let frame = ppu.frame();
+        while ppu.frame() == frame {
+            ppu.clock();
+        }
+    }
+
+    /// CHR-RAM NROM with tile 1 made of color 1 pixels and tile 2 of color 2 pixels, laid out
+    /// in alternating columns over the first nametable. The top right 2x2 tiles of the first
+    /// attribute block use palette 1, everything else palette 0.
+    fn ppu_with_background() -> PPU {
+        let (mut ppu, _) = ppu_with_rom(&crate::test::banked_rom_image(0, 1, 0));
+        for row in 0..8 {
+            ppu.ppu_write_u8(0x0010 + row, 0xFF);
+            ppu.ppu_write_u8(0x0028 + row, 0xFF);
+        }
+        for tile in 0..960 {
+            ppu.ppu_write_u8(0x2000 + tile, (tile % 2) as u8 + 1);
+        }
+        ppu.ppu_write_u8(0x23C0, 0x04);
+        for (addr, color) in [(0x3F00, 0x0F), (0x3F01, 0x01), (0x3F02, 0x02), (0x3F05, 0x05), (0x3F06, 0x06)] {
+            ppu.ppu_write_u8(addr, color);
+        }
+        ppu
+    }
+
+    fn pixel(ppu: &PPU, x: usize, y: usize) -> u16 {
+        ppu.framebuffer()[y * FRAME_WIDTH + x]
+    }
+
+    #[test]
+    fn test_vblank_timing() {
+        let mut ppu = PPU::new();
+        run_until(&mut ppu, VBLANK_SCANLINE, 1);
+        assert_eq!(ppu.status & STATUS_VBLANK, 0);
+        ppu.clock();
+        assert_eq!(ppu.status & STATUS_VBLANK, STATUS_VBLANK);
+        assert_eq!(ppu.frame(), 1);
+        assert_eq!(ppu.dots, 241 * 341 + 2);
+
+        ppu.status |= STATUS_SPRITE_0_HIT | STATUS_SPRITE_OVERFLOW;
+        run_until(&mut ppu, PRE_RENDER_SCANLINE, 1);
+        assert_eq!(ppu.status & STATUS_VBLANK, STATUS_VBLANK);
+        ppu.clock();
+        assert_eq!(ppu.status & 0xE0, 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_frame_length() {
+        let mut ppu = PPU::new();
+        run_frame(&mut ppu);
+        let start = ppu.dots;
+        run_frame(&mut ppu);
+        assert_eq!(ppu.dots - start, 341 * 262);
+
+        // with rendering on, every other frame skips a dot
+        ppu.cpu_write_u8(0x1, MASK_SHOW_BG);
+        let mut lengths = vec![];
+        for _ in 0..4 {
+            let start = ppu.dots;
+            run_frame(&mut ppu);
+            lengths.push(ppu.dots - start);
+        }
+        lengths.sort();
+        assert_eq!(lengths, vec![89341, 89341, 89342, 89342]);
+    }
+
+    #[test]
+    fn test_status_read_suppresses_vblank() {
+        let mut ppu = PPU::new();
+        ppu.cpu_write_u8(0x0, CTRL_NMI_ENABLE);
+        run_until(&mut ppu, VBLANK_SCANLINE, 1);
+        assert_eq!(ppu.cpu_read_u8(0x2, false) & STATUS_VBLANK, 0);
+        ppu.clock();
+        assert_eq!(ppu.status & STATUS_VBLANK, 0);
+        assert!(!ppu.nmi_asserted());
+
+        // a frame later everything is back to normal
+        run_frame(&mut ppu);
+        assert!(ppu.nmi_asserted());
+    }
+
+    #[test]
+    fn test_background_rendering() {
+        let mut ppu = ppu_with_background();
+        ppu.cpu_write_u8(0x1, MASK_SHOW_BG | MASK_SHOW_BG_LEFT);
+        // the first frame misses the prefetch of the pre-render line
+        run_frame(&mut ppu);
+        run_frame(&mut ppu);
+
+        assert_eq!(pixel(&ppu, 0, 0), 0x01);
+        assert_eq!(pixel(&ppu, 7, 0), 0x01);
+        assert_eq!(pixel(&ppu, 8, 0), 0x02);
+        assert_eq!(pixel(&ppu, 16, 0), 0x05);
+        assert_eq!(pixel(&ppu, 31, 15), 0x06);
+        assert_eq!(pixel(&ppu, 16, 16), 0x01);
+        assert_eq!(pixel(&ppu, 255, 239), 0x02);
+
+        // hiding the background leaves the backdrop color
+        ppu.cpu_write_u8(0x1, MASK_SHOW_SPRITES);
+        run_frame(&mut ppu);
+        assert!(ppu.framebuffer().iter().all(|&color| color == 0x0F));
+    }
+
+    #[test]
+    fn test_scrolling() {
+        let mut ppu = ppu_with_background();
+        ppu.cpu_write_u8(0x5, 3);
+        ppu.cpu_write_u8(0x5, 12);
+        ppu.cpu_write_u8(0x1, MASK_SHOW_BG | MASK_SHOW_BG_LEFT);
+        run_frame(&mut ppu);
+        run_frame(&mut ppu);
+
+        // fine X
+        assert_eq!(pixel(&ppu, 4, 0), 0x01);
+        assert_eq!(pixel(&ppu, 5, 0), 0x02);
+        // fine Y, line 4 is the start of the third tile row
+        assert_eq!(pixel(&ppu, 13, 3), 0x05);
+        assert_eq!(pixel(&ppu, 13, 4), 0x01);
+        // past the nametable, the horizontally adjacent one is mirrored over the same data
+        assert_eq!(pixel(&ppu, 253, 100), 0x01);
+        assert_eq!(pixel(&ppu, 255, 100), 0x01);
+    }
+
+    #[test]
+    fn test_mid_frame_scroll_split() {
+        let mut ppu = ppu_with_background();
+        ppu.cpu_write_u8(0x1, MASK_SHOW_BG | MASK_SHOW_BG_LEFT);
+        run_frame(&mut ppu);
+
+        // scrolling 8 pixels right while line 100 is drawn, v picks it up at dot 257 in time for
+        // the prefetch of the next line
+        run_until(&mut ppu, 100, 200);
+        ppu.cpu_read_u8(0x2, false);
+        ppu.cpu_write_u8(0x5, 8);
+        ppu.cpu_write_u8(0x5, 0);
+        run_until(&mut ppu, VBLANK_SCANLINE, 2);
+
+        assert_eq!(pixel(&ppu, 0, 100), 0x01);
+        assert_eq!(pixel(&ppu, 0, 101), 0x02);
+        assert_eq!(pixel(&ppu, 8, 101), 0x01);
+        assert_eq!(pixel(&ppu, 0, 239), 0x02);
+    }
+
+    #[test]
+    fn test_left_column_and_emphasis() {
+        let mut ppu = ppu_with_background();
+        ppu.cpu_write_u8(0x1, MASK_SHOW_BG | 0xA0);
+        run_frame(&mut ppu);
+        run_frame(&mut ppu);
+
+        assert_eq!(pixel(&ppu, 7, 50), 0x0F | 0x140);
+        assert_eq!(pixel(&ppu, 8, 50), 0x02 | 0x140);
+
+        // with rendering off, v pointing at the palette picks the backdrop
+        ppu.cpu_write_u8(0x1, 0x00);
+        set_vram_address(&mut ppu, 0x3F06);
+        run_frame(&mut ppu);
+        assert_eq!(pixel(&ppu, 100, 100), 0x06);
+    }
+
+    #[test]
+    fn test_ppudata_access_while_rendering() {
+        let mut ppu = ppu_with_background();
+        ppu.cpu_write_u8(0x1, MASK_SHOW_BG);
+        run_until(&mut ppu, 20, 100);
+        let v = ppu.v;
+        ppu.cpu_read_u8(0x7, false);
+        // coarse X and Y both move instead of the regular increment
+        assert_eq!(ppu.v & 0x1F, (v & 0x1F) + 1);
+        assert_eq!(ppu.v & 0x7000, (v & 0x7000) + 0x1000);
+    }
+
+    /// Background of ppu_with_background with a few sprite tiles on top: tile 3 is opaque
+    /// (color 3), tile 4 only has its leftmost column set and tile 5 its top row (color 1).
+    /// Every sprite starts off screen.
+    fn ppu_with_sprites() -> PPU {
+        let mut ppu = ppu_with_background();
+        for row in 0..8 {
+            ppu.ppu_write_u8(0x0030 + row, 0xFF);
+            ppu.ppu_write_u8(0x0038 + row, 0xFF);
+            ppu.ppu_write_u8(0x0040 + row, 0x80);
+        }
+        ppu.ppu_write_u8(0x0050, 0xFF);
+        for (addr, color) in [(0x3F11, 0x11), (0x3F12, 0x12), (0x3F13, 0x13), (0x3F15, 0x15), (0x3F17, 0x17)] {
+            ppu.ppu_write_u8(addr, color);
+        }
+        ppu.oam = [0xFF; 256];
+        ppu
+    }
+
+    fn set_sprite(ppu: &mut PPU, index: usize, y: u8, tile: u8, attribute: u8, x: u8) {
+        ppu.oam[index * 4..index * 4 + 4].copy_from_slice(&[y, tile, attribute, x]);
+    }
+
+    #[test]
+    fn test_sprite_rendering() {
+        let mut ppu = ppu_with_sprites();
+        // OAM holds the Y coordinate minus one
+        set_sprite(&mut ppu, 0, 49, 3, 0x00, 20);
+        set_sprite(&mut ppu, 1, 99, 4, 0x40 | 0x01, 100);
+        set_sprite(&mut ppu, 2, 99, 5, 0x80, 200);
+        ppu.cpu_write_u8(0x1, MASK_SHOW_SPRITES | MASK_SHOW_SPRITES_LEFT);
+        run_frame(&mut ppu);
+
+        assert_eq!(pixel(&ppu, 20, 50), 0x13);
+        assert_eq!(pixel(&ppu, 27, 57), 0x13);
+        for (x, y) in [(19, 50), (28, 50), (20, 49), (20, 58)] {
+            assert_eq!(pixel(&ppu, x, y), 0x0F);
+        }
+
+        // flipped horizontally, with the second palette
+        assert_eq!(pixel(&ppu, 100, 100), 0x0F);
+        assert_eq!(pixel(&ppu, 107, 100), 0x15);
+
+        // flipped vertically
+        assert_eq!(pixel(&ppu, 200, 100), 0x0F);
+        assert_eq!(pixel(&ppu, 200, 107), 0x11);
+
+        // sprites hidden in the left column
+        set_sprite(&mut ppu, 0, 49, 3, 0x00, 4);
+        ppu.cpu_write_u8(0x1, MASK_SHOW_SPRITES);
+        run_frame(&mut ppu);
+        assert_eq!(pixel(&ppu, 7, 50), 0x0F);
+        assert_eq!(pixel(&ppu, 8, 50), 0x13);
+    }
+
+    #[test]
+    fn test_8x16_sprites() {
+        let mut ppu = ppu_with_sprites();
+        // tiles 6 and 7 of the second pattern table, color 1 on top and 2 at the bottom
+        for row in 0..8 {
+            ppu.ppu_write_u8(0x1060 + row, 0xFF);
+            ppu.ppu_write_u8(0x1078 + row, 0xFF);
+        }
+        set_sprite(&mut ppu, 0, 9, 0x07, 0x00, 0);
+        set_sprite(&mut ppu, 1, 9, 0x07, 0x80, 8);
+        ppu.cpu_write_u8(0x0, CTRL_SPRITE_SIZE_16);
+        ppu.cpu_write_u8(0x1, MASK_SHOW_SPRITES | MASK_SHOW_SPRITES_LEFT);
+        run_frame(&mut ppu);
+
+        assert_eq!(pixel(&ppu, 0, 10), 0x11);
+        assert_eq!(pixel(&ppu, 0, 17), 0x11);
+        assert_eq!(pixel(&ppu, 0, 18), 0x12);
+        assert_eq!(pixel(&ppu, 0, 25), 0x12);
+        assert_eq!(pixel(&ppu, 0, 26), 0x0F);
+
+        // flipping swaps both halves
+        assert_eq!(pixel(&ppu, 8, 10), 0x12);
+        assert_eq!(pixel(&ppu, 8, 25), 0x11);
+    }
+
+    #[test]
+    fn test_sprite_priority() {
+        let mut ppu = ppu_with_sprites();
+        // a transparent background tile at the top left corner
+        ppu.ppu_write_u8(0x2000, 0);
+        set_sprite(&mut ppu, 0, 0, 3, 0x20, 0);
+        set_sprite(&mut ppu, 1, 0, 3, 0x00, 16);
+        set_sprite(&mut ppu, 2, 0, 3, 0x20, 32);
+        // sprites behind the first opaque one don't get a say, even when it's behind the
+        // background and they're not
+        set_sprite(&mut ppu, 3, 0, 3, 0x00, 32);
+        ppu.cpu_write_u8(0x1, MASK_SHOW_BG | MASK_SHOW_BG_LEFT | MASK_SHOW_SPRITES | MASK_SHOW_SPRITES_LEFT);
+        run_frame(&mut ppu);
+        run_frame(&mut ppu);
+
+        // behind the background, but the background is transparent there
+        assert_eq!(pixel(&ppu, 0, 1), 0x13);
+        assert_eq!(pixel(&ppu, 16, 1), 0x13);
+        assert_eq!(pixel(&ppu, 32, 1), 0x01);
+        assert_eq!(pixel(&ppu, 40, 1), 0x02);
+    }
+
+    #[test]
+    fn test_sprite_zero_hit() {
+        let mut ppu = ppu_with_sprites();
+        set_sprite(&mut ppu, 0, 29, 3, 0x20, 40);
+        ppu.cpu_write_u8(0x1, MASK_SHOW_BG | MASK_SHOW_BG_LEFT | MASK_SHOW_SPRITES | MASK_SHOW_SPRITES_LEFT);
+        run_frame(&mut ppu);
+
+        // the first pixel of the sprite is drawn at dot 41 of scanline 30, priority doesn't matter
+        run_until(&mut ppu, 30, 41);
+        assert_eq!(ppu.status & STATUS_SPRITE_0_HIT, 0);
+        ppu.clock();
+        assert_eq!(ppu.status & STATUS_SPRITE_0_HIT, STATUS_SPRITE_0_HIT);
+
+        // and it lasts until the pre-render scanline
+        run_until(&mut ppu, PRE_RENDER_SCANLINE, 1);
+        assert_eq!(ppu.status & STATUS_SPRITE_0_HIT, STATUS_SPRITE_0_HIT);
+        ppu.clock();
+        assert_eq!(ppu.status & STATUS_SPRITE_0_HIT, 0);
+    }
+
+    #[test]
+    fn test_sprite_zero_hit_edge_cases() {
+        let hit = |ppu: &mut PPU| {
+            run_frame(ppu);
+            run_frame(ppu);
+            ppu.status & STATUS_SPRITE_0_HIT != 0
+        };
+        let mut ppu = ppu_with_sprites();
+        ppu.cpu_write_u8(0x1, MASK_SHOW_BG | MASK_SHOW_BG_LEFT | MASK_SHOW_SPRITES | MASK_SHOW_SPRITES_LEFT);
+
+        // only other sprites overlapping the background
+        set_sprite(&mut ppu, 1, 29, 3, 0x00, 40);
+        assert!(!hit(&mut ppu));
+
+        // never on the last column
+        set_sprite(&mut ppu, 0, 29, 3, 0x00, 255);
+        assert!(!hit(&mut ppu));
+        set_sprite(&mut ppu, 0, 29, 3, 0x00, 254);
+        assert!(hit(&mut ppu));
+
+        // over a transparent background
+        ppu.ppu_write_u8(0x2000 + 4 * 32 + 31, 0);
+        set_sprite(&mut ppu, 0, 31, 3, 0x00, 248);
+        assert!(!hit(&mut ppu));
+
+        // left column, clipping either layer prevents it
+        set_sprite(&mut ppu, 0, 29, 3, 0x00, 0);
+        assert!(hit(&mut ppu));
+        ppu.cpu_write_u8(0x1, MASK_SHOW_BG | MASK_SHOW_SPRITES | MASK_SHOW_SPRITES_LEFT);
+        assert!(!hit(&mut ppu));
+        ppu.cpu_write_u8(0x1, MASK_SHOW_BG | MASK_SHOW_BG_LEFT | MASK_SHOW_SPRITES);
+        assert!(!hit(&mut ppu));
+
+        // or hiding a layer altogether
+        ppu.cpu_write_u8(0x1, MASK_SHOW_SPRITES | MASK_SHOW_SPRITES_LEFT);
+        assert!(!hit(&mut ppu));
+    }
+
+    #[test]
+    fn test_sprite_limit_and_overflow() {
+        let mut ppu = ppu_with_sprites();
+        for index in 0..9 {
+            set_sprite(&mut ppu, index, 79, 3, 0x00, index as u8 * 16);
+        }
+        ppu.cpu_write_u8(0x1, MASK_SHOW_SPRITES | MASK_SHOW_SPRITES_LEFT);
+        run_frame(&mut ppu);
+        assert_eq!(pixel(&ppu, 7 * 16, 80), 0x13);
+        assert_eq!(pixel(&ppu, 8 * 16, 80), 0x0F);
+
+        // the flag goes up as the 9th sprite is found, during scanline 79
+        run_until(&mut ppu, 79, 1);
+        assert_eq!(ppu.status & STATUS_SPRITE_OVERFLOW, 0);
+        run_until(&mut ppu, 80, 0);
+        assert_eq!(ppu.status & STATUS_SPRITE_OVERFLOW, STATUS_SPRITE_OVERFLOW);
+
+        ppu.set_sprite_limit(false);
+        run_frame(&mut ppu);
+        run_frame(&mut ppu);
+        assert_eq!(pixel(&ppu, 8 * 16, 80), 0x13);
+        assert_eq!(ppu.status & STATUS_SPRITE_OVERFLOW, STATUS_SPRITE_OVERFLOW);
+    }
+
+    #[test]
+    fn test_sprite_overflow_bug() {
+        let overflow = |ppu: &mut PPU| {
+            run_frame(ppu);
+            run_frame(ppu);
+            ppu.status & STATUS_SPRITE_OVERFLOW != 0
+        };
+        let mut ppu = ppu_with_sprites();
+        ppu.cpu_write_u8(0x1, MASK_SHOW_SPRITES);
+        for index in 0..8 {
+            set_sprite(&mut ppu, index, 79, 3, 0x00, 0);
+        }
+        assert!(!overflow(&mut ppu));
+
+        // after the 8th sprite, the next one's Y is checked, then the tile number of the one
+        // after, then the attributes of the following one and so on
+        set_sprite(&mut ppu, 9, 200, 80, 0x00, 0);
+        assert!(overflow(&mut ppu));
+
+        // which misses an actual 9th sprite
+        set_sprite(&mut ppu, 9, 80, 0, 0x00, 0);
+        assert!(!overflow(&mut ppu));
+        set_sprite(&mut ppu, 10, 200, 0, 78, 0);
+        assert!(overflow(&mut ppu));
+    }
+
+    #[test]
+    fn test_no_sprites_on_first_scanline() {
+        let mut ppu = ppu_with_sprites();
+        set_sprite(&mut ppu, 0, 0xFF, 3, 0x00, 0);
+        set_sprite(&mut ppu, 1, 0, 3, 0x00, 8);
+        ppu.cpu_write_u8(0x1, MASK_SHOW_SPRITES | MASK_SHOW_SPRITES_LEFT);
+        run_frame(&mut ppu);
+        run_frame(&mut ppu);
+
+        assert_eq!(pixel(&ppu, 0, 0), 0x0F);
+        assert_eq!(pixel(&ppu, 8, 0), 0x0F);
+        assert_eq!(pixel(&ppu, 8, 1), 0x13);
+    }
+
+    #[test]
+    fn test_oam_addr_cleared_while_fetching_sprites() {
+        let mut ppu = ppu_with_sprites();
+        ppu.cpu_write_u8(0x1, MASK_SHOW_SPRITES);
+        run_until(&mut ppu, 10, 200);
+        ppu.cpu_write_u8(0x3, 0x42);
+        run_until(&mut ppu, 10, 257);
+        assert_eq!(ppu.oam_addr, 0x42);
+        ppu.clock();
+        assert_eq!(ppu.oam_addr, 0);
+    }
+}
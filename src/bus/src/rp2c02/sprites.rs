@@ -0,0 +1,139 @@
+/// Sprite half of the rendering pipeline.
+///
+/// While a scanline is drawn the PPU looks through primary OAM for the sprites that will be on
+/// the next one and copies up to 8 of them into secondary OAM. Their patterns are then fetched
+/// during dots 257-320 into the output units, which get mixed with the background as the next
+/// scanline is drawn.
+pub(super) struct Sprites {
+    pub(super) secondary_oam: [u8; 32],
+    // sprites found past the 8th one, only kept when the limit is disabled
+    pub(super) extra: Vec<[u8; 4]>,
+    // number of sprites in secondary OAM
+    pub(super) found: usize,
+    // pattern fetches take 2 dots per plane, the low one waits here for the high one
+    pub(super) next_pattern_lo: u8,
+    // whether the first sprite in secondary OAM is sprite 0, for the next scanline
+    sprite_zero_found: bool,
+    units: Vec<Unit>,
+    // whether the first output unit holds sprite 0, for the current scanline
+    sprite_zero_loaded: bool,
+}
+
+/// One sprite ready to be drawn on the current scanline
+#[derive(Clone, Copy)]
+pub(super) struct Unit {
+    // pattern bits, already flipped so the leftmost pixel is bit 7
+    pub(super) pattern_lo: u8,
+    pub(super) pattern_hi: u8,
+    pub(super) attribute: u8,
+    pub(super) x: u8,
+}
+
+pub(super) struct SpritePixel {
+    pub(super) pixel: u8,
+    pub(super) palette: u8,
+    pub(super) behind_background: bool,
+    pub(super) sprite_zero: bool,
+}
+
+const ATTRIBUTE_PALETTE: u8 = 0x03;
+const ATTRIBUTE_BEHIND_BACKGROUND: u8 = 0x20;
+pub(super) const ATTRIBUTE_FLIP_HORIZONTAL: u8 = 0x40;
+pub(super) const ATTRIBUTE_FLIP_VERTICAL: u8 = 0x80;
+
+impl Default for Sprites {
+    fn default() -> Self {
+        Sprites {
+            secondary_oam: [0xFF; 32],
+            extra: vec![],
+            found: 0,
+            next_pattern_lo: 0,
+            sprite_zero_found: false,
+            units: Vec::with_capacity(8),
+            sprite_zero_loaded: false,
+        }
+    }
+}
+
+impl Sprites {
+    pub(super) fn clear_secondary_oam(&mut self) {
+        self.secondary_oam = [0xFF; 32];
+    }
+
+    /// Forgets whatever was found, the pre-render scanline doesn't evaluate sprites so
+    /// nothing is ever drawn on scanline 0
+    pub(super) fn clear_found(&mut self) {
+        self.found = 0;
+        self.extra.clear();
+        self.sprite_zero_found = false;
+    }
+
+    /// Looks for the sprites covering `scanline` (drawn on the following one since OAM holds
+    /// Y minus 1) and returns whether the sprite overflow flag gets set.
+    ///
+    /// Once 8 sprites are found the hardware keeps looking for a 9th one to set the overflow
+    /// flag, but it wrongly increments the byte index along with the sprite index, so it ends
+    /// up treating tile numbers, attributes or X positions as Y coordinates. That gives both
+    /// false positives and false negatives, which is what games see. With `limit` off the
+    /// extra sprites are still drawn, the flag stays just as broken.
+    pub(super) fn evaluate(&mut self, oam: &[u8; 256], scanline: u16, height: u16, limit: bool) -> bool {
+        let in_range = |y: u8| scanline.wrapping_sub(y as u16) < height;
+        self.clear_found();
+
+        let mut n = 0;
+        while n < 64 && self.found < 8 {
+            let sprite = &oam[n * 4..n * 4 + 4];
+            // the Y coordinate is copied whether it's in range or not
+            self.secondary_oam[self.found * 4] = sprite[0];
+            if in_range(sprite[0]) {
+                self.secondary_oam[self.found * 4..self.found * 4 + 4].copy_from_slice(sprite);
+                self.sprite_zero_found |= n == 0;
+                self.found += 1;
+            }
+            n += 1;
+        }
+
+        if !limit {
+            for sprite in oam[n * 4..].chunks_exact(4) {
+                if in_range(sprite[0]) {
+                    self.extra.push([sprite[0], sprite[1], sprite[2], sprite[3]]);
+                }
+            }
+        }
+
+        let mut m = 0;
+        while n < 64 {
+            if in_range(oam[n * 4 + m]) {
+                return true;
+            }
+            n += 1;
+            m = (m + 1) & 0x3;
+        }
+        false
+    }
+
+    /// Sprites being fetched during dots 257-320 replace the ones of the line just drawn
+    pub(super) fn start_loading(&mut self) {
+        self.units.clear();
+        self.sprite_zero_loaded = self.sprite_zero_found;
+    }
+
+    pub(super) fn load(&mut self, unit: Unit) {
+        self.units.push(unit);
+    }
+
+    /// First opaque sprite pixel at `x`, sprites earlier in OAM being in front
+    pub(super) fn pixel(&self, x: u8) -> Option<SpritePixel> {
+        self.units.iter().enumerate().find_map(|(index, unit)| {
+            let offset = x.checked_sub(unit.x).filter(|&offset| offset < 8)?;
+            let bit = |pattern: u8| (pattern << offset) >> 7;
+            let pixel = bit(unit.pattern_hi) << 1 | bit(unit.pattern_lo);
+            (pixel != 0).then_some(SpritePixel {
+                pixel,
+                palette: unit.attribute & ATTRIBUTE_PALETTE,
+                behind_background: unit.attribute & ATTRIBUTE_BEHIND_BACKGROUND != 0,
+                sprite_zero: index == 0 && self.sprite_zero_loaded,
+            })
+        })
+    }
+}
@@ -1,4 +1,5 @@
 use gtk4::gdk::Display;
+use gtk4::glib;
 use gtk4::glib::clone;
 use gtk4::prelude::*;
 use gtk4::{
@@ -12,15 +13,29 @@ use ui::textview::rom_disassembly::manes_rom_disassembly_textview;
 use ui::textview::cpu_registers::{cpu_register_curr_state, manes_cpu_regs_textview};
 use ui::textview::mem_view::manes_mem_view_textview;
 use ui::button::load_rom::{manes_load_rom_button, load_rom_button_events_setup};
-use ui::globals::{manes_app, manes_bus, manes_cpu};
+use ui::globals::{manes_app, manes_bus};
 use ui::window::{manes_main_ui, DEFAULT_WINDOW_WIDTH};
 
+// how often battery-backed RAM gets written to the .sav file while playing
+const BATTERY_FLUSH_INTERVAL_SECS: u32 = 10;
+
 fn main() {
     manes_app().connect_activate(|_| load_css());
     manes_app().connect_activate(build_ui);
+    manes_app().connect_shutdown(|_| flush_battery_ram());
+    glib::timeout_add_seconds_local(BATTERY_FLUSH_INTERVAL_SECS, || {
+        flush_battery_ram();
+        glib::Continue(true)
+    });
     manes_app().run();
 }
 
+fn flush_battery_ram() {
+    if let Err(err) = manes_bus().as_ref().borrow_mut().flush_battery_ram() {
+        println!("Failed to write the save file: {}", err);
+    }
+}
+
 fn load_css() {
     // Load the CSS file and add it to the provider
     let provider = CssProvider::new();
@@ -89,9 +104,9 @@ fn build_top_bar(window: &ApplicationWindow) -> Box {
         move |_| {
             println!("{}", window.width());
             println!("{}", window.height());
-            // manes_cpu()
+            // manes_bus()
             //     .as_ref()
-            //     .borrow_mut().stack_push(0x10, manes_bus().as_ref().borrow_mut().deref_mut());
+            //     .borrow_mut().reset();
 
             manes_cpu_regs_textview()
                 .as_ref()
@@ -1,6 +1,6 @@
 use gtk4::prelude::*;
 use gtk4::glib::clone;
-use gtk4::{ApplicationWindow, Button, FileChooserDialog, FileChooserAction, ResponseType, TextBuffer};
+use gtk4::{ApplicationWindow, Button, ButtonsType, DialogFlags, FileChooserDialog, FileChooserAction, MessageDialog, MessageType, ResponseType, TextBuffer};
 use std::rc::Rc;
 use crate::{manes_bus};
 use crate::ui::textview::rom_disassembly::{rom_disassembly_curr_state,manes_rom_disassembly_textview};
@@ -45,10 +45,23 @@ pub fn load_rom_button_events_setup(window: &ApplicationWindow) {
 
                             println!("filename: {}", filename);
 
-                            manes_bus()
+                            if let Err(err) = manes_bus()
                                 .as_ref()
                                 .borrow_mut()
-                                .load_cartridge(filename.as_str());
+                                .load_cartridge(filename.as_str()) {
+                                println!("Failed to load ROM: {}", err);
+                                let error_dialog = MessageDialog::new(
+                                    dialog.transient_for().as_ref(),
+                                    DialogFlags::MODAL,
+                                    MessageType::Error,
+                                    ButtonsType::Close,
+                                    &format!("Failed to load ROM: {}", err)
+                                );
+                                error_dialog.connect_response(|error_dialog, _| error_dialog.close());
+                                error_dialog.show();
+                                dialog.close();
+                                return;
+                            }
 
                             println!("Disassembling");
                             manes_rom_disassembly_textview()
@@ -35,9 +35,11 @@ pub fn rom_disassembly_curr_state() -> String {
     let mut content = String::new();
     content.push_str("[ROM Disassembly]\n\n");
 
-    //TODO replace it when Mapper logic is implemented
-    // let rom_mem = bus.read_u8_slice(ROM_START_ADDR, 0xffff);
-    let rom_mem = bus.cpu_read_u8_slice(0x0, 0x1fff);
-    content.push_str(disassemble_program(rom_mem, ROM_START_ADDR, true).as_str());
+    if !bus.cartridge().is_loaded() {
+        return content;
+    }
+
+    let rom_mem: Vec<u8> = (ROM_START_ADDR..=0xffff).map(|addr| bus.cpu_peek_u8(addr)).collect();
+    content.push_str(disassemble_program(&rom_mem, ROM_START_ADDR, true).as_str());
     content
 }
@@ -7,22 +7,12 @@ pub fn disassemble_program(machine_code: &[u8], base_address: u16, verbose: bool
 
     let mut content = String::new();
     let mut i: u16 = 0;
-    loop {
+    while (i as usize) < machine_code.len() {
         let inst = OPTABLE[machine_code[i as usize] as usize];
 
-        // handle invalid/unofficial opcodes
-        if inst.name == "IVL" {
-            if verbose {
-                content.push_str(format!(
-                    "{:04X}: {:02X} {:<8}",
-                    base_address + i,
-                    inst.opcode,
-                    ""
-                ).as_str());
-            }
-            content.push_str("??? \n");
-            i += 1;
-            continue;
+        // the last bytes may not be a whole instruction (think of ROM banks ending in vectors)
+        if i as usize + inst.bytes as usize > machine_code.len() {
+            break;
         }
 
         // verbose mode adds absolute position + machine code (useful for GUI)
@@ -50,12 +40,9 @@ pub fn disassemble_program(machine_code: &[u8], base_address: u16, verbose: bool
         content.push_str(inst.name);
         content.push(' ');
 
-        parse_arguments(&inst, &machine_code, &i, base_address, &mut content);
+        parse_arguments(&inst, machine_code, &i, base_address, &mut content);
 
         i += inst.bytes as u16;
-        if i as usize == machine_code.len() {
-            break;
-        }
     }
 
     content
@@ -71,7 +58,7 @@ fn parse_arguments(
     match instruction.mode {
         AddressingMode::Implicit => (),
         AddressingMode::Accumulator => {
-            content.push_str("A");
+            content.push('A');
         }
         AddressingMode::Immediate => {
             content.push_str(format!("#${:02X}", parse_u8(machine_code, pos)).as_str());
@@ -115,7 +102,6 @@ fn parse_arguments(
             let rel_addr = rel_addr as u16;
             content.push_str(format!("${:04X}", rel_addr).as_str());
         }
-        _ => (),
     }
     content.push('\n');
 }
@@ -166,6 +152,14 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_program_ending_in_partial_instruction() {
+        // LDX #$02 ; INC $00?? (cut short by the end of the ROM bank)
+        let machine_code: [u8; 4] = [0xa2, 0x02, 0xee, 0x00];
+        let content = disassemble_program(&machine_code, 0xfffc, false);
+        assert_eq!(content, "LDX #$02\n");
+    }
+
     #[test]
     fn parse_program_with_relative_modes() {
         // Address  Hexdump   Disassembly
@@ -191,4 +185,25 @@ mod tests {
             "JMP $0605\nLDX #$15\nLDX #$02\nBEQ $0610\nDEX \nBEQ $0610\nDEX \nBEQ $0605\nDEX \nLDA #$10\n"
         );
     }
+
+    #[test]
+    fn parse_program_with_unofficial_opcodes() {
+        // Address  Hexdump   Disassembly
+        // -------------------------------
+        // $0600    a7 10     LAX $10
+        // $0602    87 11     SAX $11
+        // $0604    c7 12     DCP $12
+        // $0606    1c 00 02  NOP $0200,x
+        // $0609    4b 0f     ALR #$0F
+        // $060b    02        JAM
+
+        let machine_code: [u8; 12] = [
+            0xa7, 0x10, 0x87, 0x11, 0xc7, 0x12, 0x1c, 0x00, 0x02, 0x4b, 0x0f, 0x02,
+        ];
+        let content = disassemble_program(&machine_code, 0x0600, false);
+        assert_eq!(
+            content,
+            "LAX $10\nSAX $11\nDCP $12\nNOP $0200,x\nALR #$0F\nJAM \n"
+        );
+    }
 }